@@ -0,0 +1,555 @@
+//! 命名环境配置（base、dev、staging、prod 等）
+//!
+//! 每个配置定义一组变量与 PATH 的前置、追加项，并可通过 `extends` 继承另一个配置。
+//! 构建进程环境的顺序：系统环境 → 各模块的内置路径 → 继承链自根向下逐层应用 → 请求中的自定义变量。
+//!
+//! 每一层中，变量值里的 `%VAR%` 按应用该层之前的环境展开，PATH 项按应用该层变量之后的环境展开，
+//! 因此同一层中可以写 `JAVA_HOME` 与 `%JAVA_HOME%\bin`。变量名不区分大小写，未定义的变量保持原样；
+//! `${secret:NAME}` 引用不展开，在执行时由密钥库解析。
+
+use crate::secrets::{self, Redactor, SecretError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+/// PATH 分隔符
+pub const PATH_SEP: char = if cfg!(windows) { ';' } else { ':' };
+
+/// 未保存任何配置时提供的默认配置
+const DEFAULT_PROFILES: &[&str] = &["base", "dev", "staging", "prod"];
+const ROOT_PROFILE: &str = "base";
+
+/// 配置文件读写锁
+static PROFILE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("环境配置名称无效: '{0}'（仅允许字母、数字、'-'、'_'、'.'）")]
+    InvalidName(String),
+    #[error("环境配置 '{0}' 不存在")]
+    NotFound(String),
+    #[error("环境配置继承存在循环: {0}")]
+    Cycle(String),
+    #[error("环境配置 '{name}' 被以下配置继承，无法删除: {dependents}")]
+    InUse { name: String, dependents: String },
+    #[error("读写环境配置失败: {0}")]
+    Io(String),
+    #[error("环境配置格式错误: {0}")]
+    Parse(String),
+    #[error("无法定位应用数据目录: {0}")]
+    DataDirUnavailable(String),
+}
+
+/// 单个环境配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvProfile {
+    /// 继承的配置名称
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 设置的变量（覆盖已有的同名变量）
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    /// 插入到 PATH 最前面的目录，按列出顺序
+    #[serde(default)]
+    pub path_prepend: Vec<String>,
+    /// 追加到 PATH 末尾的目录
+    #[serde(default)]
+    pub path_append: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, EnvProfile>,
+}
+
+/// 继承链展开后的配置，可直接应用到环境变量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvOverlay {
+    pub name: String,
+    /// 从根配置到当前配置的名称
+    pub chain: Vec<String>,
+    layers: Vec<EnvProfile>,
+}
+
+/// 环境配置存储
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: BTreeMap<String, EnvProfile>,
+}
+
+fn validate_name(name: &str) -> Result<(), ProfileError> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        Ok(())
+    } else {
+        Err(ProfileError::InvalidName(name.to_string()))
+    }
+}
+
+fn default_profiles() -> BTreeMap<String, EnvProfile> {
+    DEFAULT_PROFILES
+        .iter()
+        .map(|name| {
+            let profile = EnvProfile {
+                extends: (*name != ROOT_PROFILE).then(|| ROOT_PROFILE.to_string()),
+                ..Default::default()
+            };
+            (name.to_string(), profile)
+        })
+        .collect()
+}
+
+impl ProfileStore {
+    /// 从文件加载，文件不存在时使用默认配置
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, ProfileError> {
+        let path = path.into();
+        let profiles = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str::<ProfileFile>(&text)
+                    .map_err(|e| ProfileError::Parse(e.to_string()))?
+                    .profiles
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => default_profiles(),
+            Err(e) => return Err(ProfileError::Io(e.to_string())),
+        };
+        Ok(Self { path, profiles })
+    }
+
+    /// 写回文件（先写临时文件再重命名）
+    pub fn save(&self) -> Result<(), ProfileError> {
+        let io_err = |e: std::io::Error| ProfileError::Io(e.to_string());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let file = ProfileFile {
+            profiles: self.profiles.clone(),
+        };
+        let text =
+            serde_json::to_string_pretty(&file).map_err(|e| ProfileError::Parse(e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, text).map_err(io_err)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err)
+    }
+
+    pub fn profiles(&self) -> &BTreeMap<String, EnvProfile> {
+        &self.profiles
+    }
+
+    /// 新增或替换配置；继承的配置必须存在且不能形成循环
+    pub fn put(&mut self, name: &str, profile: EnvProfile) -> Result<(), ProfileError> {
+        validate_name(name)?;
+        let previous = self.profiles.insert(name.to_string(), profile);
+        if let Err(e) = self.overlay(name) {
+            match previous {
+                Some(p) => self.profiles.insert(name.to_string(), p),
+                None => self.profiles.remove(name),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// 删除配置，返回是否存在；仍被继承时拒绝删除
+    pub fn remove(&mut self, name: &str) -> Result<bool, ProfileError> {
+        let dependents: Vec<&str> = self
+            .profiles
+            .iter()
+            .filter(|(_, p)| p.extends.as_deref() == Some(name))
+            .map(|(n, _)| n.as_str())
+            .collect();
+        if !dependents.is_empty() {
+            return Err(ProfileError::InUse {
+                name: name.to_string(),
+                dependents: dependents.join(", "),
+            });
+        }
+        Ok(self.profiles.remove(name).is_some())
+    }
+
+    /// 展开继承链
+    pub fn overlay(&self, name: &str) -> Result<EnvOverlay, ProfileError> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut current = Some(name.to_string());
+        while let Some(n) = current {
+            if chain.contains(&n) {
+                chain.push(n);
+                chain.reverse();
+                return Err(ProfileError::Cycle(chain.join(" -> ")));
+            }
+            let profile = self
+                .profiles
+                .get(&n)
+                .ok_or_else(|| ProfileError::NotFound(n.clone()))?;
+            current = profile.extends.clone();
+            chain.push(n);
+        }
+        chain.reverse();
+        let layers = chain.iter().map(|n| self.profiles[n].clone()).collect();
+        Ok(EnvOverlay {
+            name: name.to_string(),
+            chain,
+            layers,
+        })
+    }
+}
+
+impl EnvOverlay {
+    /// 依次应用继承链上的每一层
+    pub fn apply(&self, env: &mut HashMap<String, String>) {
+        for layer in &self.layers {
+            let before = env.clone();
+            for (key, value) in &layer.vars {
+                set_var(env, key, expand(value, &before));
+            }
+            let expand_all = |items: &[String], env: &HashMap<String, String>| -> Vec<String> {
+                items.iter().map(|p| expand(p, env)).collect()
+            };
+            let prepend = expand_all(&layer.path_prepend, env);
+            let append = expand_all(&layer.path_append, env);
+            prepend_path(env, &prepend);
+            append_path(env, &append);
+        }
+    }
+
+    /// 在执行前解析各层变量中的密钥引用，返回替换后的配置与脱敏器
+    pub fn resolve_secrets(&self, app: &AppHandle) -> Result<(EnvOverlay, Redactor), SecretError> {
+        let mut redactor = Redactor::default();
+        let mut resolved = self.clone();
+        for layer in &mut resolved.layers {
+            let vars: HashMap<String, String> = layer.vars.clone().into_iter().collect();
+            let env = secrets::resolve_for_app(app, Some(&vars), None)?;
+            redactor.merge(&env.redactor);
+            layer.vars = env.vars.into_iter().collect();
+        }
+        Ok((resolved, redactor))
+    }
+}
+
+/// 按名称查找（不区分大小写），返回实际使用的键
+fn find_key(env: &HashMap<String, String>, name: &str) -> Option<String> {
+    if env.contains_key(name) {
+        return Some(name.to_string());
+    }
+    env.keys().find(|k| k.eq_ignore_ascii_case(name)).cloned()
+}
+
+/// 读取变量（不区分大小写）
+pub fn get_var<'a>(env: &'a HashMap<String, String>, name: &str) -> Option<&'a String> {
+    find_key(env, name).and_then(|k| env.get(&k))
+}
+
+/// 设置变量，沿用已有同名变量的键（Windows 中 PATH 常为 `Path`）
+pub fn set_var(env: &mut HashMap<String, String>, name: &str, value: String) {
+    let key = find_key(env, name).unwrap_or_else(|| name.to_string());
+    env.insert(key, value);
+}
+
+/// 展开 `%VAR%`；未定义的变量与单独的 `%` 保持原样
+pub fn expand(value: &str, env: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if end > 0 => {
+                let name = &after[..end];
+                match get_var(env, name) {
+                    Some(v) => out.push_str(v),
+                    None => {
+                        out.push('%');
+                        out.push_str(name);
+                        out.push('%');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('%');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// PATH 中的各项
+pub fn path_entries(env: &HashMap<String, String>) -> Vec<String> {
+    get_var(env, "PATH")
+        .map(|p| {
+            p.split(PATH_SEP)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    let trim = |s: &str| s.trim_end_matches(['\\', '/']).to_string();
+    if cfg!(windows) {
+        trim(a).eq_ignore_ascii_case(&trim(b))
+    } else {
+        trim(a) == trim(b)
+    }
+}
+
+fn write_path(env: &mut HashMap<String, String>, entries: &[String]) {
+    set_var(env, "PATH", entries.join(&PATH_SEP.to_string()));
+}
+
+/// 将目录按顺序插入到 PATH 最前面；已存在的项移动到前面
+pub fn prepend_path(env: &mut HashMap<String, String>, dirs: &[String]) {
+    if dirs.is_empty() {
+        return;
+    }
+    let mut entries: Vec<String> = dirs.to_vec();
+    entries.extend(
+        path_entries(env)
+            .into_iter()
+            .filter(|p| !dirs.iter().any(|d| same_path(d, p))),
+    );
+    write_path(env, &entries);
+}
+
+/// 将目录追加到 PATH 末尾；已存在的项保持原位
+pub fn append_path(env: &mut HashMap<String, String>, dirs: &[String]) {
+    if dirs.is_empty() {
+        return;
+    }
+    let mut entries = path_entries(env);
+    for dir in dirs {
+        if !entries.iter().any(|p| same_path(p, dir)) {
+            entries.push(dir.clone());
+        }
+    }
+    write_path(env, &entries);
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, ProfileError> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("env_profiles.json"))
+        .map_err(|e| ProfileError::DataDirUnavailable(e.to_string()))
+}
+
+/// 加载应用的环境配置
+pub fn load_store(app: &AppHandle) -> Result<ProfileStore, ProfileError> {
+    ProfileStore::load(store_path(app)?)
+}
+
+/// 按名称加载并展开配置
+pub fn load_overlay(app: &AppHandle, name: &str) -> Result<EnvOverlay, ProfileError> {
+    load_store(app)?.overlay(name)
+}
+
+/// 修改环境配置并写回
+fn update_store<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut ProfileStore) -> Result<T, ProfileError>,
+) -> Result<T, ProfileError> {
+    let _guard = PROFILE_LOCK.lock().expect("profile lock");
+    let mut store = load_store(app)?;
+    let out = f(&mut store)?;
+    store.save()?;
+    Ok(out)
+}
+
+/// 计算有效环境的目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvTarget {
+    Scoop,
+    Winsw,
+}
+
+/// 有效环境
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveEnv {
+    pub profile: Option<String>,
+    pub chain: Vec<String>,
+    /// 完整的环境变量，敏感名称的值已脱敏，密钥引用保持原样
+    pub env: BTreeMap<String, String>,
+    /// 按顺序拆分后的 PATH
+    pub path: Vec<String>,
+}
+
+fn effective_env(target: EnvTarget, overlay: Option<&EnvOverlay>) -> EffectiveEnv {
+    let env = match target {
+        EnvTarget::Scoop => crate::scoop::api::get_enhanced_env(overlay),
+        EnvTarget::Winsw => crate::winsw::get_enhanced_env(None, overlay),
+    };
+    EffectiveEnv {
+        profile: overlay.map(|o| o.name.clone()),
+        chain: overlay.map(|o| o.chain.clone()).unwrap_or_default(),
+        path: path_entries(&env),
+        env: secrets::redact_env(&env),
+    }
+}
+
+/// Tauri 命令：列出所有环境配置
+#[tauri::command]
+pub async fn env_profile_list(app: AppHandle) -> Result<BTreeMap<String, EnvProfile>, String> {
+    load_store(&app)
+        .map(|s| s.profiles().clone())
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：新增或替换环境配置
+#[tauri::command]
+pub async fn env_profile_save(
+    app: AppHandle,
+    name: String,
+    profile: EnvProfile,
+) -> Result<EnvProfile, String> {
+    update_store(&app, |store| store.put(&name, profile.clone()))
+        .map(|_| profile)
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：删除环境配置，返回是否存在
+#[tauri::command]
+pub async fn env_profile_remove(app: AppHandle, name: String) -> Result<bool, String> {
+    update_store(&app, |store| store.remove(&name)).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：显示选中配置下 scoop 或 WinSW 进程将获得的完整环境
+///
+/// ```javascript
+/// const { env, path } = await invoke('env_profile_effective', { profile: 'prod', target: 'winsw' });
+/// ```
+#[tauri::command]
+pub async fn env_profile_effective(
+    app: AppHandle,
+    profile: Option<String>,
+    target: EnvTarget,
+) -> Result<EffectiveEnv, String> {
+    let overlay = match profile.as_deref() {
+        Some(name) => Some(load_overlay(&app, name).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    Ok(effective_env(target, overlay.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sep(items: &[&str]) -> String {
+        items.join(&PATH_SEP.to_string())
+    }
+
+    #[test]
+    fn test_expand() {
+        let env = HashMap::from([("JAVA_HOME".to_string(), "/opt/jdk".to_string())]);
+        assert_eq!(expand("%java_home%/bin", &env), "/opt/jdk/bin");
+        assert_eq!(expand("%MISSING%/x", &env), "%MISSING%/x");
+        assert_eq!(expand("100% sure", &env), "100% sure");
+        assert_eq!(expand("${secret:DB}", &env), "${secret:DB}");
+    }
+
+    #[test]
+    fn test_overlay_chain_and_apply() {
+        let path = std::env::temp_dir().join(format!("env-profiles-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut store = ProfileStore::load(&path).unwrap();
+        assert_eq!(store.profiles().len(), 4);
+
+        store
+            .put(
+                "base",
+                EnvProfile {
+                    vars: BTreeMap::from([("APP_ROOT".into(), "/srv/app".into())]),
+                    path_append: vec!["/usr/local/tools".into()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        store
+            .put(
+                "prod",
+                EnvProfile {
+                    extends: Some("base".into()),
+                    vars: BTreeMap::from([
+                        ("JAVA_HOME".into(), "%APP_ROOT%/jdk".into()),
+                        ("LOG_LEVEL".into(), "warn".into()),
+                    ]),
+                    path_prepend: vec!["%JAVA_HOME%/bin".into()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        store.save().unwrap();
+
+        let store = ProfileStore::load(&path).unwrap();
+        let overlay = store.overlay("prod").unwrap();
+        assert_eq!(overlay.chain, vec!["base", "prod"]);
+
+        let mut env = HashMap::from([
+            ("Path".to_string(), sep(&["/usr/bin", "/srv/app/jdk/bin"])),
+            ("LOG_LEVEL".to_string(), "debug".to_string()),
+        ]);
+        overlay.apply(&mut env);
+        assert_eq!(env["JAVA_HOME"], "/srv/app/jdk");
+        assert_eq!(env["LOG_LEVEL"], "warn");
+        assert!(!env.contains_key("PATH"));
+        assert_eq!(
+            env["Path"],
+            sep(&["/srv/app/jdk/bin", "/usr/bin", "/usr/local/tools"])
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_put_rejects_cycles_and_remove_in_use() {
+        let mut store = ProfileStore {
+            path: PathBuf::new(),
+            profiles: default_profiles(),
+        };
+
+        let cyclic = EnvProfile {
+            extends: Some("dev".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            store.put("base", cyclic),
+            Err(ProfileError::Cycle(_))
+        ));
+        // 失败时保留原配置
+        assert_eq!(store.profiles()["base"].extends, None);
+
+        assert!(matches!(
+            store.put(
+                "qa",
+                EnvProfile {
+                    extends: Some("missing".into()),
+                    ..Default::default()
+                }
+            ),
+            Err(ProfileError::NotFound(_))
+        ));
+        assert!(!store.profiles().contains_key("qa"));
+
+        assert!(matches!(
+            store.remove("base"),
+            Err(ProfileError::InUse { .. })
+        ));
+        assert!(store.remove("prod").unwrap());
+        assert!(matches!(
+            store.overlay("prod"),
+            Err(ProfileError::NotFound(_))
+        ));
+    }
+}
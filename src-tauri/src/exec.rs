@@ -0,0 +1,1085 @@
+//! 子进程执行层
+//!
+//! scoop 与 winsw 共用：超时或取消时终止整个进程树、内存中只保留输出首尾并将完整输出写入记录文件、
+//! 解码控制台输出并去除 ANSI 控制序列、构建子进程环境并记录起止时间，结果统一为可序列化的 [`ExecResult`]。
+
+pub mod ansi;
+mod decode;
+pub mod transcript;
+mod tree;
+
+pub use decode::decode;
+pub use transcript::{transcript_options, TranscriptOptions, TranscriptSettings};
+
+use crate::env_profile::{self, EnvOverlay};
+use crate::secrets::Redactor;
+use ansi::StyledSpan;
+use decode::LineDecoder;
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout, Duration};
+use transcript::Transcript;
+
+/// 每个输出流默认在内存中保留的开头部分（KB）
+pub const DEFAULT_HEAD_KB: usize = 64;
+
+/// 每个输出流默认在内存中保留的末尾部分（KB）
+pub const DEFAULT_TAIL_KB: usize = 64;
+
+/// 超时时附带的最近输出行数
+const TAIL_LINES: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum ExecError {
+    #[error("无法启动 {program}: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("等待进程结束失败: {0}")]
+    Wait(String),
+}
+
+/// 输出来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// 实时推送给前端的单行输出（已去除 ANSI 控制序列）
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputLine {
+    pub stream: OutputStream,
+    pub line: String,
+    /// 带样式的分段（仅在 [`OutputOptions::styled`] 开启时提供）
+    pub spans: Option<Vec<StyledSpan>>,
+}
+
+impl OutputLine {
+    /// 对行文本与各分段应用替换（用于隐藏密钥值）
+    pub fn redact(self, redact: impl Fn(&str) -> String) -> Self {
+        Self {
+            line: redact(&self.line),
+            spans: redact_spans(self.spans, &redact),
+            ..self
+        }
+    }
+}
+
+fn redact_spans(
+    spans: Option<Vec<StyledSpan>>,
+    redact: &impl Fn(&str) -> String,
+) -> Option<Vec<StyledSpan>> {
+    spans.map(|spans| {
+        spans
+            .into_iter()
+            .map(|span| StyledSpan {
+                text: redact(&span.text),
+                ..span
+            })
+            .collect()
+    })
+}
+
+/// 输出解码与捕获选项
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OutputOptions {
+    /// 输出不是 UTF-8 时使用的控制台代码页（如 936），默认为系统 OEM 代码页；
+    /// 以 BOM 开头的 UTF-16 LE 输出会被自动识别
+    pub code_page: Option<u32>,
+    /// 同时返回带样式的分段，便于界面渲染颜色
+    pub styled: Option<bool>,
+    /// 每个输出流返回的开头部分（KB），默认 [`DEFAULT_HEAD_KB`]
+    pub head_kb: Option<usize>,
+    /// 每个输出流返回的末尾部分（KB），默认 [`DEFAULT_TAIL_KB`]；中间部分只写入记录文件
+    pub tail_kb: Option<usize>,
+}
+
+/// 输出行回调
+pub type LineSink<'a> = &'a (dyn Fn(OutputLine) + Send + Sync);
+
+/// 一次进程调用
+#[derive(Debug, Clone, Default)]
+pub struct ExecRequest {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// 追加到当前进程环境之上的变量（通常为 [`inherited_env`] 构建的完整环境）
+    pub env: HashMap<String, String>,
+    /// 工作目录，默认继承当前进程
+    pub cwd: Option<PathBuf>,
+    pub timeout_secs: u64,
+    /// 持续无输出的最长时间，超过则终止；None 表示只受总超时限制
+    pub idle_timeout_secs: Option<u64>,
+    pub output: OutputOptions,
+    /// 调用 ID，运行期间可通过 [`cancel`] 终止（同一 ID 可对应多个进程）
+    pub invocation_id: Option<String>,
+    /// 完整输出的记录文件位置（见 [`transcript_options`]），None 表示不写记录
+    pub transcript: Option<TranscriptOptions>,
+    /// 写入记录文件前对命令行与输出应用的脱敏
+    pub redactor: Redactor,
+}
+
+impl ExecRequest {
+    pub fn new(program: impl Into<PathBuf>, args: Vec<String>, timeout_secs: u64) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            timeout_secs,
+            ..Self::default()
+        }
+    }
+}
+
+/// 触发终止的超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    /// 总时长超过 `timeout_secs`
+    Total,
+    /// 超过 `idle_timeout_secs` 没有任何输出
+    Idle,
+}
+
+/// 起止时间（Unix 毫秒）与耗时
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ExecTiming {
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub duration_ms: u64,
+}
+
+/// 进程执行结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExecResult {
+    /// 进程以退出码 0 结束
+    pub ok: bool,
+    /// 标准输出（已解码并去除 ANSI 控制序列）
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    /// 与 stdout/stderr 对应的带样式分段（仅在 [`OutputOptions::styled`] 开启时提供）
+    pub stdout_spans: Option<Vec<StyledSpan>>,
+    pub stderr_spans: Option<Vec<StyledSpan>>,
+    pub code: i32,
+    pub error: Option<String>,
+    /// 因超时被终止时为触发的超时
+    pub timeout: Option<TimeoutKind>,
+    /// 通过 [`cancel`] 取消
+    pub cancelled: bool,
+    /// 超时或取消前收到的最近若干行输出（正常结束时为空）
+    pub last_lines: Vec<String>,
+    /// 超时或取消后被终止的进程 ID（根进程及其派生的进程）
+    pub killed_pids: Vec<u32>,
+    /// 输出超过首尾保留的大小，stdout/stderr 中间部分已省略
+    pub truncated: bool,
+    /// 截断时完整输出所在的记录文件
+    pub transcript: Option<String>,
+    /// 未实际启动进程（dry_run 等）时为 None
+    pub timing: Option<ExecTiming>,
+}
+
+impl ExecResult {
+    pub fn success(stdout: Option<String>, stderr: Option<String>, code: i32) -> Self {
+        Self {
+            ok: true,
+            stdout,
+            stderr,
+            code,
+            ..Self::default()
+        }
+    }
+
+    pub fn failure(code: i32, error: String) -> Self {
+        Self {
+            code,
+            error: Some(error),
+            ..Self::default()
+        }
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.timeout.is_some()
+    }
+
+    /// 因超时或取消被终止
+    pub fn interrupted(&self) -> bool {
+        self.timed_out() || self.cancelled
+    }
+
+    /// 对输出、带样式分段与错误信息应用替换（用于隐藏密钥值）
+    pub fn redact(self, redact: impl Fn(&str) -> String) -> Self {
+        let apply = |s: Option<String>| s.map(|s| redact(&s));
+        Self {
+            stdout: apply(self.stdout),
+            stderr: apply(self.stderr),
+            stdout_spans: redact_spans(self.stdout_spans, &redact),
+            stderr_spans: redact_spans(self.stderr_spans, &redact),
+            error: apply(self.error),
+            last_lines: self.last_lines.iter().map(|l| redact(l)).collect(),
+            ..self
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 当前进程的环境变量
+pub fn inherited_env() -> HashMap<String, String> {
+    std::env::vars().collect()
+}
+
+/// 依次应用环境配置与自定义变量（自定义变量覆盖同名项）
+pub fn overlay_env(
+    env: &mut HashMap<String, String>,
+    profile: Option<&EnvOverlay>,
+    custom: Option<&HashMap<String, String>>,
+) {
+    if let Some(profile) = profile {
+        profile.apply(env);
+    }
+    if let Some(custom) = custom {
+        for (key, value) in custom {
+            env_profile::set_var(env, key, value.clone());
+        }
+    }
+}
+
+/// 只保留首尾的输出缓冲，同时解码并切分行
+struct Capture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    head_limit: usize,
+    tail_limit: usize,
+    /// 已读取的总字节数
+    total: usize,
+    fallback: Option<&'static Encoding>,
+    styled: bool,
+    lines: LineDecoder,
+}
+
+impl Capture {
+    fn new(output: &OutputOptions) -> Self {
+        let fallback = decode::fallback_encoding(output.code_page);
+        Self {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            head_limit: output.head_kb.unwrap_or(DEFAULT_HEAD_KB) * 1024,
+            tail_limit: output.tail_kb.unwrap_or(DEFAULT_TAIL_KB) * 1024,
+            total: 0,
+            fallback,
+            styled: output.styled.unwrap_or(false),
+            lines: LineDecoder::new(fallback),
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.total += bytes.len();
+        let room = self.head_limit.saturating_sub(self.head.len());
+        let (head, rest) = bytes.split_at(bytes.len().min(room));
+        self.head.extend_from_slice(head);
+        let rest = &rest[rest.len().saturating_sub(self.tail_limit)..];
+        self.tail.extend(rest);
+        let excess = self.tail.len().saturating_sub(self.tail_limit);
+        self.tail.drain(..excess);
+        self.lines.push(bytes);
+    }
+
+    fn truncated(&self) -> bool {
+        self.total > self.head.len() + self.tail.len()
+    }
+
+    /// 解码后的输出（含控制序列），截断时首尾之间插入省略说明
+    fn text(&self) -> Option<String> {
+        if self.total == 0 {
+            return None;
+        }
+        let (tail_a, tail_b) = self.tail.as_slices();
+        let tail = [tail_a, tail_b].concat();
+        if !self.truncated() {
+            return Some(decode::decode_with(
+                &[&self.head[..], &tail].concat(),
+                self.fallback,
+            ));
+        }
+        let omitted = self.total - self.head.len() - tail.len();
+        let utf16 = decode::is_utf16(&self.head, self.fallback);
+        Some(format!(
+            "{}\n… 已省略 {} 字节，完整输出见记录文件 …\n{}",
+            decode::decode_with(&self.head, self.fallback),
+            omitted,
+            decode::decode_tail(&tail, self.total - tail.len(), utf16, self.fallback)
+        ))
+    }
+
+    /// 去除控制序列后的输出与带样式分段
+    fn finish(&self) -> (Option<String>, Option<Vec<StyledSpan>>) {
+        let text = self.text();
+        let spans = match &text {
+            Some(t) if self.styled => Some(ansi::parse(t)),
+            _ => None,
+        };
+        (text.map(|t| ansi::strip(&t)), spans)
+    }
+}
+
+/// 正在运行的调用：ID → (取消信号, 使用该 ID 的进程数)
+type Invocations = HashMap<String, (watch::Sender<bool>, usize)>;
+
+fn invocations() -> &'static Mutex<Invocations> {
+    static RUNNING: OnceLock<Mutex<Invocations>> = OnceLock::new();
+    RUNNING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 运行期间登记的调用 ID，结束时注销
+struct Registration {
+    id: String,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl Registration {
+    fn new(id: &str) -> Self {
+        let mut running = invocations().lock().expect("invocations lock");
+        let entry = running
+            .entry(id.to_string())
+            .or_insert_with(|| (watch::channel(false).0, 0));
+        entry.1 += 1;
+        Self {
+            id: id.to_string(),
+            cancelled: entry.0.subscribe(),
+        }
+    }
+
+    async fn wait(&mut self) {
+        if self.cancelled.wait_for(|c| *c).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut running = invocations().lock().expect("invocations lock");
+        if let Some(entry) = running.get_mut(&self.id) {
+            entry.1 -= 1;
+            if entry.1 == 0 {
+                running.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// 取消调用 ID 下正在运行的进程（终止整个进程树），没有正在运行的进程时返回 false
+pub fn cancel(invocation_id: &str) -> bool {
+    match invocations()
+        .lock()
+        .expect("invocations lock")
+        .get(invocation_id)
+    {
+        Some((signal, _)) => {
+            signal.send_replace(true);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Tauri 命令：取消正在运行的 scoop / winsw 调用（请求中的 `invocation_id`）
+///
+/// ```javascript
+/// const id = crypto.randomUUID();
+/// const pending = invoke('scoop_install', { req: { package: 'git', invocation_id: id } });
+/// await invoke('exec_cancel', { invocationId: id }); // true 表示已发出取消
+/// ```
+#[tauri::command]
+pub async fn exec_cancel(invocation_id: String) -> Result<bool, String> {
+    Ok(cancel(&invocation_id))
+}
+
+/// 终止进程的原因
+#[derive(Clone, Copy)]
+enum Stop {
+    Timeout(TimeoutKind),
+    Cancelled,
+}
+
+/// 两个输出流共享的活动记录：最近一次产生输出的时间与最近的输出行
+struct Activity {
+    state: Mutex<(Instant, VecDeque<String>)>,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new((Instant::now(), VecDeque::new())),
+        }
+    }
+
+    fn touch(&self) {
+        self.state.lock().expect("activity lock").0 = Instant::now();
+    }
+
+    fn push_line(&self, line: &str) {
+        let tail = &mut self.state.lock().expect("activity lock").1;
+        if tail.len() == TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.state.lock().expect("activity lock").0.elapsed()
+    }
+
+    fn tail(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .expect("activity lock")
+            .1
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+/// 一个输出流的去向：活动记录、记录文件与行回调
+struct Sinks<'a, 'b> {
+    activity: &'a Activity,
+    transcript: Option<&'a Transcript>,
+    on_line: Option<LineSink<'b>>,
+}
+
+fn emit(raw: String, kind: OutputStream, styled: bool, sinks: &Sinks<'_, '_>) {
+    let line = ansi::strip(&raw);
+    sinks.activity.push_line(&line);
+    if let Some(transcript) = sinks.transcript {
+        transcript.line(kind, &line);
+    }
+    if let Some(sink) = sinks.on_line {
+        sink(OutputLine {
+            stream: kind,
+            line,
+            spans: styled.then(|| ansi::parse(&raw)),
+        });
+    }
+}
+
+/// 读取输出流直至结束，逐行回调、写入记录文件与缓冲
+///
+/// 任何字节（包括不换行的进度输出）都计为活动。
+async fn read_output(
+    stream: Option<impl AsyncRead + Unpin>,
+    kind: OutputStream,
+    capture: &mut Capture,
+    sinks: &Sinks<'_, '_>,
+) {
+    let Some(mut stream) = stream else {
+        return;
+    };
+    let mut chunk = vec![0u8; 8192];
+
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                sinks.activity.touch();
+                capture.push(&chunk[..n]);
+                while let Some(line) = capture.lines.next_line() {
+                    emit(line, kind, capture.styled, sinks);
+                }
+            }
+        }
+    }
+    if let Some(rest) = capture.lines.take_rest() {
+        emit(rest, kind, capture.styled, sinks);
+    }
+}
+
+/// 空闲超时：自最近一次输出起持续 `secs` 秒无输出时返回
+async fn idle_watch(activity: &Activity, secs: Option<u64>) {
+    let Some(secs) = secs else {
+        return std::future::pending().await;
+    };
+    let limit = Duration::from_secs(secs);
+    loop {
+        let idle = activity.idle_for();
+        if idle >= limit {
+            return;
+        }
+        sleep(limit - idle).await;
+    }
+}
+
+/// 运行进程并等待结束
+///
+/// stdout/stderr 在进程运行期间并发读取。总超时或空闲超时触发后终止进程树，
+/// 结果中 `timeout` 标明触发的超时，并保留此前已读取的输出与最近的输出行。
+/// 输出超过首尾保留的大小时，结果中 `transcript` 为完整输出的记录文件。
+pub async fn run(
+    req: &ExecRequest,
+    on_line: Option<LineSink<'_>>,
+) -> Result<ExecResult, ExecError> {
+    let mut cmd = Command::new(&req.program);
+    cmd.args(&req.args)
+        .envs(&req.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    tree::configure(&mut cmd);
+    if let Some(cwd) = &req.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    let started_at = unix_millis();
+    let started = Instant::now();
+    let mut child = cmd.spawn().map_err(|source| ExecError::Spawn {
+        program: req.program.display().to_string(),
+        source,
+    })?;
+    // 执行被丢弃（future 被丢弃）时由 Drop 终止整棵树
+    let mut tree = tree::ProcessTree::attach(&child);
+    let mut registration = req.invocation_id.as_deref().map(Registration::new);
+
+    let mut stdout = Capture::new(&req.output);
+    let mut stderr = Capture::new(&req.output);
+    let stdout_pipe = child.stdout.take();
+    let stderr_pipe = child.stderr.take();
+    let activity = Activity::new();
+    let transcript = req
+        .transcript
+        .as_ref()
+        .and_then(|options| Transcript::create(options, req));
+    let sinks = Sinks {
+        activity: &activity,
+        transcript: transcript.as_ref(),
+        on_line,
+    };
+
+    let run = async {
+        let (status, _, _) = tokio::join!(
+            child.wait(),
+            read_output(stdout_pipe, OutputStream::Stdout, &mut stdout, &sinks),
+            read_output(stderr_pipe, OutputStream::Stderr, &mut stderr, &sinks),
+        );
+        status
+    };
+
+    let cancelled = async {
+        match registration.as_mut() {
+            Some(r) => r.wait().await,
+            None => std::future::pending().await,
+        }
+    };
+    let outcome = tokio::select! {
+        res = timeout(Duration::from_secs(req.timeout_secs), run) => {
+            res.map_err(|_| Stop::Timeout(TimeoutKind::Total))
+        }
+        _ = idle_watch(&activity, req.idle_timeout_secs) => Err(Stop::Timeout(TimeoutKind::Idle)),
+        _ = cancelled => Err(Stop::Cancelled),
+    };
+
+    let mut killed_pids = Vec::new();
+    let mut last_lines = Vec::new();
+    let (status, fired) = match outcome {
+        Ok(Ok(status)) => {
+            tree.disarm();
+            (Some(status), None)
+        }
+        Ok(Err(e)) => return Err(ExecError::Wait(e.to_string())),
+        Err(stop) => {
+            // 超时或取消，强制终止整个进程树
+            killed_pids = tree.kill();
+            let _ = child.kill().await;
+            last_lines = activity.tail();
+            for (kind, capture) in [
+                (OutputStream::Stdout, &mut stdout),
+                (OutputStream::Stderr, &mut stderr),
+            ] {
+                if let Some(rest) = capture.lines.take_rest() {
+                    let line = ansi::strip(&rest);
+                    if let Some(transcript) = &transcript {
+                        transcript.line(kind, &line);
+                    }
+                    last_lines.push(line);
+                }
+            }
+            (None, Some(stop))
+        }
+    };
+
+    let timing = ExecTiming {
+        started_at,
+        ended_at: unix_millis(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    let (stdout_text, stdout_spans) = stdout.finish();
+    let (stderr_text, stderr_spans) = stderr.finish();
+    let ok = status.is_some_and(|s| s.success());
+    let code = status
+        .and_then(|s| s.code())
+        .unwrap_or(if ok { 0 } else { -1 });
+
+    let error = fired.as_ref().map(|stop| match stop {
+        Stop::Timeout(TimeoutKind::Total) => {
+            format!("进程运行超过 {}s，已终止", req.timeout_secs)
+        }
+        Stop::Timeout(TimeoutKind::Idle) => format!(
+            "进程 {}s 无输出，已终止",
+            req.idle_timeout_secs.unwrap_or_default()
+        ),
+        Stop::Cancelled => "已取消，进程已终止".to_string(),
+    });
+    let truncated = stdout.truncated() || stderr.truncated();
+    let transcript = transcript.and_then(|t| {
+        let footer = match &error {
+            Some(e) => e.clone(),
+            None => format!("退出码 {}，耗时 {}ms", code, timing.duration_ms),
+        };
+        t.finish(&footer, truncated)
+    });
+
+    Ok(ExecResult {
+        ok,
+        stdout: stdout_text,
+        stderr: stderr_text,
+        stdout_spans,
+        stderr_spans,
+        code,
+        error,
+        timeout: match fired {
+            Some(Stop::Timeout(kind)) => Some(kind),
+            _ => None,
+        },
+        cancelled: matches!(fired, Some(Stop::Cancelled)),
+        last_lines,
+        killed_pids,
+        truncated,
+        transcript,
+        timing: Some(timing),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sinks<'a, 'b>(activity: &'a Activity, on_line: Option<LineSink<'b>>) -> Sinks<'a, 'b> {
+        Sinks {
+            activity,
+            transcript: None,
+            on_line,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_output_streams_lines() {
+        let received = std::sync::Mutex::new(Vec::new());
+        let sink = |line: OutputLine| received.lock().unwrap().push(line);
+
+        let data: &[u8] = b"installing\r\nconfigured\nno newline";
+        let mut capture = Capture::new(&OutputOptions::default());
+        read_output(
+            Some(data),
+            OutputStream::Stdout,
+            &mut capture,
+            &sinks(&Activity::new(), Some(&sink)),
+        )
+        .await;
+
+        assert_eq!(
+            capture.text().as_deref(),
+            Some("installing\r\nconfigured\nno newline")
+        );
+        let lines = received.into_inner().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].line, "installing");
+        assert_eq!(lines[2].line, "no newline");
+        assert!(lines.iter().all(|l| l.stream == OutputStream::Stdout));
+
+        let empty: &[u8] = b"";
+        let mut capture = Capture::new(&OutputOptions::default());
+        read_output(
+            Some(empty),
+            OutputStream::Stderr,
+            &mut capture,
+            &sinks(&Activity::new(), None),
+        )
+        .await;
+        assert!(capture.text().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_capture_limit() {
+        let data: &[u8] = b"0123456789\nabcdef\nlast line\n";
+        let mut capture = Capture::new(&OutputOptions::default());
+        capture.head_limit = 8;
+        capture.tail_limit = 12;
+        read_output(
+            Some(data),
+            OutputStream::Stdout,
+            &mut capture,
+            &sinks(&Activity::new(), None),
+        )
+        .await;
+        assert_eq!(
+            capture.text().as_deref(),
+            Some("01234567\n… 已省略 8 字节，完整输出见记录文件 …\nlast line\n")
+        );
+        assert!(capture.truncated());
+
+        // 逐字节写入时末尾缓冲同样只保留最后的字节
+        let mut bytewise = Capture::new(&OutputOptions::default());
+        bytewise.head_limit = 8;
+        bytewise.tail_limit = 12;
+        for b in data {
+            bytewise.push(std::slice::from_ref(b));
+        }
+        assert_eq!(bytewise.text(), capture.text());
+
+        let mut exact = Capture::new(&OutputOptions::default());
+        exact.head_limit = 8;
+        exact.tail_limit = 20;
+        exact.push(data);
+        assert!(!exact.truncated());
+        assert_eq!(exact.text().as_deref(), std::str::from_utf8(data).ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_output_strips_ansi() {
+        let received = std::sync::Mutex::new(Vec::new());
+        let sink = |line: OutputLine| received.lock().unwrap().push(line);
+
+        let data: &[u8] = b"\x1b[32mInstalling\x1b[0m 'git'\n";
+        let output = OutputOptions {
+            styled: Some(true),
+            ..Default::default()
+        };
+        let mut capture = Capture::new(&output);
+        read_output(
+            Some(data),
+            OutputStream::Stdout,
+            &mut capture,
+            &sinks(&Activity::new(), Some(&sink)),
+        )
+        .await;
+
+        let lines = received.into_inner().unwrap();
+        assert_eq!(lines[0].line, "Installing 'git'");
+        let spans = lines[0].spans.as_ref().unwrap();
+        assert_eq!(spans[0].style.fg.as_deref(), Some("green"));
+        assert_eq!(spans[1].text, " 'git'");
+
+        let (text, spans) = capture.finish();
+        assert_eq!(text.as_deref(), Some("Installing 'git'\n"));
+        assert_eq!(spans.unwrap().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_decodes_code_page() {
+        // GBK 编码的 "服务"
+        let script = r"printf '\267\376\316\361\n'; printf '\033[31mfail\033[0m\n' >&2";
+        let req = ExecRequest {
+            output: OutputOptions {
+                code_page: Some(936),
+                ..Default::default()
+            },
+            ..ExecRequest::new("sh", vec!["-c".into(), script.into()], 10)
+        };
+        let res = run(&req, None).await.unwrap();
+        assert_eq!(res.stdout.as_deref(), Some("服务\n"));
+        assert_eq!(res.stderr.as_deref(), Some("fail\n"));
+        assert!(res.stderr_spans.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_redact_styled_output() {
+        let mut redactor = Redactor::default();
+        redactor.add("s3cr3t-token-value");
+        let received = std::sync::Mutex::new(Vec::new());
+        let sink = |line: OutputLine| {
+            received
+                .lock()
+                .unwrap()
+                .push(line.redact(|s| redactor.redact(s)))
+        };
+
+        let script = r"printf 'token=\033[33ms3cr3t-token-value\033[0m\n'";
+        let req = ExecRequest {
+            output: OutputOptions {
+                styled: Some(true),
+                ..Default::default()
+            },
+            ..ExecRequest::new("sh", vec!["-c".into(), script.into()], 10)
+        };
+        let res = run(&req, Some(&sink))
+            .await
+            .unwrap()
+            .redact(|s| redactor.redact(s));
+
+        let spans = res.stdout_spans.unwrap();
+        assert_eq!(spans[1].style.fg.as_deref(), Some("yellow"));
+        assert!(spans.iter().all(|s| !s.text.contains("s3cr3t")));
+        assert!(!res.stdout.unwrap().contains("s3cr3t"));
+
+        let lines = received.into_inner().unwrap();
+        let line_spans = lines[0].spans.as_ref().unwrap();
+        assert!(line_spans.iter().all(|s| !s.text.contains("s3cr3t")));
+        assert!(!lines[0].line.contains("s3cr3t"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_spills_transcript() {
+        let dir = std::env::temp_dir().join(format!("exec-transcript-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let options = TranscriptOptions {
+            dir: dir.clone(),
+            label: "scoop-install-git".into(),
+            retention: 10,
+        };
+        let mut redactor = Redactor::default();
+        redactor.add("s3cr3t-token-value");
+
+        let script = "echo token=s3cr3t-token-value; seq 1 5000; echo warn >&2";
+        let req = ExecRequest {
+            output: OutputOptions {
+                head_kb: Some(1),
+                tail_kb: Some(1),
+                ..Default::default()
+            },
+            transcript: Some(options.clone()),
+            redactor,
+            ..ExecRequest::new("sh", vec!["-c".into(), script.into()], 10)
+        };
+        let res = run(&req, None).await.unwrap();
+        assert!(res.ok);
+        assert!(res.truncated);
+        let path = PathBuf::from(res.transcript.unwrap());
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert!(path.to_string_lossy().ends_with("-scoop-install-git.log"));
+        let stdout = res.stdout.unwrap();
+        assert!(stdout.starts_with("token=s3cr3t-token-value\n1\n"));
+        assert!(stdout.contains("已省略"));
+        assert!(stdout.ends_with("4999\n5000\n"));
+        assert!(stdout.len() < 3 * 1024);
+        assert_eq!(res.stderr.as_deref(), Some("warn\n"));
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert!(!log.contains("s3cr3t-token-value"));
+        assert!(log.contains("\n2500\n"));
+        assert!(log.contains("[stderr] warn\n"));
+        assert!(log.starts_with(&format!("# sh -c echo token={}", crate::secrets::REDACTED)));
+        assert!(log.contains("# 退出码 0"));
+
+        // 未截断时不保留记录文件
+        let req = ExecRequest {
+            transcript: Some(options),
+            ..ExecRequest::new("sh", vec!["-c".into(), "echo done".into()], 10)
+        };
+        let res = run(&req, None).await.unwrap();
+        assert!(!res.truncated);
+        assert!(res.transcript.is_none());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overlay_env() {
+        let mut env = HashMap::from([("PATH".to_string(), "/bin".to_string())]);
+        let custom = HashMap::from([("APP_MODE".to_string(), "test".to_string())]);
+        overlay_env(&mut env, None, Some(&custom));
+        assert_eq!(env["APP_MODE"], "test");
+        assert_eq!(env["PATH"], "/bin");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_exit_code_and_timing() {
+        let req = ExecRequest::new(
+            "sh",
+            vec!["-c".into(), "echo out; echo err >&2; exit 3".into()],
+            10,
+        );
+        let res = run(&req, None).await.unwrap();
+        assert!(!res.ok);
+        assert_eq!(res.code, 3);
+        assert_eq!(res.stdout.as_deref(), Some("out\n"));
+        assert_eq!(res.stderr.as_deref(), Some("err\n"));
+        assert_eq!(res.timeout, None);
+        let timing = res.timing.unwrap();
+        assert!(timing.ended_at >= timing.started_at);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_timeout_kills_child() {
+        let req = ExecRequest::new(
+            "sh",
+            vec!["-c".into(), "echo begin; exec sleep 30".into()],
+            1,
+        );
+        let res = run(&req, None).await.unwrap();
+        assert_eq!(res.timeout, Some(TimeoutKind::Total));
+        assert!(!res.ok);
+        assert_eq!(res.stdout.as_deref(), Some("begin\n"));
+        assert!(res.timing.unwrap().duration_ms < 10_000);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        // 输出两行后挂起，不换行的进度输出也算作最近输出
+        let script = "echo resolving; echo downloading; printf 'progress 45%%'; sleep 30";
+        let req = ExecRequest {
+            idle_timeout_secs: Some(1),
+            ..ExecRequest::new("sh", vec!["-c".into(), script.into()], 30)
+        };
+        let res = run(&req, None).await.unwrap();
+        assert_eq!(res.timeout, Some(TimeoutKind::Idle));
+        assert_eq!(
+            res.last_lines,
+            vec!["resolving", "downloading", "progress 45%"]
+        );
+        assert!(res.error.unwrap().contains("无输出"));
+        assert!(res.timing.unwrap().duration_ms < 10_000);
+
+        // 持续有输出时不触发空闲超时
+        let script = "for i in 1 2 3 4; do echo $i; sleep 0.4; done";
+        let req = ExecRequest {
+            idle_timeout_secs: Some(1),
+            ..ExecRequest::new("sh", vec!["-c".into(), script.into()], 30)
+        };
+        let res = run(&req, None).await.unwrap();
+        assert!(res.ok);
+        assert!(res.last_lines.is_empty());
+    }
+
+    /// 进程是否仍在运行（已退出或成为僵尸进程均视为不在运行）
+    #[cfg(target_os = "linux")]
+    fn alive(pid: u32) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| {
+                let state = stat[stat.rfind(')').unwrap() + 1..]
+                    .split_whitespace()
+                    .next();
+                !matches!(state, Some("Z") | Some("X"))
+            })
+            .unwrap_or(false)
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn wait_dead(pid: u32) -> bool {
+        for _ in 0..50 {
+            if !alive(pid) {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_kills_process_tree() {
+        // 模拟 PowerShell 派生下载/解压进程后挂起
+        let script = "sleep 30 & sleep 30 & echo forked; wait";
+        let req = ExecRequest::new("sh", vec!["-c".into(), script.into()], 1);
+        let res = run(&req, None).await.unwrap();
+
+        assert_eq!(res.timeout, Some(TimeoutKind::Total));
+        assert!(res.killed_pids.len() >= 3, "killed: {:?}", res.killed_pids);
+        for pid in &res.killed_pids {
+            assert!(wait_dead(*pid).await, "进程 {} 未被终止", pid);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cancel_kills_process_tree() {
+        let dir = std::env::temp_dir().join(format!("exec-cancel-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("pid");
+        let script = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let req = ExecRequest::new("sh", vec!["-c".into(), script], 30);
+
+        // 在总超时之前丢弃执行 future
+        let cancelled = timeout(Duration::from_secs(1), run(&req, None)).await;
+        assert!(cancelled.is_err());
+
+        let pid: u32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        assert!(wait_dead(pid).await, "孙进程 {} 未被终止", pid);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cancel_by_invocation_id() {
+        let dir = std::env::temp_dir().join(format!("exec-cancel-id-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("pid");
+        let script = format!(
+            "echo started; sleep 30 & echo $! > '{}'; wait",
+            pid_file.display()
+        );
+        let req = ExecRequest {
+            invocation_id: Some("install-git".into()),
+            ..ExecRequest::new("sh", vec!["-c".into(), script], 30)
+        };
+        assert!(!cancel("install-git"));
+
+        let canceller = async {
+            while !pid_file.exists() {
+                sleep(Duration::from_millis(20)).await;
+            }
+            assert!(cancel("install-git"));
+        };
+        let (res, _) = tokio::join!(run(&req, None), canceller);
+        let res = res.unwrap();
+        assert!(res.cancelled);
+        assert!(res.interrupted());
+        assert!(res.timeout.is_none());
+        assert_eq!(res.last_lines, vec!["started"]);
+
+        let pid: u32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        assert!(res.killed_pids.contains(&pid));
+        assert!(wait_dead(pid).await, "孙进程 {} 未被终止", pid);
+        // 结束后注销
+        assert!(!cancel("install-git"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_run_spawn_error() {
+        let req = ExecRequest::new("/nonexistent/program", Vec::new(), 1);
+        assert!(matches!(
+            run(&req, None).await,
+            Err(ExecError::Spawn { .. })
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+//! ANSI 控制序列处理
+//!
+//! scoop 的输出带有大量颜色控制序列。[`strip`] 去除全部控制序列；
+//! [`parse`] 同时按 SGR（颜色、粗体等）将文本切分为带样式的分段，供界面渲染颜色。
+
+use serde::Serialize;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// 文本样式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Style {
+    /// 前景色：0–15 号色为名称（如 "red"、"bright_green"），其余为 "#rrggbb"
+    pub fg: Option<String>,
+    /// 背景色，格式同前景色
+    pub bg: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// 一段相同样式的文本；按顺序拼接各段即为去除控制序列后的文本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StyledSpan {
+    pub text: String,
+    pub style: Style,
+}
+
+/// 256 色表中的颜色
+fn indexed_color(n: u8) -> String {
+    match n {
+        0..=7 => COLOR_NAMES[n as usize].to_string(),
+        8..=15 => format!("bright_{}", COLOR_NAMES[n as usize - 8]),
+        16..=231 => {
+            const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+            let i = (n - 16) as usize;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                LEVELS[i / 36],
+                LEVELS[(i / 6) % 6],
+                LEVELS[i % 6]
+            )
+        }
+        232..=255 => {
+            let gray = 8 + (n - 232) * 10;
+            format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+        }
+    }
+}
+
+/// `38;5;n` / `38;2;r;g;b` 形式的扩展颜色
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<String> {
+    let channel = |v: u16| v.min(255) as u8;
+    match params.next()? {
+        5 => params.next().map(|n| indexed_color(channel(n))),
+        2 => {
+            let (r, g, b) = (params.next()?, params.next()?, params.next()?);
+            Some(format!(
+                "#{:02x}{:02x}{:02x}",
+                channel(r),
+                channel(g),
+                channel(b)
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl Style {
+    /// 应用一条 SGR 序列（`ESC [ params m`）
+    fn apply_sgr(&mut self, params: &str) {
+        let mut codes = params
+            .split([';', ':'])
+            .map(|p| p.parse::<u16>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.fg = Some(indexed_color((code - 30) as u8)),
+                38 => self.fg = extended_color(&mut codes),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(indexed_color((code - 40) as u8)),
+                48 => self.bg = extended_color(&mut codes),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(indexed_color((code - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(indexed_color((code - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 扫描得到的片段
+enum Segment<'a> {
+    Text(&'a str),
+    /// SGR 序列的参数
+    Sgr(&'a str),
+}
+
+/// 跳过从 `start`（ESC 所在位置）开始的控制序列，返回其后的位置
+fn skip_escape<'a>(input: &'a str, start: usize, on: &mut impl FnMut(Segment<'a>)) -> usize {
+    let bytes = input.as_bytes();
+    let len = bytes.len();
+    let mut i = start + 1;
+    match bytes.get(i) {
+        None => i,
+        // CSI：参数字节、中间字节、结束字节
+        Some(b'[') => {
+            i += 1;
+            let params_start = i;
+            while i < len && (0x30..=0x3f).contains(&bytes[i]) {
+                i += 1;
+            }
+            let params_end = i;
+            while i < len && (0x20..=0x2f).contains(&bytes[i]) {
+                i += 1;
+            }
+            if i < len && (0x40..=0x7e).contains(&bytes[i]) {
+                if bytes[i] == b'm' && params_end == i {
+                    on(Segment::Sgr(&input[params_start..params_end]));
+                }
+                i + 1
+            } else {
+                i
+            }
+        }
+        // OSC 等字符串序列，以 BEL 或 ESC \ 结束
+        Some(b']' | b'P' | b'X' | b'^' | b'_') => {
+            while i < len {
+                if bytes[i] == BEL {
+                    return i + 1;
+                }
+                if bytes[i] == ESC && bytes.get(i + 1) == Some(&b'\\') {
+                    return i + 2;
+                }
+                i += 1;
+            }
+            len
+        }
+        // 其余两字节（或带中间字节）的序列，如 `ESC ( B`
+        Some(_) => {
+            while i < len && (0x20..=0x2f).contains(&bytes[i]) {
+                i += 1;
+            }
+            if i < len && bytes[i].is_ascii() {
+                i + 1
+            } else {
+                i
+            }
+        }
+    }
+}
+
+/// 逐段扫描文本与 SGR 序列，其余控制序列被丢弃
+fn scan<'a>(input: &'a str, mut on: impl FnMut(Segment<'a>)) {
+    let bytes = input.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            ESC => {
+                if start < i {
+                    on(Segment::Text(&input[start..i]));
+                }
+                i = skip_escape(input, i, &mut on);
+                start = i;
+            }
+            BEL => {
+                if start < i {
+                    on(Segment::Text(&input[start..i]));
+                }
+                i += 1;
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    if start < bytes.len() {
+        on(Segment::Text(&input[start..]));
+    }
+}
+
+/// 去除全部 ANSI 控制序列
+pub fn strip(input: &str) -> String {
+    if !input.as_bytes().iter().any(|&b| b == ESC || b == BEL) {
+        return input.to_string();
+    }
+    let mut out = String::with_capacity(input.len());
+    scan(input, |seg| {
+        if let Segment::Text(text) = seg {
+            out.push_str(text);
+        }
+    });
+    out
+}
+
+/// 去除控制序列并按样式切分
+pub fn parse(input: &str) -> Vec<StyledSpan> {
+    let mut spans: Vec<StyledSpan> = Vec::new();
+    let mut style = Style::default();
+    scan(input, |seg| match seg {
+        Segment::Sgr(params) => style.apply_sgr(params),
+        Segment::Text(text) => match spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => spans.push(StyledSpan {
+                text: text.to_string(),
+                style: style.clone(),
+            }),
+        },
+    });
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("plain"), "plain");
+        assert_eq!(
+            strip("\x1b[32mInstalling\x1b[0m 'git' (2.45.1) [\x1b[1m64bit\x1b[22m]"),
+            "Installing 'git' (2.45.1) [64bit]"
+        );
+        // 光标控制、OSC 标题、字符集切换、未结束的序列
+        assert_eq!(strip("\x1b[2K\x1b[1Gdone"), "done");
+        assert_eq!(strip("\x1b]0;scoop\x07ok\x1b]8;;\x1b\\"), "ok");
+        assert_eq!(strip("\x1b(B中文\x1b["), "中文");
+    }
+
+    #[test]
+    fn test_parse_spans() {
+        let spans = parse("\x1b[31;1mERROR\x1b[0m 下载失败 \x1b[38;5;208mretry\x1b[39m");
+        let text: String = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, "ERROR 下载失败 retry");
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].text, "ERROR");
+        assert_eq!(spans[0].style.fg.as_deref(), Some("red"));
+        assert!(spans[0].style.bold);
+        assert_eq!(spans[1].style, Style::default());
+        assert_eq!(spans[2].style.fg.as_deref(), Some("#ff8700"));
+
+        let spans = parse("\x1b[48;2;0;128;255m\x1b[92mok");
+        assert_eq!(spans[0].style.bg.as_deref(), Some("#0080ff"));
+        assert_eq!(spans[0].style.fg.as_deref(), Some("bright_green"));
+        assert!(parse("").is_empty());
+    }
+}
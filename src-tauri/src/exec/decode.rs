@@ -0,0 +1,318 @@
+//! 控制台输出解码
+//!
+//! 依次识别：UTF-16 LE（以 BOM 开头，如 PowerShell 的 `Out-File` 默认编码）、UTF-8（可带 BOM），
+//! 其余按指定的控制台代码页解码，未指定时使用系统 OEM 代码页（中文 Windows 为 936/GBK）。
+
+use encoding_rs::{Decoder, Encoding};
+
+/// 单行最大长度，超过时按此长度切分（如不换行的进度条）
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+
+/// Windows 代码页对应的编码（不支持的代码页返回 None）
+pub(super) fn encoding_for_code_page(code_page: u32) -> Option<&'static Encoding> {
+    let enc = match code_page {
+        65001 => encoding_rs::UTF_8,
+        1200 => encoding_rs::UTF_16LE,
+        936 => encoding_rs::GBK,
+        950 => encoding_rs::BIG5,
+        932 => encoding_rs::SHIFT_JIS,
+        949 => encoding_rs::EUC_KR,
+        866 => encoding_rs::IBM866,
+        874 => encoding_rs::WINDOWS_874,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        1253 => encoding_rs::WINDOWS_1253,
+        1254 => encoding_rs::WINDOWS_1254,
+        1255 => encoding_rs::WINDOWS_1255,
+        1256 => encoding_rs::WINDOWS_1256,
+        1257 => encoding_rs::WINDOWS_1257,
+        1258 => encoding_rs::WINDOWS_1258,
+        _ => return None,
+    };
+    Some(enc)
+}
+
+/// 无控制台的子进程使用的 OEM 代码页
+#[cfg(windows)]
+fn oem_code_page() -> Option<u32> {
+    // SAFETY: GetOEMCP 无参数且不会失败
+    Some(unsafe { windows_sys::Win32::Globalization::GetOEMCP() })
+}
+
+#[cfg(not(windows))]
+fn oem_code_page() -> Option<u32> {
+    None
+}
+
+/// 输出不是 UTF-8 时使用的编码：指定的代码页，否则为系统 OEM 代码页
+pub(super) fn fallback_encoding(code_page: Option<u32>) -> Option<&'static Encoding> {
+    code_page
+        .or_else(oem_code_page)
+        .and_then(encoding_for_code_page)
+}
+
+/// 解码一段 UTF-8 或代码页编码的文本（不含 UTF-16）
+fn decode_bytes(bytes: &[u8], fallback: Option<&'static Encoding>) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        // 仅末尾字符不完整（输出在字符中间被截断）时仍为 UTF-8
+        Err(e) if e.error_len().is_none() && e.valid_up_to() > 0 => {
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+        Err(_) => match fallback {
+            Some(enc) if enc != encoding_rs::UTF_16LE => {
+                enc.decode_without_bom_handling(bytes).0.into_owned()
+            }
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        },
+    }
+}
+
+/// 解码完整输出
+pub(super) fn decode_with(bytes: &[u8], fallback: Option<&'static Encoding>) -> String {
+    match bytes.strip_prefix(&UTF16LE_BOM) {
+        Some(rest) => encoding_rs::UTF_16LE
+            .decode_without_bom_handling(rest)
+            .0
+            .into_owned(),
+        None if fallback == Some(encoding_rs::UTF_16LE) => encoding_rs::UTF_16LE
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned(),
+        None => decode_bytes(bytes, fallback),
+    }
+}
+
+/// 按 `head`（输出开头）判断是否为 UTF-16 输出
+pub(super) fn is_utf16(head: &[u8], fallback: Option<&'static Encoding>) -> bool {
+    head.starts_with(&UTF16LE_BOM) || fallback == Some(encoding_rs::UTF_16LE)
+}
+
+/// 解码从输出中间（第 `offset` 个字节）开始的末尾部分
+///
+/// 起始处可能位于字符中间，因此丢弃第一个换行之前的不完整行（没有换行时保留全部）。
+pub(super) fn decode_tail(
+    bytes: &[u8],
+    offset: usize,
+    utf16: bool,
+    fallback: Option<&'static Encoding>,
+) -> String {
+    let text = if utf16 {
+        let bytes = if offset % 2 == 1 {
+            bytes.get(1..).unwrap_or_default()
+        } else {
+            bytes
+        };
+        encoding_rs::UTF_16LE
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()
+    } else {
+        // 跳过被截断字符残留的 UTF-8 后续字节
+        let skip = bytes
+            .iter()
+            .take(3)
+            .take_while(|&&b| (0x80..0xC0).contains(&b))
+            .count();
+        decode_bytes(&bytes[skip..], fallback)
+    };
+    match text.find('\n') {
+        Some(pos) if pos + 1 < text.len() => text[pos + 1..].to_string(),
+        _ => text,
+    }
+}
+
+/// 解码进程输出：`code_page` 为输出不是 UTF-8 时使用的代码页，None 表示系统 OEM 代码页
+pub fn decode(bytes: &[u8], code_page: Option<u32>) -> String {
+    decode_with(bytes, fallback_encoding(code_page))
+}
+
+fn trim_line_end(line: &str) -> String {
+    line.trim_end_matches(['\r', '\n']).to_string()
+}
+
+enum Mode {
+    /// 尚未读到足够字节判断是否为 UTF-16
+    Undecided,
+    /// 按字节切分行，逐行解码（UTF-8 与各代码页中 `\n` 均为单字节）
+    Bytes,
+    /// UTF-16 须先解码再切分
+    Utf16(Decoder),
+}
+
+/// 将输出流逐步解码并切分为行
+pub(super) struct LineDecoder {
+    fallback: Option<&'static Encoding>,
+    mode: Mode,
+    bytes: Vec<u8>,
+    text: String,
+}
+
+impl LineDecoder {
+    pub(super) fn new(fallback: Option<&'static Encoding>) -> Self {
+        Self {
+            fallback,
+            mode: Mode::Undecided,
+            bytes: Vec::new(),
+            text: String::new(),
+        }
+    }
+
+    fn decode_utf16(decoder: &mut Decoder, bytes: &[u8], text: &mut String, last: bool) {
+        let needed = decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len() * 3);
+        text.reserve(needed);
+        let _ = decoder.decode_to_string(bytes, text, last);
+    }
+
+    pub(super) fn push(&mut self, bytes: &[u8]) {
+        if let Mode::Utf16(decoder) = &mut self.mode {
+            Self::decode_utf16(decoder, bytes, &mut self.text, false);
+            return;
+        }
+        self.bytes.extend_from_slice(bytes);
+        if matches!(self.mode, Mode::Undecided) && self.bytes.len() >= UTF16LE_BOM.len() {
+            if is_utf16(&self.bytes, self.fallback) {
+                let mut decoder = encoding_rs::UTF_16LE.new_decoder_with_bom_removal();
+                let pending = std::mem::take(&mut self.bytes);
+                Self::decode_utf16(&mut decoder, &pending, &mut self.text, false);
+                self.mode = Mode::Utf16(decoder);
+            } else {
+                self.mode = Mode::Bytes;
+            }
+        }
+    }
+
+    /// 取出下一个完整的行（不含行尾）
+    pub(super) fn next_line(&mut self) -> Option<String> {
+        match self.mode {
+            Mode::Utf16(_) => {
+                let end = match self.text.find('\n') {
+                    Some(pos) => pos + 1,
+                    None if self.text.len() >= MAX_LINE_BYTES => {
+                        let mut end = MAX_LINE_BYTES;
+                        while !self.text.is_char_boundary(end) {
+                            end -= 1;
+                        }
+                        end
+                    }
+                    None => return None,
+                };
+                let line: String = self.text.drain(..end).collect();
+                Some(trim_line_end(&line))
+            }
+            Mode::Bytes => {
+                let end = match self.bytes.iter().position(|&b| b == b'\n') {
+                    Some(pos) => pos + 1,
+                    None if self.bytes.len() >= MAX_LINE_BYTES => MAX_LINE_BYTES,
+                    None => return None,
+                };
+                let line: Vec<u8> = self.bytes.drain(..end).collect();
+                Some(trim_line_end(&decode_bytes(&line, self.fallback)))
+            }
+            Mode::Undecided => None,
+        }
+    }
+
+    /// 取出末尾未换行的部分
+    pub(super) fn take_rest(&mut self) -> Option<String> {
+        if let Mode::Utf16(decoder) = &mut self.mode {
+            Self::decode_utf16(decoder, &[], &mut self.text, true);
+        }
+        let rest = if self.text.is_empty() {
+            decode_bytes(&std::mem::take(&mut self.bytes), self.fallback)
+        } else {
+            std::mem::take(&mut self.text)
+        };
+        let rest = trim_line_end(&rest);
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom {
+            UTF16LE_BOM.to_vec()
+        } else {
+            Vec::new()
+        };
+        bytes.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_with("服务已启动".as_bytes(), None), "服务已启动");
+        assert_eq!(decode_with(b"\xEF\xBB\xBFok", None), "ok");
+        // "服务" 的 GBK 编码
+        let gbk = [0xB7, 0xFE, 0xCE, 0xF1];
+        assert_eq!(decode_with(&gbk, encoding_for_code_page(936)), "服务");
+        assert_eq!(decode(&gbk, Some(936)), "服务");
+        assert_eq!(decode_with(b"ok\xff", None), "ok\u{fffd}");
+        assert!(encoding_for_code_page(437).is_none());
+
+        assert_eq!(
+            decode_with(&utf16le("已安装\r\n", true), None),
+            "已安装\r\n"
+        );
+        assert_eq!(decode(&utf16le("ok", false), Some(1200)), "ok");
+        // 在多字节字符中间截断的 UTF-8 不按代码页解码
+        let cut = &"安装完成".as_bytes()[..7];
+        assert_eq!(
+            decode_with(cut, encoding_for_code_page(936)),
+            "安装\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn test_decode_tail() {
+        let text = "第一行\n第二行\n";
+        let bytes = text.as_bytes();
+        // 从 "一" 的中间开始
+        assert_eq!(decode_tail(&bytes[4..], 4, false, None), "第二行\n");
+        assert_eq!(decode_tail(b"no newline", 100, false, None), "no newline");
+
+        let gbk = [b'x', b'\n', 0xB7, 0xFE, 0xCE, 0xF1];
+        let gbk_enc = encoding_for_code_page(936);
+        assert_eq!(decode_tail(&gbk, 10, false, gbk_enc), "服务");
+
+        let wide = utf16le(text, true);
+        assert_eq!(decode_tail(&wide[5..], 5, true, None), "第二行\n");
+    }
+
+    #[test]
+    fn test_line_decoder_utf16() {
+        let bytes = utf16le("第一行\r\n第二行\r\n未换行", true);
+        let mut lines = LineDecoder::new(None);
+        // 逐字节输入，覆盖 BOM 与代码单元被拆开的情况
+        let mut out = Vec::new();
+        for b in &bytes {
+            lines.push(std::slice::from_ref(b));
+            while let Some(line) = lines.next_line() {
+                out.push(line);
+            }
+        }
+        out.extend(lines.take_rest());
+        assert_eq!(out, vec!["第一行", "第二行", "未换行"]);
+    }
+
+    #[test]
+    fn test_line_decoder_code_page() {
+        let mut bytes = b"ok\r\n".to_vec();
+        bytes.extend([0xB7, 0xFE, 0xCE, 0xF1, b'\n']);
+        let mut lines = LineDecoder::new(encoding_for_code_page(936));
+        lines.push(&bytes);
+        assert_eq!(lines.next_line().as_deref(), Some("ok"));
+        assert_eq!(lines.next_line().as_deref(), Some("服务"));
+        assert_eq!(lines.next_line(), None);
+        assert_eq!(lines.take_rest(), None);
+    }
+}
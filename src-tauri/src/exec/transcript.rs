@@ -0,0 +1,298 @@
+//! 完整输出记录
+//!
+//! 内存中只保留输出的首尾部分，完整输出逐行写入 `<app_data_dir>/transcripts/` 下的记录文件。
+//! 写入的内容已去除 ANSI 控制序列并隐藏密钥值；输出未被截断时运行结束后删除该文件。
+//! 记录文件在进程实际启动时才创建（dry_run 不会创建），保留数量见 [`TranscriptSettings`]。
+
+use super::{ExecRequest, OutputStream};
+use crate::secrets::Redactor;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+const SETTINGS_FILE: &str = "transcripts.json";
+const TRANSCRIPT_DIR: &str = "transcripts";
+
+/// 记录文件设置，保存在 `<app_data_dir>/transcripts.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptSettings {
+    /// 为 false 时不写记录文件（输出仍只保留首尾）
+    pub enabled: bool,
+    /// 保留的记录文件数，超出时删除最早的
+    pub retention: usize,
+}
+
+impl Default for TranscriptSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention: 50,
+        }
+    }
+}
+
+/// 一次调用的记录文件位置
+#[derive(Debug, Clone)]
+pub struct TranscriptOptions {
+    pub dir: PathBuf,
+    /// 文件名中的标识（如 `scoop-install-git`）
+    pub label: String,
+    /// 创建时清理到的保留数量（含本次）
+    pub retention: usize,
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("无法定位应用数据目录: {}", e))
+}
+
+fn load_settings(app: &AppHandle) -> Result<TranscriptSettings, String> {
+    let path = data_dir(app)?.join(SETTINGS_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TranscriptSettings::default()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 按应用设置确定记录文件位置；关闭记录或应用数据目录不可用时返回 None
+pub fn transcript_options(app: &AppHandle, label: &str) -> Option<TranscriptOptions> {
+    let settings = load_settings(app)
+        .map_err(|e| log::warn!("读取输出记录设置失败，使用默认设置: {}", e))
+        .unwrap_or_default();
+    if !settings.enabled || settings.retention == 0 {
+        return None;
+    }
+    match data_dir(app) {
+        Ok(dir) => Some(TranscriptOptions {
+            dir: dir.join(TRANSCRIPT_DIR),
+            label: label.to_string(),
+            retention: settings.retention,
+        }),
+        Err(e) => {
+            log::warn!("{}，不写输出记录", e);
+            None
+        }
+    }
+}
+
+/// 正在写入的记录文件，清理时跳过
+fn active() -> &'static Mutex<HashSet<PathBuf>> {
+    static ACTIVE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    ACTIVE.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// 文件名 `<毫秒时间戳>-<进程 ID>-<序号>-<label>.log`，同一毫秒内同名的调用也不会冲突
+fn file_name(label: &str) -> String {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let label: String = label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "{}-{}-{}-{}.log",
+        super::unix_millis(),
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed),
+        label
+    )
+}
+
+/// 只保留最新的 `keep` 个记录文件（文件名以时间戳开头，按名称排序即为时间顺序），
+/// 正在写入的文件不计入也不删除
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let open = active().lock().expect("transcript lock").clone();
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log") && !open.contains(p))
+        .collect();
+    if files.len() <= keep {
+        return;
+    }
+    files.sort();
+    for old in &files[..files.len() - keep] {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// 运行期间写入的记录文件
+pub(super) struct Transcript {
+    path: PathBuf,
+    /// 写入失败后置为 None，不再继续写
+    writer: Mutex<Option<BufWriter<File>>>,
+    redactor: Redactor,
+}
+
+impl Transcript {
+    /// 创建记录文件并写入命令行，同时清理过多的旧记录
+    pub(super) fn create(options: &TranscriptOptions, req: &ExecRequest) -> Option<Self> {
+        if let Err(e) = std::fs::create_dir_all(&options.dir) {
+            log::warn!("创建输出记录目录 {} 失败: {}", options.dir.display(), e);
+            return None;
+        }
+        prune(&options.dir, options.retention.saturating_sub(1));
+        let path = options.dir.join(file_name(&options.label));
+        let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(f) => f,
+            Err(e) => {
+                log::warn!("创建输出记录 {} 失败: {}", path.display(), e);
+                return None;
+            }
+        };
+        active()
+            .lock()
+            .expect("transcript lock")
+            .insert(path.clone());
+        let transcript = Self {
+            path,
+            writer: Mutex::new(Some(BufWriter::new(file))),
+            redactor: req.redactor.clone(),
+        };
+        let command = std::iter::once(req.program.display().to_string())
+            .chain(req.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        transcript.write(&format!("# {}", command));
+        Some(transcript)
+    }
+
+    fn write(&self, text: &str) {
+        let mut writer = self.writer.lock().expect("transcript lock");
+        let Some(w) = writer.as_mut() else {
+            return;
+        };
+        if let Err(e) = writeln!(w, "{}", self.redactor.redact(text)) {
+            log::warn!("写入输出记录 {} 失败: {}", self.path.display(), e);
+            *writer = None;
+        }
+    }
+
+    /// 写入一行输出（stderr 的行带 `[stderr]` 前缀）
+    pub(super) fn line(&self, kind: OutputStream, line: &str) {
+        match kind {
+            OutputStream::Stdout => self.write(line),
+            OutputStream::Stderr => self.write(&format!("[stderr] {}", line)),
+        }
+    }
+
+    /// 写入结束信息并关闭文件；`keep` 为 false 时删除文件。返回保留的文件路径
+    pub(super) fn finish(self, footer: &str, keep: bool) -> Option<String> {
+        self.write(&format!("# {}", footer));
+        let writer = self.writer.lock().expect("transcript lock").take();
+        let flushed = writer.is_some_and(|mut w| w.flush().is_ok());
+        if keep && flushed {
+            Some(self.path.display().to_string())
+        } else {
+            let _ = std::fs::remove_file(&self.path);
+            None
+        }
+    }
+}
+
+impl Drop for Transcript {
+    fn drop(&mut self) {
+        active().lock().expect("transcript lock").remove(&self.path);
+    }
+}
+
+/// Tauri 命令：读取输出记录设置
+#[tauri::command]
+pub async fn exec_transcripts_get(app: AppHandle) -> Result<TranscriptSettings, String> {
+    load_settings(&app)
+}
+
+/// Tauri 命令：保存输出记录设置
+///
+/// ```javascript
+/// await invoke('exec_transcripts_set', { settings: { enabled: true, retention: 100 } });
+/// ```
+#[tauri::command]
+pub async fn exec_transcripts_set(
+    app: AppHandle,
+    settings: TranscriptSettings,
+) -> Result<TranscriptSettings, String> {
+    let dir = data_dir(&app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!("{}.tmp", SETTINGS_FILE));
+    std::fs::write(&tmp, text).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, dir.join(SETTINGS_FILE)).map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_keeps_latest() {
+        let dir = std::env::temp_dir().join(format!("transcript-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for ts in ["100", "300", "200", "050"] {
+            std::fs::write(dir.join(format!("{}-scoop.log", ts)), "").unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        // 正在写入的最早文件不会被删除
+        let open = dir.join("050-scoop.log");
+        active().lock().unwrap().insert(open.clone());
+
+        prune(&dir, 2);
+        active().lock().unwrap().remove(&open);
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![
+                "050-scoop.log",
+                "200-scoop.log",
+                "300-scoop.log",
+                "notes.txt"
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transcripts_do_not_collide() {
+        let dir = std::env::temp_dir().join(format!("transcript-unique-{}", std::process::id()));
+        let options = TranscriptOptions {
+            dir: dir.clone(),
+            label: "winsw-api-start".into(),
+            retention: 10,
+        };
+        let req = ExecRequest::new("winsw.exe", vec!["start".into()], 10);
+        let a = Transcript::create(&options, &req).unwrap();
+        let b = Transcript::create(&options, &req).unwrap();
+        assert_ne!(a.path, b.path);
+        a.line(OutputStream::Stdout, "a");
+        b.line(OutputStream::Stdout, "b");
+
+        let a_path = a.finish("退出码 0", true).unwrap();
+        let b_path = b.finish("退出码 0", true).unwrap();
+        assert!(!active().lock().unwrap().contains(Path::new(&a_path)));
+        assert!(std::fs::read_to_string(a_path).unwrap().contains("\na\n"));
+        assert!(std::fs::read_to_string(b_path).unwrap().contains("\nb\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! 子进程及其派生进程组成的进程树
+//!
+//! Unix 上子进程在独立的进程组中启动；Windows 上子进程以挂起状态启动，加入一个 Job 对象后再恢复运行，
+//! 其后派生的进程（如 scoop 调用的 7zip、aria2）都归入同一 Job。
+//! 超时、取消或执行被丢弃时终止整棵树。
+
+use tokio::process::{Child, Command};
+
+/// 启动前调用：Unix 上让子进程成为新进程组的组长，Windows 上以挂起状态启动（由 [`ProcessTree::attach`] 恢复）
+pub(super) fn configure(cmd: &mut Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    #[cfg(windows)]
+    cmd.creation_flags(windows_sys::Win32::System::Threading::CREATE_SUSPENDED);
+    #[cfg(not(any(unix, windows)))]
+    let _ = cmd;
+}
+
+pub(super) struct ProcessTree {
+    root: Option<u32>,
+    #[cfg(windows)]
+    job: Option<job::Job>,
+    /// 进程已正常结束时为 false，不再清理
+    armed: bool,
+}
+
+impl ProcessTree {
+    /// 启动后立即调用
+    ///
+    /// Windows 上子进程处于挂起状态，加入 Job 后才恢复运行，因此不会有进程在加入之前派生。
+    pub(super) fn attach(child: &Child) -> Self {
+        #[cfg(windows)]
+        let job = child.raw_handle().and_then(job::Job::assign);
+        #[cfg(windows)]
+        if let Some(pid) = child.id() {
+            job::resume(pid);
+        }
+        Self {
+            root: child.id(),
+            #[cfg(windows)]
+            job,
+            armed: true,
+        }
+    }
+
+    /// 进程已正常结束
+    pub(super) fn disarm(&mut self) {
+        self.armed = false;
+    }
+
+    /// 终止整棵树，返回被终止的进程 ID
+    pub(super) fn kill(&mut self) -> Vec<u32> {
+        self.armed = false;
+        let Some(root) = self.root else {
+            return Vec::new();
+        };
+        self.kill_tree(root)
+    }
+
+    #[cfg(unix)]
+    fn kill_tree(&self, root: u32) -> Vec<u32> {
+        let pids = group_members(root);
+        // SAFETY: 进程组 ID 为子进程 PID，只向本模块创建的进程组发送信号
+        unsafe {
+            libc::killpg(root as libc::pid_t, libc::SIGKILL);
+        }
+        pids
+    }
+
+    #[cfg(windows)]
+    fn kill_tree(&self, root: u32) -> Vec<u32> {
+        match &self.job {
+            Some(job) => {
+                let pids = job.pids();
+                job.terminate();
+                pids
+            }
+            // 未能加入 Job 时只能终止根进程（由调用方完成）
+            None => vec![root],
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn kill_tree(&self, root: u32) -> Vec<u32> {
+        vec![root]
+    }
+}
+
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if self.armed {
+            let killed = self.kill();
+            log::debug!("执行被取消，已终止进程 {:?}", killed);
+        }
+    }
+}
+
+/// 进程组中的全部进程（读取 /proc；无法枚举时只返回组长）
+#[cfg(unix)]
+fn group_members(pgid: u32) -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![pgid];
+    };
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| process_group_of(*pid) == Some(pgid))
+        .collect();
+    if !pids.contains(&pgid) {
+        pids.push(pgid);
+    }
+    pids.sort_unstable();
+    pids
+}
+
+/// /proc/<pid>/stat 中的进程组 ID（命令名之后的第 3 个字段）
+#[cfg(unix)]
+fn process_group_of(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(2)?.parse().ok()
+}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::RawHandle;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicProcessIdList,
+        QueryInformationJobObject, TerminateJobObject,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+    /// 恢复以 CREATE_SUSPENDED 启动的进程（其主线程）
+    ///
+    /// 标准库不提供主线程句柄，通过线程快照找到属于该进程的线程。
+    pub(super) fn resume(pid: u32) {
+        // SAFETY: 快照句柄在本函数内使用并关闭，THREADENTRY32 按要求设置 dwSize
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                log::warn!("创建线程快照失败，无法恢复进程 {}", pid);
+                return;
+            }
+            let mut entry: THREADENTRY32 = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+            let mut more = Thread32First(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32OwnerProcessID == pid {
+                    let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                    if !thread.is_null() {
+                        ResumeThread(thread);
+                        CloseHandle(thread);
+                    }
+                }
+                more = Thread32Next(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+        }
+    }
+
+    /// 单次查询最多返回的进程数
+    const MAX_PIDS: usize = 1024;
+
+    /// 与 JOBOBJECT_BASIC_PROCESS_ID_LIST 布局一致，带足够大的 ID 数组
+    #[repr(C)]
+    struct PidList {
+        _assigned: u32,
+        listed: u32,
+        ids: [usize; MAX_PIDS],
+    }
+
+    pub(super) struct Job(HANDLE);
+
+    // SAFETY: Job 句柄可在线程间传递，所有操作均为线程安全的内核调用
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        /// 创建 Job 并将进程加入其中
+        pub(super) fn assign(process: RawHandle) -> Option<Self> {
+            // SAFETY: 参数均为空指针（默认安全属性、匿名 Job）
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return None;
+            }
+            let job = Job(handle);
+            // SAFETY: process 为仍在运行的子进程句柄
+            if unsafe { AssignProcessToJobObject(job.0, process as HANDLE) } == 0 {
+                return None;
+            }
+            Some(job)
+        }
+
+        /// Job 中当前的进程 ID
+        pub(super) fn pids(&self) -> Vec<u32> {
+            let mut list = PidList {
+                _assigned: 0,
+                listed: 0,
+                ids: [0; MAX_PIDS],
+            };
+            // SAFETY: 缓冲区大小与传入长度一致
+            let ok = unsafe {
+                QueryInformationJobObject(
+                    self.0,
+                    JobObjectBasicProcessIdList,
+                    &mut list as *mut PidList as *mut core::ffi::c_void,
+                    std::mem::size_of::<PidList>() as u32,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Vec::new();
+            }
+            let listed = (list.listed as usize).min(MAX_PIDS);
+            list.ids[..listed].iter().map(|&id| id as u32).collect()
+        }
+
+        pub(super) fn terminate(&self) {
+            // SAFETY: 句柄在 Job 存活期间有效
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // 未设置 KILL_ON_JOB_CLOSE，关闭句柄不影响仍在运行的进程
+            // SAFETY: 句柄由 CreateJobObjectW 创建且只关闭一次
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
@@ -1,14 +1,67 @@
+pub mod env_profile;
+pub mod exec;
 pub mod scoop;
+pub mod secrets;
 pub mod winsw;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .invoke_handler(tauri::generate_handler![
+      exec::exec_cancel,
+      exec::transcript::exec_transcripts_get,
+      exec::transcript::exec_transcripts_set,
       scoop::scoop_detect,
       scoop::scoop_install,
       scoop::scoop_uninstall,
+      scoop::scoop_uninstall_impact,
       scoop::scoop_ensure,
-      winsw::winsw_action
+      winsw::winsw_action,
+      winsw::command::winsw_command,
+      winsw::deploy::winsw_deploy,
+      winsw::scoop_app::winsw_deploy_scoop_app,
+      winsw::history::winsw_history_list,
+      winsw::history::winsw_history_diff,
+      winsw::history::winsw_history_rollback,
+      winsw::policy::winsw_policy_get,
+      winsw::policy::winsw_policy_set,
+      winsw::template::winsw_template_render,
+      winsw::lifecycle::winsw_lifecycle_snapshot,
+      winsw::lifecycle::winsw_lifecycle_poll_get,
+      winsw::lifecycle::winsw_lifecycle_poll_set,
+      winsw::watchdog::winsw_watchdog_get,
+      winsw::watchdog::winsw_watchdog_set,
+      winsw::watchdog::winsw_watchdog_incidents,
+      winsw::bundle::winsw_bundle_export,
+      winsw::bundle::winsw_bundle_import,
+      winsw::discover::winsw_discover,
+      winsw::discover::winsw_discover_adopt,
+      winsw::catalog::winsw_catalog_add,
+      winsw::catalog::winsw_catalog_remove,
+      winsw::catalog::winsw_catalog_list,
+      winsw::catalog::winsw_catalog_get,
+      winsw::catalog::winsw_catalog_set_health,
+      winsw::catalog::winsw_catalog_set_profile,
+      winsw::catalog::winsw_catalog_set_depends,
+      winsw::group::winsw_group_save,
+      winsw::group::winsw_group_remove,
+      winsw::group::winsw_group_list,
+      winsw::group::winsw_group_plan,
+      winsw::group::winsw_group_start,
+      winsw::group::winsw_group_stop,
+      winsw::provision::winsw_binary_info,
+      winsw::provision::winsw_provision_get,
+      winsw::provision::winsw_provision_set,
+      winsw::logs::winsw_logs_resolve,
+      winsw::logs::winsw_logs_tail,
+      winsw::logs::winsw_logs_follow,
+      winsw::logs::winsw_logs_unfollow,
+      secrets::secret_set,
+      secrets::secret_remove,
+      secrets::secret_list,
+      env_profile::env_profile_list,
+      env_profile::env_profile_save,
+      env_profile::env_profile_remove,
+      env_profile::env_profile_effective
     ])
     .setup(|app| {
       if cfg!(debug_assertions) {
@@ -18,6 +71,8 @@ pub fn run() {
             .build(),
         )?;
       }
+      winsw::lifecycle::init(app.handle());
+      winsw::watchdog::init(app.handle());
       Ok(())
     })
     .run(tauri::generate_context!())
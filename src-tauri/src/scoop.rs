@@ -1,13 +1,14 @@
+use crate::env_profile::{self, EnvOverlay};
+use crate::exec::{self, ExecError, ExecRequest, ExecResult, OutputOptions, TranscriptOptions};
+use crate::secrets::Redactor;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::process::Stdio;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tauri::AppHandle;
 use thiserror::Error;
-use tokio::process::Command;
 use tokio::sync::RwLock;
-use tokio::time::timeout;
 
 /// Scoop 包管理封装模块
 ///
@@ -42,23 +43,31 @@ pub mod api {
     pub struct InstallOptions {
         /// 超时时间（秒）
         pub timeout_seconds: Option<u64>,
+        /// 持续无输出超过该秒数时终止（如下载挂起），与总超时同时生效
+        pub idle_timeout_seconds: Option<u64>,
+        /// 输出的解码与样式选项（代码页、是否返回颜色分段）
+        pub output: Option<OutputOptions>,
+        /// 调用 ID，执行中可通过 `exec_cancel` 取消
+        pub invocation_id: Option<String>,
         /// 是否为全局安装（需要管理员权限）
         pub global: Option<bool>,
         /// 仅构建命令，不执行（用于测试/基准）
         pub dry_run: Option<bool>,
         /// 附加参数（如 `--arch 64bit` 等）
         pub extra_args: Option<Vec<String>>,
+        /// 选中的环境配置（密钥引用须已解析）
+        #[serde(skip)]
+        pub profile: Option<EnvOverlay>,
+        /// 完整输出的记录文件位置（见 [`exec::transcript_options`]），仅在实际执行时创建
+        #[serde(skip)]
+        pub transcript: Option<TranscriptOptions>,
+        /// 写入记录文件前的脱敏
+        #[serde(skip)]
+        pub redactor: Redactor,
     }
 
-    /// 操作统一响应
-    #[derive(Debug, Clone, Serialize)]
-    pub struct ActionResp {
-        pub ok: bool,
-        pub stdout: Option<String>,
-        pub stderr: Option<String>,
-        pub code: i32,
-        pub error: Option<String>,
-    }
+    /// 操作统一响应（与 winsw 共用的进程执行结果）
+    pub type ActionResp = ExecResult;
 
     /// 检测响应
     #[derive(Debug, Clone, Serialize)]
@@ -75,6 +84,9 @@ pub mod api {
     pub struct BootstrapOptions {
         pub timeout_seconds: Option<u64>,
         pub dry_run: Option<bool>,
+        /// 选中的环境配置（密钥引用须已解析）
+        #[serde(skip)]
+        pub profile: Option<EnvOverlay>,
     }
 
     /// 模块错误类型
@@ -84,12 +96,119 @@ pub mod api {
         PowerShellNotAvailable(String),
         #[error("命令启动失败: {0}")]
         CommandSpawn(#[from] std::io::Error),
-        #[error("命令执行超时: {secs}s")]
-        Timeout { secs: u64 },
+        #[error("等待命令结束失败: {0}")]
+        CommandWait(String),
+        /// 结果中包含触发的超时与超时前的最近输出
+        #[error("命令执行超时: {}", .0.error.as_deref().unwrap_or_default())]
+        Timeout(Box<ExecResult>),
+        /// 通过 `exec_cancel` 取消，结果中包含取消前的最近输出
+        #[error("命令已取消")]
+        Cancelled(Box<ExecResult>),
         #[error("命令执行失败，退出码: {code:?}, 错误: {stderr}")]
         CommandFailed { code: Option<i32>, stderr: String },
         #[error("包名无效或为空")]
         InvalidPackageName,
+        #[error("读取应用清单失败: {0}")]
+        Manifest(String),
+    }
+
+    /// 应用清单中的一个 `bin` 条目
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct BinEntry {
+        /// 相对于应用目录的路径
+        pub path: String,
+        /// shim 名称
+        pub alias: Option<String>,
+        /// shim 附加的参数
+        pub args: Vec<String>,
+    }
+
+    /// 已安装应用的清单（仅包含本模块用到的字段）
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct AppManifest {
+        pub version: Option<String>,
+        pub bin: Vec<BinEntry>,
+        /// 持久化目录（相对于 `persist/<app>`）
+        pub persist: Vec<String>,
+        /// 依赖的包名
+        pub depends: Vec<String>,
+    }
+
+    /// purge 时会删除的持久化路径
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct PersistPath {
+        pub path: String,
+        pub exists: bool,
+        /// 占用空间（字节）
+        pub bytes: u64,
+    }
+
+    /// 引用应用目录下可执行文件的 WinSW 服务
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct DependentService {
+        pub service_id: String,
+        pub config_path: String,
+        pub executable: String,
+    }
+
+    /// 卸载影响
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+    pub struct UninstallImpact {
+        pub package: String,
+        pub app_dir: String,
+        pub installed: bool,
+        pub purge: bool,
+        /// 应用的持久化路径；仅 purge 时会被删除
+        pub persist: Vec<PersistPath>,
+        /// 清单 `depends` 中包含该应用的已安装应用
+        pub dependents: Vec<String>,
+        /// 卸载后消失的 shim 文件
+        pub shims: Vec<String>,
+        /// 可执行文件位于应用目录下的 WinSW 服务
+        pub services: Vec<DependentService>,
+    }
+
+    impl UninstallImpact {
+        /// 是否有需要确认的影响
+        pub fn has_impact(&self) -> bool {
+            let deletes_data = self.purge && self.persist.iter().any(|p| p.exists);
+            deletes_data
+                || !self.dependents.is_empty()
+                || !self.shims.is_empty()
+                || !self.services.is_empty()
+        }
+
+        /// 影响摘要，用于提示与错误信息
+        pub fn summary(&self) -> String {
+            let mut parts = Vec::new();
+            if self.purge {
+                let existing: Vec<&PersistPath> =
+                    self.persist.iter().filter(|p| p.exists).collect();
+                if !existing.is_empty() {
+                    let bytes: u64 = existing.iter().map(|p| p.bytes).sum();
+                    parts.push(format!(
+                        "删除 {} 个持久化路径（{} 字节）",
+                        existing.len(),
+                        bytes
+                    ));
+                }
+            }
+            if !self.dependents.is_empty() {
+                parts.push(format!("被 {} 依赖", self.dependents.join(", ")));
+            }
+            if !self.shims.is_empty() {
+                parts.push(format!("移除 {} 个 shim", self.shims.len()));
+            }
+            if !self.services.is_empty() {
+                let ids: Vec<&str> = self
+                    .services
+                    .iter()
+                    .map(|s| s.service_id.as_str())
+                    .collect();
+                parts.push(format!("WinSW 服务 {} 使用该应用", ids.join(", ")));
+            }
+            parts.join("；")
+        }
     }
 
     #[derive(Debug, Clone)]
@@ -153,36 +272,33 @@ pub mod api {
         ]
     }
 
-    /// 获取增强的环境变量（包含 Scoop 路径）
-    fn get_enhanced_env() -> HashMap<String, String> {
-        let mut env: HashMap<String, String> = std::env::vars().collect();
+    /// 获取增强的环境变量（包含 Scoop 路径），最后应用选中的环境配置
+    pub(crate) fn get_enhanced_env(profile: Option<&EnvOverlay>) -> HashMap<String, String> {
+        let mut env = exec::inherited_env();
 
         // 确保 SCOOP 相关路径在 PATH 中
-        if let Some(path) = env.get("PATH") {
-            let mut paths: Vec<String> = path.split(';').map(|s| s.to_string()).collect();
+        if env_profile::get_var(&env, "PATH").is_some() {
+            let paths = env_profile::path_entries(&env);
+            let mut missing = Vec::new();
+
+            // 添加全局 Scoop 路径
+            if let Ok(programdata) = std::env::var("ProgramData") {
+                if !paths.iter().any(|p| p.contains("ProgramData\\scoop")) {
+                    missing.push(format!("{}\\scoop\\shims", programdata));
+                }
+            }
 
             // 添加用户 Scoop 路径
             if let Ok(userprofile) = std::env::var("USERPROFILE") {
-                let scoop_shims = format!("{}\\scoop\\shims", userprofile);
-                let scoop_apps = format!("{}\\scoop\\apps\\scoop\\current\\bin", userprofile);
-
-                if !paths.iter().any(|p| p.contains("scoop\\shims")) {
-                    paths.insert(0, scoop_shims);
-                }
                 if !paths.iter().any(|p| p.contains("scoop\\apps")) {
-                    paths.insert(0, scoop_apps);
+                    missing.push(format!("{}\\scoop\\apps\\scoop\\current\\bin", userprofile));
                 }
-            }
-
-            // 添加全局 Scoop 路径
-            if let Ok(programdata) = std::env::var("ProgramData") {
-                let global_shims = format!("{}\\scoop\\shims", programdata);
-                if !paths.iter().any(|p| p.contains("ProgramData\\scoop")) {
-                    paths.insert(0, global_shims);
+                if !paths.iter().any(|p| p.contains("scoop\\shims")) {
+                    missing.push(format!("{}\\scoop\\shims", userprofile));
                 }
             }
 
-            env.insert("PATH".to_string(), paths.join(";"));
+            env_profile::prepend_path(&mut env, &missing);
         }
 
         // 设置 SCOOP 环境变量
@@ -207,6 +323,8 @@ pub mod api {
             env.insert("SCOOP_GLOBAL".to_string(), "C:\\aidex\\scoop".into());
         }
 
+        exec::overlay_env(&mut env, profile, None);
+
         env
     }
 
@@ -219,7 +337,7 @@ pub mod api {
 
         // 检查 scoop 命令是否在 PATH 中
         if which::which("scoop").is_ok() {
-            let ver = try_scoop_version().await.ok();
+            let ver = try_scoop_version(None).await.ok();
             cache_put(true, ver).await;
             return Ok(true);
         }
@@ -241,44 +359,31 @@ pub mod api {
         Ok(installed)
     }
 
-    async fn try_scoop_version() -> Result<String, ScoopError> {
+    async fn try_scoop_version(profile: Option<&EnvOverlay>) -> Result<String, ScoopError> {
         let ps = powershell_path().ok_or_else(|| {
             ScoopError::PowerShellNotAvailable("未找到 PowerShell 可执行文件".into())
         })?;
 
-        let args = build_ps_command_args("scoop --version");
-        let env = get_enhanced_env();
-
-        let child = Command::new(ps)
-            .args(&args)
-            .envs(&env)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        let out = timeout(
-            Duration::from_secs(VERSION_CHECK_TIMEOUT_SECS),
-            child.wait_with_output(),
+        let env = get_enhanced_env(profile);
+        let out = execute_ps_command(
+            &ps,
+            "scoop --version",
+            VERSION_CHECK_TIMEOUT_SECS,
+            &InstallOptions::default(),
+            &env,
         )
-        .await
-        .map_err(|_| ScoopError::Timeout {
-            secs: VERSION_CHECK_TIMEOUT_SECS,
-        })??;
+        .await?;
 
-        if out.status.success() {
-            let version = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            Ok(version)
+        if out.ok {
+            Ok(out.stdout.unwrap_or_default().trim().to_string())
         } else {
-            Err(ScoopError::CommandFailed {
-                code: out.status.code(),
-                stderr: String::from_utf8_lossy(&out.stderr).trim().to_string(),
-            })
+            Err(command_failed(out))
         }
     }
 
-    /// 获取 Scoop 版本字符串（若命令可用）
-    pub async fn scoop_version() -> Result<String, ScoopError> {
-        try_scoop_version().await
+    /// 获取 Scoop 版本字符串（若命令可用），在选中的环境配置下查询
+    pub async fn scoop_version(profile: Option<&EnvOverlay>) -> Result<String, ScoopError> {
+        try_scoop_version(profile).await
     }
 
     /// 返回当前检测缓存快照（若仍在 TTL 内）
@@ -301,58 +406,50 @@ pub mod api {
 
         if dry_run {
             let combined = format!("{}\n{}", set_policy, install_cmd);
-            return Ok(ActionResp {
-                ok: true,
-                stdout: Some(combined),
-                stderr: None,
-                code: 0,
-                error: None,
-            });
+            return Ok(ActionResp::success(Some(combined), None, 0));
         }
 
-        let env = get_enhanced_env();
+        let env = get_enhanced_env(opts.profile.as_ref());
 
         // 设置执行策略
-        let out1 = execute_ps_command(&ps, set_policy, timeout_secs, &env).await?;
-        if !out1.status.success() {
-            return Err(ScoopError::CommandFailed {
-                code: out1.status.code(),
-                stderr: String::from_utf8_lossy(&out1.stderr).to_string(),
-            });
+        let out1 = execute_ps_command(
+            &ps,
+            set_policy,
+            timeout_secs,
+            &InstallOptions::default(),
+            &env,
+        )
+        .await?;
+        if !out1.ok {
+            return Err(command_failed(out1));
         }
 
         // 运行安装脚本
-        let out2 = execute_ps_command(&ps, install_cmd, timeout_secs, &env).await?;
-        let ok = out2.status.success();
-        let stdout = parse_output(&out2.stdout);
-        let stderr = parse_output(&out2.stderr);
-        let code = out2.status.code().unwrap_or(if ok { 0 } else { -1 });
-
-        if ok {
-            let ver = try_scoop_version().await.ok();
+        let out2 = execute_ps_command(
+            &ps,
+            install_cmd,
+            timeout_secs,
+            &InstallOptions::default(),
+            &env,
+        )
+        .await?;
+        if out2.ok {
+            let ver = try_scoop_version(opts.profile.as_ref()).await.ok();
             cache_put(true, ver).await;
-            Ok(ActionResp {
-                ok,
-                stdout,
-                stderr,
-                code,
-                error: None,
-            })
+            Ok(out2)
         } else {
-            Err(ScoopError::CommandFailed {
-                code: out2.status.code(),
-                stderr: stderr.unwrap_or_default(),
-            })
+            Err(command_failed(out2))
         }
     }
 
     /// 确保 Scoop 已安装：若未安装则自动安装，返回检测信息
     pub async fn ensure_scoop_installed(opts: BootstrapOptions) -> Result<DetectResp, ScoopError> {
+        let profile = opts.profile.clone();
         if is_scoop_installed().await? {
             let cached = detection_cache().await.is_some();
             return Ok(DetectResp {
                 installed: true,
-                version: try_scoop_version().await.ok(),
+                version: try_scoop_version(profile.as_ref()).await.ok(),
                 error: None,
                 source: Some("detect".into()),
                 cached,
@@ -363,7 +460,7 @@ pub mod api {
         if res.ok {
             Ok(DetectResp {
                 installed: true,
-                version: try_scoop_version().await.ok(),
+                version: try_scoop_version(profile.as_ref()).await.ok(),
                 error: None,
                 source: Some("bootstrap".into()),
                 cached: false,
@@ -389,7 +486,7 @@ pub mod api {
         let timeout_secs = opts.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECS);
         let global = opts.global.unwrap_or(false);
         let dry_run = opts.dry_run.unwrap_or(false);
-        let extra_args = opts.extra_args.unwrap_or_default();
+        let extra_args = opts.extra_args.clone().unwrap_or_default();
 
         let ps = powershell_path().ok_or_else(|| {
             ScoopError::PowerShellNotAvailable("未找到 PowerShell 可执行文件".into())
@@ -409,32 +506,16 @@ pub mod api {
         };
 
         if dry_run {
-            return Ok(ActionResp {
-                ok: true,
-                stdout: Some(cmdline),
-                stderr: None,
-                code: 0,
-                error: None,
-            });
+            return Ok(ActionResp::success(Some(cmdline), None, 0));
         }
 
-        let env = get_enhanced_env();
-        let out = execute_ps_command(&ps, &cmdline, timeout_secs, &env).await?;
-        let ok = out.status.success();
+        let env = get_enhanced_env(opts.profile.as_ref());
+        let out = execute_ps_command(&ps, &cmdline, timeout_secs, &opts, &env).await?;
 
-        if ok {
-            Ok(ActionResp {
-                ok,
-                stdout: parse_output(&out.stdout),
-                stderr: parse_output(&out.stderr),
-                code: out.status.code().unwrap_or(0),
-                error: None,
-            })
+        if out.ok {
+            Ok(out)
         } else {
-            Err(ScoopError::CommandFailed {
-                code: out.status.code(),
-                stderr: parse_output(&out.stderr).unwrap_or_default(),
-            })
+            Err(command_failed(out))
         }
     }
 
@@ -456,69 +537,279 @@ pub mod api {
             ScoopError::PowerShellNotAvailable("未找到 PowerShell 可执行文件".into())
         })?;
 
-        let cmdline = if purge {
-            format!("scoop uninstall --purge {}", pkg)
+        let mut cmd_parts = vec!["scoop uninstall"];
+        if opts.global.unwrap_or(false) {
+            cmd_parts.push("--global");
+        }
+        if purge {
+            cmd_parts.push("--purge");
+        }
+        cmd_parts.push(pkg);
+        let cmdline = cmd_parts.join(" ");
+
+        if dry_run {
+            return Ok(ActionResp::success(Some(cmdline), None, 0));
+        }
+
+        let env = get_enhanced_env(opts.profile.as_ref());
+        let out = execute_ps_command(&ps, &cmdline, timeout_secs, &opts, &env).await?;
+
+        if out.ok {
+            Ok(out)
         } else {
-            format!("scoop uninstall {}", pkg)
+            Err(command_failed(out))
+        }
+    }
+
+    /// 包名中的应用名：去掉 bucket 前缀与版本后缀（`main/nginx@1.25` → `nginx`）
+    pub fn app_name(pkg: &str) -> &str {
+        let name = pkg.trim().rsplit(['/', '\\']).next().unwrap_or("");
+        name.split('@').next().unwrap_or(name)
+    }
+
+    /// Scoop 根目录（`SCOOP` 或 `SCOOP_GLOBAL`）
+    pub fn scoop_root(global: bool, profile: Option<&EnvOverlay>) -> PathBuf {
+        let env = get_enhanced_env(profile);
+        let key = if global { "SCOOP_GLOBAL" } else { "SCOOP" };
+        PathBuf::from(env.get(key).cloned().unwrap_or_default())
+    }
+
+    /// 应用的 `current` 目录：指向当前版本的稳定路径，升级后不变
+    pub fn current_dir(pkg: &str, global: bool, profile: Option<&EnvOverlay>) -> PathBuf {
+        scoop_root(global, profile)
+            .join("apps")
+            .join(app_name(pkg))
+            .join("current")
+    }
+
+    fn parse_bin(value: &serde_json::Value) -> Vec<BinEntry> {
+        use serde_json::Value;
+        let entry = |v: &Value| match v {
+            Value::String(path) => Some(BinEntry {
+                path: path.clone(),
+                alias: None,
+                args: Vec::new(),
+            }),
+            // [路径, 别名, 参数...]
+            Value::Array(parts) => {
+                let mut parts = parts.iter().filter_map(Value::as_str);
+                Some(BinEntry {
+                    path: parts.next()?.to_string(),
+                    alias: parts.next().map(str::to_string),
+                    args: parts.map(str::to_string).collect(),
+                })
+            }
+            _ => None,
         };
+        match value {
+            Value::Array(items) => items.iter().filter_map(entry).collect(),
+            other => entry(other).into_iter().collect(),
+        }
+    }
 
-        if dry_run {
-            return Ok(ActionResp {
-                ok: true,
-                stdout: Some(cmdline),
-                stderr: None,
-                code: 0,
-                error: None,
-            });
+    /// 字符串或字符串数组；数组元素为 `[源, 目标]` 时取目标
+    fn parse_string_list(value: Option<&serde_json::Value>) -> Vec<String> {
+        use serde_json::Value;
+        let item = |v: &Value| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Array(pair) => pair
+                .last()
+                .or(pair.first())
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            _ => None,
+        };
+        match value {
+            Some(Value::Array(items)) => items.iter().filter_map(item).collect(),
+            Some(other) => item(other).into_iter().collect(),
+            None => Vec::new(),
         }
+    }
 
-        let env = get_enhanced_env();
-        let out = execute_ps_command(&ps, &cmdline, timeout_secs, &env).await?;
-        let ok = out.status.success();
+    /// 解析应用清单；`bin` 缺省时依次取 `architecture.64bit.bin`、`architecture.32bit.bin`
+    pub fn parse_manifest(text: &str) -> Result<AppManifest, ScoopError> {
+        let json: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| ScoopError::Manifest(e.to_string()))?;
+        let bin = ["/bin", "/architecture/64bit/bin", "/architecture/32bit/bin"]
+            .iter()
+            .filter_map(|p| json.pointer(p))
+            .map(parse_bin)
+            .find(|b| !b.is_empty())
+            .unwrap_or_default();
+        Ok(AppManifest {
+            version: json
+                .get("version")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            bin,
+            persist: parse_string_list(json.get("persist")),
+            depends: parse_string_list(json.get("depends")),
+        })
+    }
 
-        if ok {
-            Ok(ActionResp {
-                ok,
-                stdout: parse_output(&out.stdout),
-                stderr: parse_output(&out.stderr),
-                code: out.status.code().unwrap_or(0),
-                error: None,
+    /// 读取应用目录下的 `manifest.json`
+    pub fn read_manifest(app_dir: &Path) -> Result<AppManifest, ScoopError> {
+        let path = app_dir.join("manifest.json");
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| ScoopError::Manifest(format!("{}: {}", path.display(), e)))?;
+        parse_manifest(&text)
+    }
+
+    /// 文件或目录占用的空间（字节），不跟随符号链接
+    fn disk_usage(path: &Path) -> u64 {
+        let Ok(meta) = std::fs::symlink_metadata(path) else {
+            return 0;
+        };
+        if !meta.is_dir() {
+            return meta.len();
+        }
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|e| disk_usage(&e.path()))
+                    .sum()
             })
-        } else {
-            Err(ScoopError::CommandFailed {
-                code: out.status.code(),
-                stderr: parse_output(&out.stderr).unwrap_or_default(),
+            .unwrap_or(0)
+    }
+
+    /// 分析在指定 Scoop 根目录下卸载应用的影响（不含 WinSW 服务）
+    pub fn analyze_uninstall_in(root: &Path, pkg: &str, purge: bool) -> UninstallImpact {
+        let name = app_name(pkg);
+        let app_dir = root.join("apps").join(name);
+        let manifest = read_manifest(&app_dir.join("current")).ok();
+
+        let persist_dir = root.join("persist").join(name);
+        let mut persist: Vec<PersistPath> = manifest
+            .as_ref()
+            .map(|m| m.persist.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| persist_dir.join(p))
+            .map(|path| PersistPath {
+                exists: path.exists(),
+                bytes: disk_usage(&path),
+                path: path.display().to_string(),
             })
+            .collect();
+        // 清单未声明但持久化目录存在时，purge 同样会删除整个目录
+        if persist.is_empty() && persist_dir.exists() {
+            persist.push(PersistPath {
+                exists: true,
+                bytes: disk_usage(&persist_dir),
+                path: persist_dir.display().to_string(),
+            });
+        }
+
+        let mut dependents: Vec<String> = std::fs::read_dir(root.join("apps"))
+            .map(|entries| entries.filter_map(Result::ok).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|e| {
+                let other = e.file_name().to_string_lossy().to_string();
+                if other.eq_ignore_ascii_case(name) {
+                    return None;
+                }
+                let m = read_manifest(&e.path().join("current")).ok()?;
+                m.depends
+                    .iter()
+                    .any(|d| app_name(d).eq_ignore_ascii_case(name))
+                    .then_some(other)
+            })
+            .collect();
+        dependents.sort();
+
+        let shim_names: Vec<String> = manifest
+            .as_ref()
+            .map(|m| m.bin.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|b| {
+                b.alias.clone().or_else(|| {
+                    Path::new(&b.path.replace('\\', "/"))
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                })
+            })
+            .map(|n| n.to_lowercase())
+            .collect();
+        let mut shims: Vec<String> = std::fs::read_dir(root.join("shims"))
+            .map(|entries| entries.filter_map(Result::ok).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_stem()
+                    .is_some_and(|s| shim_names.contains(&s.to_string_lossy().to_lowercase()))
+            })
+            .map(|p| p.display().to_string())
+            .collect();
+        shims.sort();
+
+        UninstallImpact {
+            package: pkg.trim().to_string(),
+            app_dir: app_dir.display().to_string(),
+            installed: app_dir.exists(),
+            purge,
+            persist,
+            dependents,
+            shims,
+            services: Vec::new(),
+        }
+    }
+
+    /// 分析卸载应用的影响（不含 WinSW 服务）
+    pub fn analyze_uninstall(
+        pkg: &str,
+        purge: bool,
+        global: bool,
+        profile: Option<&EnvOverlay>,
+    ) -> Result<UninstallImpact, ScoopError> {
+        if app_name(pkg).is_empty() {
+            return Err(ScoopError::InvalidPackageName);
         }
+        Ok(analyze_uninstall_in(
+            &scoop_root(global, profile),
+            pkg,
+            purge,
+        ))
     }
 
-    // 辅助函数：执行 PowerShell 命令
+    // 辅助函数：执行 PowerShell 命令（空闲超时与输出选项取自 opts），超时时进程已被终止
     async fn execute_ps_command(
-        ps_path: &PathBuf,
+        ps_path: &Path,
         script: &str,
         timeout_secs: u64,
+        opts: &InstallOptions,
         env: &HashMap<String, String>,
-    ) -> Result<std::process::Output, ScoopError> {
-        let args = build_ps_command_args(script);
-        let child = Command::new(ps_path)
-            .args(&args)
-            .envs(env)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        timeout(Duration::from_secs(timeout_secs), child.wait_with_output())
-            .await
-            .map_err(|_| ScoopError::Timeout { secs: timeout_secs })?
-            .map_err(ScoopError::from)
+    ) -> Result<ExecResult, ScoopError> {
+        let req = ExecRequest {
+            env: env.clone(),
+            idle_timeout_secs: opts.idle_timeout_seconds,
+            output: opts.output.clone().unwrap_or_default(),
+            invocation_id: opts.invocation_id.clone(),
+            transcript: opts.transcript.clone(),
+            redactor: opts.redactor.clone(),
+            ..ExecRequest::new(ps_path, build_ps_command_args(script), timeout_secs)
+        };
+        let out = exec::run(&req, None).await.map_err(|e| match e {
+            ExecError::Spawn { source, .. } => ScoopError::CommandSpawn(source),
+            ExecError::Wait(e) => ScoopError::CommandWait(e),
+        })?;
+        if out.timed_out() {
+            return Err(ScoopError::Timeout(Box::new(out)));
+        }
+        if out.cancelled {
+            return Err(ScoopError::Cancelled(Box::new(out)));
+        }
+        Ok(out)
     }
 
-    // 辅助函数：解析输出
-    fn parse_output(output: &[u8]) -> Option<String> {
-        if output.is_empty() {
-            None
-        } else {
-            Some(String::from_utf8_lossy(output).to_string())
+    // 辅助函数：非零退出转换为错误
+    fn command_failed(out: ExecResult) -> ScoopError {
+        ScoopError::CommandFailed {
+            code: Some(out.code),
+            stderr: out.stderr.unwrap_or_default().trim().to_string(),
         }
     }
 }
@@ -530,8 +821,33 @@ pub struct InstallReq {
     pub package: String,
     pub global: Option<bool>,
     pub timeout_seconds: Option<u64>,
+    /// 持续无输出超过该秒数时终止
+    pub idle_timeout_seconds: Option<u64>,
+    /// 输出的解码与样式选项
+    pub output: Option<OutputOptions>,
+    /// 调用 ID，执行中可通过 `exec_cancel` 取消
+    pub invocation_id: Option<String>,
     pub dry_run: Option<bool>,
     pub extra_args: Option<Vec<String>>,
+    /// 使用的环境配置名称（如 "dev"）
+    pub profile: Option<String>,
+}
+
+/// `scoop_uninstall` 的附加选项，均可省略
+#[derive(Default, Deserialize)]
+pub struct UninstallOptions {
+    /// 卸载全局安装的包（`--global`）
+    pub global: Option<bool>,
+    /// 持续无输出超过该秒数时终止
+    pub idle_timeout_seconds: Option<u64>,
+    /// 输出的解码与样式选项
+    pub output: Option<OutputOptions>,
+    /// 调用 ID，执行中可通过 `exec_cancel` 取消
+    pub invocation_id: Option<String>,
+    /// 使用的环境配置名称（如 "dev"）
+    pub profile: Option<String>,
+    /// 确认卸载影响（见 `scoop_uninstall_impact`）
+    pub acknowledge: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -543,12 +859,57 @@ pub struct DetectCmdResp {
     pub cached: bool,
 }
 
-/// Tauri 命令：Scoop 检测
+/// 加载环境配置并解析其中的密钥引用
+pub(crate) fn load_profile(
+    app: &AppHandle,
+    name: Option<&str>,
+) -> Result<(Option<EnvOverlay>, Redactor), String> {
+    let Some(name) = name else {
+        return Ok((None, Redactor::default()));
+    };
+    let overlay = env_profile::load_overlay(app, name).map_err(|e| e.to_string())?;
+    let (overlay, redactor) = overlay.resolve_secrets(app).map_err(|e| e.to_string())?;
+    Ok((Some(overlay), redactor))
+}
+
+fn failure_resp(error: String) -> ActionResp {
+    ActionResp::failure(-1, error)
+}
+
+/// 错误转换为响应；超时保留触发的超时与最近输出
+fn error_resp(e: ScoopError) -> ActionResp {
+    match e {
+        ScoopError::Timeout(result) | ScoopError::Cancelled(result) => *result,
+        e => failure_resp(e.to_string()),
+    }
+}
+
+/// 替换输出中已解析的密钥值
+fn redact_resp(resp: ActionResp, redactor: &Redactor) -> ActionResp {
+    resp.redact(|s| redactor.redact(s))
+}
+
+/// Tauri 命令：Scoop 检测；`profile` 为查询版本时使用的环境配置
 #[tauri::command]
-pub async fn scoop_detect() -> Result<DetectCmdResp, String> {
+pub async fn scoop_detect(
+    app: AppHandle,
+    profile: Option<String>,
+) -> Result<DetectCmdResp, String> {
+    let profile = match load_profile(&app, profile.as_deref()) {
+        Ok((profile, _)) => profile,
+        Err(e) => {
+            return Ok(DetectCmdResp {
+                ok: false,
+                installed: false,
+                version: None,
+                error: Some(e),
+                cached: false,
+            })
+        }
+    };
     match is_scoop_installed().await {
         Ok(installed) => {
-            let v = scoop_version().await.ok();
+            let v = scoop_version(profile.as_ref()).await.ok();
             let cached = detection_cache().await.is_some();
             Ok(DetectCmdResp {
                 ok: true,
@@ -570,60 +931,181 @@ pub async fn scoop_detect() -> Result<DetectCmdResp, String> {
 
 /// Tauri 命令：安装包
 #[tauri::command]
-pub async fn scoop_install(req: InstallReq) -> Result<ActionResp, String> {
+pub async fn scoop_install(app: AppHandle, req: InstallReq) -> Result<ActionResp, String> {
+    let (profile, redactor) = match load_profile(&app, req.profile.as_deref()) {
+        Ok(p) => p,
+        Err(e) => return Ok(failure_resp(e)),
+    };
     let opts = InstallOptions {
         timeout_seconds: req.timeout_seconds,
+        idle_timeout_seconds: req.idle_timeout_seconds,
+        output: req.output,
+        invocation_id: req.invocation_id,
         global: req.global,
         dry_run: req.dry_run,
         extra_args: req.extra_args,
+        profile,
+        transcript: exec::transcript_options(
+            &app,
+            &format!("scoop-install-{}", app_name(&req.package)),
+        ),
+        redactor: redactor.clone(),
     };
     match install_package(&req.package, opts).await {
-        Ok(r) => Ok(r),
-        Err(e) => Ok(ActionResp {
-            ok: false,
-            stdout: None,
-            stderr: None,
-            code: -1,
-            error: Some(e.to_string()),
-        }),
+        Ok(r) => Ok(redact_resp(r, &redactor)),
+        Err(e) => Ok(redact_resp(error_resp(e), &redactor)),
     }
 }
 
+/// 路径是否位于目录下（不区分大小写与分隔符）
+fn is_under(path: &str, dir: &Path) -> bool {
+    let normalize = |s: &str| s.replace('\\', "/").trim_end_matches('/').to_lowercase();
+    let dir = normalize(&dir.to_string_lossy());
+    !dir.is_empty() && normalize(path).starts_with(&format!("{}/", dir))
+}
+
+/// 服务目录中可执行文件位于应用目录下的 WinSW 服务
+fn dependent_services(app: &AppHandle, app_dir: &Path) -> Vec<DependentService> {
+    let catalog = match crate::winsw::catalog::load_catalog(app) {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("读取服务目录失败: {}", e);
+            return Vec::new();
+        }
+    };
+    catalog
+        .list()
+        .iter()
+        .filter_map(|entry| {
+            let config = crate::winsw::config::ServiceConfig::load(&entry.config_path).ok()?;
+            let executable = config.expand(config.executable.as_deref()?);
+            is_under(&executable, app_dir).then(|| DependentService {
+                service_id: entry.id.clone(),
+                config_path: entry.config_path.clone(),
+                executable,
+            })
+        })
+        .collect()
+}
+
+/// 分析卸载影响（含服务目录中的 WinSW 服务）
+fn uninstall_impact(
+    app: &AppHandle,
+    package: &str,
+    purge: bool,
+    global: bool,
+    profile: Option<&EnvOverlay>,
+) -> Result<UninstallImpact, String> {
+    let mut impact =
+        analyze_uninstall(package, purge, global, profile).map_err(|e| e.to_string())?;
+    impact.services = dependent_services(app, Path::new(&impact.app_dir));
+    Ok(impact)
+}
+
+/// Tauri 命令：分析卸载包的影响（purge 删除的持久化数据、依赖它的应用、shim 与 WinSW 服务）
+///
+/// ```javascript
+/// const impact = await invoke('scoop_uninstall_impact', { package: 'redis', purge: true });
+/// // impact: { persist: [{ path, exists, bytes }], dependents: [], shims: [], services: [{ service_id, ... }] }
+/// ```
+#[tauri::command]
+pub async fn scoop_uninstall_impact(
+    app: AppHandle,
+    package: String,
+    purge: Option<bool>,
+    global: Option<bool>,
+    profile: Option<String>,
+) -> Result<UninstallImpact, String> {
+    let (profile, _) = load_profile(&app, profile.as_deref())?;
+    uninstall_impact(
+        &app,
+        &package,
+        purge.unwrap_or(false),
+        global.unwrap_or(false),
+        profile.as_ref(),
+    )
+}
+
 /// Tauri 命令：卸载包
+///
+/// 卸载有影响时（见 `scoop_uninstall_impact`）需要在 `options` 中传入 `acknowledge: true`，否则不执行。
+///
+/// ```javascript
+/// await invoke('scoop_uninstall', { package: 'redis', purge: true, options: { acknowledge: true } });
+/// ```
 #[tauri::command]
 pub async fn scoop_uninstall(
+    app: AppHandle,
     package: String,
     purge: Option<bool>,
     timeout_seconds: Option<u64>,
     dry_run: Option<bool>,
+    options: Option<UninstallOptions>,
 ) -> Result<ActionResp, String> {
+    let options = options.unwrap_or_default();
+    let (profile, redactor) = match load_profile(&app, options.profile.as_deref()) {
+        Ok(p) => p,
+        Err(e) => return Ok(failure_resp(e)),
+    };
+    let purge = purge.unwrap_or(false);
+    let global = options.global.unwrap_or(false);
+    if !dry_run.unwrap_or(false) && !options.acknowledge.unwrap_or(false) {
+        match uninstall_impact(&app, &package, purge, global, profile.as_ref()) {
+            Ok(impact) if impact.has_impact() => {
+                return Ok(failure_resp(format!(
+                    "卸载 {} 有以下影响，确认后请传入 acknowledge: true 重试: {}",
+                    package.trim(),
+                    impact.summary()
+                )));
+            }
+            Ok(_) => {}
+            Err(e) => return Ok(failure_resp(e)),
+        }
+    }
     let opts = InstallOptions {
         timeout_seconds,
-        global: None,
+        idle_timeout_seconds: options.idle_timeout_seconds,
+        output: options.output,
+        invocation_id: options.invocation_id,
+        global: options.global,
         dry_run,
         extra_args: None,
+        profile,
+        transcript: exec::transcript_options(
+            &app,
+            &format!("scoop-uninstall-{}", app_name(&package)),
+        ),
+        redactor: redactor.clone(),
     };
-    match uninstall_package(&package, purge.unwrap_or(false), opts).await {
-        Ok(r) => Ok(r),
-        Err(e) => Ok(ActionResp {
-            ok: false,
-            stdout: None,
-            stderr: None,
-            code: -1,
-            error: Some(e.to_string()),
-        }),
+    match uninstall_package(&package, purge, opts).await {
+        Ok(r) => Ok(redact_resp(r, &redactor)),
+        Err(e) => Ok(redact_resp(error_resp(e), &redactor)),
     }
 }
 
 /// Tauri 命令：确保 Scoop 已安装（未安装则执行安装脚本）
 #[tauri::command]
 pub async fn scoop_ensure(
+    app: AppHandle,
     dry_run: Option<bool>,
     timeout_seconds: Option<u64>,
+    profile: Option<String>,
 ) -> Result<DetectCmdResp, String> {
+    let failure = |error: String| DetectCmdResp {
+        ok: false,
+        installed: false,
+        version: None,
+        error: Some(error),
+        cached: false,
+    };
+    let (profile, redactor) = match load_profile(&app, profile.as_deref()) {
+        Ok(p) => p,
+        Err(e) => return Ok(failure(e)),
+    };
     let opts = BootstrapOptions {
         timeout_seconds,
         dry_run,
+        profile,
     };
     match ensure_scoop_installed(opts).await {
         Ok(d) => Ok(DetectCmdResp {
@@ -633,13 +1115,7 @@ pub async fn scoop_ensure(
             error: None,
             cached: d.cached,
         }),
-        Err(e) => Ok(DetectCmdResp {
-            ok: false,
-            installed: false,
-            version: None,
-            error: Some(e.to_string()),
-            cached: false,
-        }),
+        Err(e) => Ok(failure(redactor.redact(&e.to_string()))),
     }
 }
 
@@ -673,6 +1149,22 @@ mod tests {
         .unwrap();
         assert!(r2.ok);
         assert!(r2.stdout.unwrap().contains("scoop uninstall"));
+
+        let r3 = uninstall_package(
+            "python",
+            true,
+            InstallOptions {
+                dry_run: Some(true),
+                global: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            r3.stdout.as_deref(),
+            Some("scoop uninstall --global --purge python")
+        );
     }
 
     #[tokio::test]
@@ -696,11 +1188,96 @@ mod tests {
         assert!(c2.unwrap().cached);
     }
 
+    #[test]
+    fn test_parse_manifest() {
+        assert_eq!(app_name("main/nginx@1.25.3"), "nginx");
+        assert_eq!(app_name(" redis "), "redis");
+
+        let m = parse_manifest(
+            r#"{"version": "1.25.3", "bin": ["nginx.exe", ["tools\\ctl.exe", "nginxctl", "--quiet"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(m.version.as_deref(), Some("1.25.3"));
+        assert_eq!(m.bin.len(), 2);
+        assert_eq!(m.bin[1].alias.as_deref(), Some("nginxctl"));
+        assert_eq!(m.bin[1].args, vec!["--quiet"]);
+
+        let m = parse_manifest(
+            r#"{"architecture": {"64bit": {"bin": "minio.exe"}, "32bit": {"bin": "x86\\minio.exe"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(m.bin[0].path, "minio.exe");
+        assert!(parse_manifest(r#"{"version": "1"}"#)
+            .unwrap()
+            .bin
+            .is_empty());
+        assert!(parse_manifest("not json").is_err());
+    }
+
+    #[test]
+    fn test_analyze_uninstall() {
+        let root = std::env::temp_dir().join(format!("scoop-impact-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let write = |rel: &str, text: &str| {
+            let path = root.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        };
+        write(
+            "apps/redis/current/manifest.json",
+            r#"{"bin": ["redis-server.exe", ["redis-cli.exe", "rcli"]], "persist": ["data", ["conf\\redis.conf", "redis.conf"]]}"#,
+        );
+        write(
+            "apps/app2/current/manifest.json",
+            r#"{"depends": "main/redis"}"#,
+        );
+        write(
+            "apps/other/current/manifest.json",
+            r#"{"depends": ["git"]}"#,
+        );
+        write("persist/redis/data/dump.rdb", "12345");
+        write("shims/redis-server.exe", "");
+        write("shims/redis-server.shim", "");
+        write("shims/rcli.exe", "");
+        write("shims/git.exe", "");
+
+        let impact = analyze_uninstall_in(&root, "redis", true);
+        assert!(impact.installed);
+        assert_eq!(impact.persist.len(), 2);
+        assert_eq!(impact.persist[0].bytes, 5);
+        assert!(!impact.persist[1].exists);
+        assert_eq!(impact.dependents, vec!["app2"]);
+        assert_eq!(impact.shims.len(), 3);
+        assert!(impact.has_impact());
+        assert!(impact.summary().contains("5 字节"));
+
+        // 不 purge 时持久化数据不计入影响
+        let impact = UninstallImpact {
+            dependents: Vec::new(),
+            shims: Vec::new(),
+            ..analyze_uninstall_in(&root, "redis", false)
+        };
+        assert!(!impact.has_impact());
+        assert!(!analyze_uninstall_in(&root, "missing", true).has_impact());
+
+        assert!(is_under(
+            "C:\\Scoop\\apps\\redis\\current\\redis-server.exe",
+            Path::new("c:/scoop/apps/redis")
+        ));
+        assert!(!is_under(
+            "C:\\Scoop\\apps\\redis2\\x.exe",
+            Path::new("C:\\Scoop\\apps\\redis")
+        ));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     #[tokio::test]
     async fn test_install_scoop_dry_run() {
         let r = install_scoop(BootstrapOptions {
             dry_run: Some(true),
             timeout_seconds: Some(1),
+            ..Default::default()
         })
         .await
         .unwrap();
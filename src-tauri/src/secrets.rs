@@ -0,0 +1,728 @@
+//! 服务密钥存储
+//!
+//! 密码、令牌等敏感值只保存在加密的密钥库中，配置与环境变量中仅写引用：
+//! - 环境变量值（服务目录的 `env_vars`、请求中的 `env_vars`）：`${secret:NAME}`，可与其他文本拼接；
+//! - WinSW 配置文件：`%SECRET_NAME%`。WinSW 会展开配置中的环境变量，执行时注入同名变量即可，
+//!   例如 `<serviceaccount><password>%SECRET_SVC_PASSWORD%</password></serviceaccount>`
+//!   引用名为 `SVC_PASSWORD` 的密钥，配置文件中始终不出现明文。
+//!
+//!   注入的变量只存在于本应用启动的 WinSW 进程中，因此配置中的引用仅对 `install` 时读取的
+//!   [`INSTALL_TIME_ELEMENTS`] 有效；`<env>`、`<arguments>` 等由服务控制管理器启动服务时
+//!   才展开的字段中看不到这些变量，引用会原样传给被包装的程序。需要运行时使用的密钥应通过
+//!   服务目录的 `env_vars`（`${secret:NAME}`）在执行操作时注入。
+//!
+//! 引用只在启动进程前解析，解析出的值不会写入任何文件或响应；进程输出中出现的密钥值会被替换为 `******`。
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const FILE_VERSION: u32 = 1;
+
+/// 脱敏后的占位值
+pub const REDACTED: &str = "******";
+
+/// 配置文件中引用密钥时使用的环境变量前缀
+pub const CONFIG_ENV_PREFIX: &str = "SECRET_";
+
+/// 由 `install` 读取的配置元素，只有其中的 `%SECRET_NAME%` 引用能被展开
+pub const INSTALL_TIME_ELEMENTS: &[&str] = &["serviceaccount"];
+
+/// 短于此长度的值不参与输出脱敏，避免误替换大量普通文本；
+/// 密钥库拒绝保存更短的值，因此引用解析出的值总会被脱敏
+const MIN_REDACT_LEN: usize = 4;
+
+/// 变量名中包含以下片段（不区分大小写）即视为敏感
+const SECRET_MARKERS: &[&str] = &[
+    "PASSWORD",
+    "PASSWD",
+    "SECRET",
+    "TOKEN",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    "CREDENTIAL",
+];
+
+/// 密钥库读写锁，避免并发修改交错写入
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("密钥名称无效: '{0}'（仅允许字母、数字与 '_'）")]
+    InvalidName(String),
+    #[error("密钥 '{0}' 不存在")]
+    NotFound(String),
+    #[error("密钥 '{0}' 的值过短（至少 {1} 个字符），过短的值无法在输出中可靠地隐藏")]
+    ValueTooShort(String, usize),
+    #[error("读写密钥库失败: {0}")]
+    Io(String),
+    #[error("密钥库格式错误: {0}")]
+    Format(String),
+    #[error("密钥 '{0}' 解密失败（密钥文件不匹配或数据已损坏）")]
+    Decrypt(String),
+    #[error("无法定位应用数据目录: {0}")]
+    DataDirUnavailable(String),
+}
+
+fn io_err(e: std::io::Error) -> SecretError {
+    SecretError::Io(e.to_string())
+}
+
+/// 变量名是否表示敏感值
+pub fn is_secret_name(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|m| upper.contains(m))
+}
+
+/// 对环境变量脱敏，并按名称排序
+pub fn redact_env(env: &HashMap<String, String>) -> BTreeMap<String, String> {
+    env.iter()
+        .map(|(k, v)| {
+            let v = if is_secret_name(k) {
+                REDACTED.to_string()
+            } else {
+                v.clone()
+            };
+            (k.clone(), v)
+        })
+        .collect()
+}
+
+/// 密钥的元数据（不含值）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SecretInfo {
+    pub name: String,
+    pub updated_at: u64,
+}
+
+/// 密钥存储后端
+///
+/// 值只通过 [`SecretStore::get`] 在执行时读取，列表只返回名称。
+pub trait SecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError>;
+    fn set(&mut self, name: &str, value: &str) -> Result<(), SecretError>;
+    /// 返回是否存在并已删除
+    fn remove(&mut self, name: &str) -> Result<bool, SecretError>;
+    fn list(&self) -> Result<Vec<SecretInfo>, SecretError>;
+}
+
+/// 校验密钥名称
+pub fn validate_name(name: &str) -> Result<(), SecretError> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(())
+    } else {
+        Err(SecretError::InvalidName(name.to_string()))
+    }
+}
+
+/// 校验密钥值：必须足够长才能在输出中脱敏
+pub fn validate_value(name: &str, value: &str) -> Result<(), SecretError> {
+    if value.len() >= MIN_REDACT_LEN {
+        Ok(())
+    } else {
+        Err(SecretError::ValueTooShort(name.to_string(), MIN_REDACT_LEN))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedEntry {
+    nonce: String,
+    ciphertext: String,
+    updated_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    entries: BTreeMap<String, EncryptedEntry>,
+}
+
+/// 加密文件后端：每个值单独以 ChaCha20-Poly1305 加密，名称作为附加数据防止条目被互换
+///
+/// 主密钥保存在同目录的 `secrets.key` 中，首次使用时随机生成：Windows 下以 DPAPI 加密
+/// （只有当前用户能解密），其他平台以 0600 权限保存原始字节。
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    file: StoreFile,
+}
+
+impl EncryptedFileStore {
+    /// 打开目录中的密钥库，文件不存在时创建空库与新的主密钥
+    pub fn open(dir: &Path) -> Result<Self, SecretError> {
+        std::fs::create_dir_all(dir).map_err(io_err)?;
+        let key = load_or_create_key(&dir.join("secrets.key"))?;
+        let path = dir.join("secrets.json");
+        let file = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<StoreFile>(&text)
+                .map_err(|e| SecretError::Format(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StoreFile {
+                version: FILE_VERSION,
+                entries: BTreeMap::new(),
+            },
+            Err(e) => return Err(io_err(e)),
+        };
+        if file.version != FILE_VERSION {
+            return Err(SecretError::Format(format!(
+                "不支持的版本 {}",
+                file.version
+            )));
+        }
+        Ok(Self {
+            path,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            file,
+        })
+    }
+
+    /// 写回文件（先写临时文件再重命名）
+    fn save(&self) -> Result<(), SecretError> {
+        let text = serde_json::to_string_pretty(&self.file)
+            .map_err(|e| SecretError::Format(e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, text).map_err(io_err)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &str) -> Result<Option<String>, SecretError> {
+        let Some(entry) = self.file.entries.get(name) else {
+            return Ok(None);
+        };
+        let decode = |s: &str| {
+            BASE64
+                .decode(s)
+                .map_err(|e| SecretError::Format(e.to_string()))
+        };
+        let nonce = decode(&entry.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(SecretError::Format(format!(
+                "密钥 '{}' 的 nonce 长度错误",
+                name
+            )));
+        }
+        let plain = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &decode(&entry.ciphertext)?,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| SecretError::Decrypt(name.to_string()))?;
+        String::from_utf8(plain)
+            .map(Some)
+            .map_err(|_| SecretError::Decrypt(name.to_string()))
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), SecretError> {
+        validate_name(name)?;
+        validate_value(name, value)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| SecretError::Io(e.to_string()))?;
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| SecretError::Format("加密失败".to_string()))?;
+        self.file.entries.insert(
+            name.to_string(),
+            EncryptedEntry {
+                nonce: BASE64.encode(nonce),
+                ciphertext: BASE64.encode(ciphertext),
+                updated_at: now_secs(),
+            },
+        );
+        self.save()
+    }
+
+    fn remove(&mut self, name: &str) -> Result<bool, SecretError> {
+        if self.file.entries.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn list(&self) -> Result<Vec<SecretInfo>, SecretError> {
+        Ok(self
+            .file
+            .entries
+            .iter()
+            .map(|(name, e)| SecretInfo {
+                name: name.clone(),
+                updated_at: e.updated_at,
+            })
+            .collect())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 读取主密钥，不存在时生成
+fn load_or_create_key(path: &Path) -> Result<[u8; KEY_LEN], SecretError> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            #[cfg(windows)]
+            if bytes.len() == KEY_LEN {
+                // 旧版本保存的未加密主密钥，改为 DPAPI 保护
+                let tmp = path.with_extension("key.tmp");
+                seal_key(&bytes)
+                    .and_then(|sealed| std::fs::write(&tmp, sealed))
+                    .and_then(|_| std::fs::rename(&tmp, path))
+                    .map_err(io_err)?;
+                return load_or_create_key(path);
+            }
+            unseal_key(&bytes)
+                .map_err(|e| {
+                    SecretError::Format(format!("无法解密主密钥 {}: {}", path.display(), e))
+                })?
+                .try_into()
+                .map_err(|_| SecretError::Format(format!("主密钥长度错误: {}", path.display())))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut key = [0u8; KEY_LEN];
+            getrandom::getrandom(&mut key).map_err(|e| SecretError::Io(e.to_string()))?;
+            match seal_key(&key).and_then(|sealed| write_private(path, &sealed)) {
+                Ok(()) => Ok(key),
+                // 并发打开时由另一方先生成，改为读取
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => load_or_create_key(path),
+                Err(e) => Err(io_err(e)),
+            }
+        }
+        Err(e) => Err(io_err(e)),
+    }
+}
+
+/// 落盘前保护主密钥：Windows 下以 DPAPI 加密，其他平台依赖文件权限，原样保存
+#[cfg(windows)]
+fn seal_key(key: &[u8]) -> std::io::Result<Vec<u8>> {
+    dpapi::protect(key)
+}
+
+#[cfg(not(windows))]
+fn seal_key(key: &[u8]) -> std::io::Result<Vec<u8>> {
+    Ok(key.to_vec())
+}
+
+#[cfg(windows)]
+fn unseal_key(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    dpapi::unprotect(data)
+}
+
+#[cfg(not(windows))]
+fn unseal_key(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    Ok(data.to_vec())
+}
+
+#[cfg(windows)]
+mod dpapi {
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+        CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr() as *mut u8,
+        }
+    }
+
+    /// 复制 DPAPI 分配的输出并释放
+    ///
+    /// # Safety
+    /// `out` 须为 DPAPI 调用成功后返回的缓冲区
+    unsafe fn take(out: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data = std::slice::from_raw_parts(out.pbData, out.cbData as usize).to_vec();
+        LocalFree(out.pbData as _);
+        data
+    }
+
+    /// 以当前用户的凭据加密
+    pub(super) fn protect(data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let input = blob(data);
+        let mut out = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: std::ptr::null_mut(),
+        };
+        // SAFETY: 输入缓冲区在调用期间有效，DPAPI 只读取；可选参数均为空指针
+        let ok = unsafe {
+            CryptProtectData(
+                &input,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut out,
+            )
+        };
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: 调用成功，out 由 DPAPI 分配
+        Ok(unsafe { take(out) })
+    }
+
+    /// 解密 [`protect`] 的输出，只有加密时的用户能解密
+    pub(super) fn unprotect(data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let input = blob(data);
+        let mut out = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: std::ptr::null_mut(),
+        };
+        // SAFETY: 同 protect；不读取描述字符串
+        let ok = unsafe {
+            CryptUnprotectData(
+                &input,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut out,
+            )
+        };
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: 调用成功，out 由 DPAPI 分配
+        Ok(unsafe { take(out) })
+    }
+}
+
+/// 创建新文件；Unix 下权限为 0600（仅当前用户可读写），Windows 下内容由调用方以 DPAPI 保护
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path).and_then(|mut f| f.write_all(content))
+}
+
+/// 应用数据目录下的密钥库目录
+fn store_dir(app: &AppHandle) -> Result<PathBuf, SecretError> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("secrets"))
+        .map_err(|e| SecretError::DataDirUnavailable(e.to_string()))
+}
+
+/// 打开应用的密钥库
+pub fn open_store(app: &AppHandle) -> Result<EncryptedFileStore, SecretError> {
+    EncryptedFileStore::open(&store_dir(app)?)
+}
+
+fn env_ref_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$\{secret:([A-Za-z0-9_]+)\}").expect("secret ref regex"))
+}
+
+fn config_ref_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"%SECRET_([A-Za-z0-9_]+)%").expect("config ref regex"))
+}
+
+/// 环境变量值中引用的密钥名称
+pub fn env_refs(value: &str) -> impl Iterator<Item = &str> {
+    env_ref_regex()
+        .captures_iter(value)
+        .filter_map(|c| c.get(1).map(|m| m.as_str()))
+}
+
+/// 配置文件中引用的密钥名称（`%SECRET_NAME%`）
+pub fn config_refs(text: &str) -> BTreeSet<String> {
+    config_ref_regex()
+        .captures_iter(text)
+        .filter_map(|c| c.get(1).map(|m| m.as_str().to_string()))
+        .collect()
+}
+
+/// 是否包含密钥引用
+pub fn has_refs(value: &str) -> bool {
+    env_ref_regex().is_match(value)
+}
+
+/// 输出脱敏：将已解析的密钥值替换为占位符
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    values: Vec<String>,
+}
+
+impl Redactor {
+    pub fn add(&mut self, value: &str) {
+        if value.len() >= MIN_REDACT_LEN && !self.values.iter().any(|v| v == value) {
+            self.values.push(value.to_string());
+            // 长值优先替换，避免较短的值先命中其中一部分
+            self.values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// 合并另一组已解析的值
+    pub fn merge(&mut self, other: &Redactor) {
+        for v in &other.values {
+            self.add(v);
+        }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .fold(text.to_string(), |acc, v| acc.replace(v.as_str(), REDACTED))
+    }
+}
+
+/// 执行时解析后的环境变量
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedEnv {
+    /// 引用已替换为实际值的环境变量，仅用于启动进程
+    pub vars: HashMap<String, String>,
+    pub redactor: Redactor,
+}
+
+/// 解析环境变量中的 `${secret:NAME}` 引用，以及配置文件中 `%SECRET_NAME%` 所需的变量
+pub fn resolve(
+    store: &dyn SecretStore,
+    env: Option<&HashMap<String, String>>,
+    config_text: Option<&str>,
+) -> Result<ResolvedEnv, SecretError> {
+    let mut resolved = ResolvedEnv::default();
+    let lookup = |name: &str, redactor: &mut Redactor| -> Result<String, SecretError> {
+        let value = store
+            .get(name)?
+            .ok_or_else(|| SecretError::NotFound(name.to_string()))?;
+        redactor.add(&value);
+        Ok(value)
+    };
+
+    for (key, value) in env.into_iter().flatten() {
+        let mut out = String::with_capacity(value.len());
+        let mut last = 0;
+        for caps in env_ref_regex().captures_iter(value) {
+            let whole = caps.get(0).expect("match");
+            out.push_str(&value[last..whole.start()]);
+            out.push_str(&lookup(&caps[1], &mut resolved.redactor)?);
+            last = whole.end();
+        }
+        out.push_str(&value[last..]);
+        resolved.vars.insert(key.clone(), out);
+    }
+
+    for name in config_text.map(config_refs).unwrap_or_default() {
+        let key = format!("{}{}", CONFIG_ENV_PREFIX, name);
+        if !resolved.vars.contains_key(&key) {
+            let value = lookup(&name, &mut resolved.redactor)?;
+            resolved.vars.insert(key, value);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// 解析执行目标的环境变量；没有任何引用时不打开密钥库
+pub fn resolve_for_app(
+    app: &AppHandle,
+    env: Option<&HashMap<String, String>>,
+    config_text: Option<&str>,
+) -> Result<ResolvedEnv, SecretError> {
+    let env_has_refs = env.is_some_and(|e| e.values().any(|v| has_refs(v)));
+    let config_has_refs = config_text.is_some_and(|t| config_ref_regex().is_match(t));
+    if !env_has_refs && !config_has_refs {
+        return Ok(ResolvedEnv {
+            vars: env.cloned().unwrap_or_default(),
+            redactor: Redactor::default(),
+        });
+    }
+    resolve(&open_store(app)?, env, config_text)
+}
+
+/// 在密钥库中不存在的引用（用于预览与校验，不读取任何值）
+pub fn missing_refs(
+    known: &[SecretInfo],
+    env: Option<&HashMap<String, String>>,
+    config_text: Option<&str>,
+) -> Vec<String> {
+    let mut names: BTreeSet<String> = env
+        .into_iter()
+        .flat_map(|e| e.values())
+        .flat_map(|v| env_refs(v).map(str::to_string))
+        .collect();
+    names.extend(config_text.map(config_refs).unwrap_or_default());
+    names
+        .into_iter()
+        .filter(|n| !known.iter().any(|k| &k.name == n))
+        .collect()
+}
+
+/// Tauri 命令：保存（新增或覆盖）密钥
+#[tauri::command]
+pub async fn secret_set(app: AppHandle, name: String, value: String) -> Result<SecretInfo, String> {
+    let _guard = STORE_LOCK.lock().expect("secret store lock");
+    let mut store = open_store(&app).map_err(|e| e.to_string())?;
+    store.set(&name, &value).map_err(|e| e.to_string())?;
+    store
+        .list()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| SecretError::NotFound(name).to_string())
+}
+
+/// Tauri 命令：删除密钥，返回是否存在
+#[tauri::command]
+pub async fn secret_remove(app: AppHandle, name: String) -> Result<bool, String> {
+    let _guard = STORE_LOCK.lock().expect("secret store lock");
+    let mut store = open_store(&app).map_err(|e| e.to_string())?;
+    store.remove(&name).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：列出密钥名称（不返回值）
+#[tauri::command]
+pub async fn secret_list(app: AppHandle) -> Result<Vec<SecretInfo>, String> {
+    let _guard = STORE_LOCK.lock().expect("secret store lock");
+    open_store(&app)
+        .and_then(|s| s.list())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("secrets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_redact_env() {
+        let env = HashMap::from([
+            ("DB_PASSWORD".to_string(), "hunter2".to_string()),
+            ("github_token".to_string(), "abc".to_string()),
+            ("APP_HOME".to_string(), "C:\\app".to_string()),
+        ]);
+        let redacted = redact_env(&env);
+        assert_eq!(redacted["DB_PASSWORD"], REDACTED);
+        assert_eq!(redacted["github_token"], REDACTED);
+        assert_eq!(redacted["APP_HOME"], "C:\\app");
+    }
+
+    #[test]
+    fn test_encrypted_file_store_roundtrip() {
+        let dir = temp_dir("store");
+        let mut store = EncryptedFileStore::open(&dir).unwrap();
+        store.set("DB_PASS", "hunter2-very-secret").unwrap();
+        assert!(store.set("bad name", "hunter2").is_err());
+        assert!(matches!(
+            store.set("PIN", "123"),
+            Err(SecretError::ValueTooShort(_, MIN_REDACT_LEN))
+        ));
+
+        // 文件中没有明文
+        let raw = std::fs::read_to_string(dir.join("secrets.json")).unwrap();
+        assert!(raw.contains("DB_PASS"));
+        assert!(!raw.contains("hunter2"));
+
+        let reopened = EncryptedFileStore::open(&dir).unwrap();
+        assert_eq!(
+            reopened.get("DB_PASS").unwrap().as_deref(),
+            Some("hunter2-very-secret")
+        );
+        assert_eq!(reopened.get("OTHER").unwrap(), None);
+        assert_eq!(reopened.list().unwrap()[0].name, "DB_PASS");
+
+        // 条目被挪到其他名称下时无法解密
+        let tampered = raw.replace("\"DB_PASS\"", "\"API_KEY\"");
+        std::fs::write(dir.join("secrets.json"), tampered).unwrap();
+        let store2 = EncryptedFileStore::open(&dir).unwrap();
+        assert!(matches!(
+            store2.get("API_KEY"),
+            Err(SecretError::Decrypt(_))
+        ));
+
+        let mut store3 = EncryptedFileStore::open(&dir).unwrap();
+        assert!(store3.remove("API_KEY").unwrap());
+        assert!(!store3.remove("API_KEY").unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_and_redact() {
+        let dir = temp_dir("resolve");
+        let mut store = EncryptedFileStore::open(&dir).unwrap();
+        store.set("DB_PASS", "p@ssw0rd!").unwrap();
+        store.set("SVC_PASSWORD", "svc-secret-1").unwrap();
+
+        let env = HashMap::from([
+            (
+                "DATABASE_URL".to_string(),
+                "postgres://app:${secret:DB_PASS}@localhost/app".to_string(),
+            ),
+            ("LOG_LEVEL".to_string(), "info".to_string()),
+        ]);
+        let config = "<service><serviceaccount><password>%SECRET_SVC_PASSWORD%</password></serviceaccount></service>";
+
+        let resolved = resolve(&store, Some(&env), Some(config)).unwrap();
+        assert_eq!(
+            resolved.vars["DATABASE_URL"],
+            "postgres://app:p@ssw0rd!@localhost/app"
+        );
+        assert_eq!(resolved.vars["LOG_LEVEL"], "info");
+        assert_eq!(resolved.vars["SECRET_SVC_PASSWORD"], "svc-secret-1");
+        assert_eq!(
+            resolved
+                .redactor
+                .redact("login svc-secret-1 with p@ssw0rd! failed"),
+            "login ****** with ****** failed"
+        );
+
+        // 缺失的引用报错，并在预览时列出
+        let missing_env = HashMap::from([("X".to_string(), "${secret:NOPE}".to_string())]);
+        assert!(matches!(
+            resolve(&store, Some(&missing_env), None),
+            Err(SecretError::NotFound(n)) if n == "NOPE"
+        ));
+        assert_eq!(
+            missing_refs(&store.list().unwrap(), Some(&missing_env), Some(config)),
+            vec!["NOPE".to_string()]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,34 @@
+pub mod bundle;
+pub mod catalog;
+pub mod command;
+pub mod config;
+pub mod deploy;
+pub mod discover;
+pub mod group;
+pub mod health;
+pub mod history;
+pub mod lifecycle;
+pub mod logs;
+pub mod policy;
+pub mod preview;
+pub mod provision;
+pub mod scoop_app;
+pub mod template;
+pub mod watchdog;
+
+use crate::env_profile::{self, EnvOverlay};
+use crate::exec::{self, ExecError, ExecRequest, ExecResult, LineSink, OutputOptions};
+use crate::secrets::{self, ResolvedEnv};
+use health::{HealthReport, HealthSpec, HealthWaiter};
+use provision::{WinswBinary, WinswMajor};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
-use std::process::Stdio;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager};
 use thiserror::Error;
-use tokio::io::AsyncReadExt;
-use tokio::process::Command;
-use tokio::time::{timeout, Duration};
+
+pub use crate::exec::{OutputLine, OutputStream};
 
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 const DEFAULT_WINSW_PATH: &str = "winsw.exe";
@@ -32,55 +55,223 @@ pub enum WinswError {
     SpawnFailed(#[from] std::io::Error),
     #[error("等待 WinSW 输出失败: {0}")]
     WaitFailed(String),
-    #[error("WinSW 操作超时 ({0}s)")]
-    Timeout(u64),
+    /// 结果中包含触发的超时与超时前的最近输出
+    #[error("WinSW 操作超时: {}", .0.error.as_deref().unwrap_or_default())]
+    Timeout(Box<ExecResult>),
+    /// 通过 `exec_cancel` 取消，结果中包含取消前的最近输出
+    #[error("WinSW 操作已取消")]
+    Cancelled(Box<ExecResult>),
     #[error("配置文件不存在: {0}")]
     ConfigNotFound(String),
+    #[error("无法定位应用数据目录: {0}")]
+    DataDirUnavailable(String),
+    #[error("服务 ID 无效: '{0}'（仅允许字母、数字、'-'、'_'、'.'）")]
+    InvalidServiceId(String),
+    #[error("服务 '{0}' 已存在")]
+    DuplicateService(String),
+    #[error("服务 '{0}' 不存在")]
+    ServiceNotFound(String),
+    #[error("读写服务目录失败: {0}")]
+    CatalogIo(String),
+    #[error("服务目录格式错误: {0}")]
+    CatalogParse(String),
+    #[error("需要提供服务 ID (service_id) 或配置文件路径 (config)")]
+    ServiceRefRequired,
+    #[error("读取配置文件失败: {0}")]
+    ConfigRead(String),
+    #[error("配置文件格式错误: {0}")]
+    ConfigInvalid(String),
+    #[error("服务组 '{0}' 不存在")]
+    GroupNotFound(String),
+    #[error("服务依赖存在循环: {0}")]
+    DependencyCycle(String),
+    #[error("找不到 WinSW 可执行文件: {0}")]
+    BinaryNotFound(String),
+    #[error("读取 WinSW 可执行文件失败: {0}")]
+    BinaryRead(String),
+    #[error("WinSW 可执行文件未通过校验: {path} (sha256: {sha256})")]
+    BinaryUnverified { path: String, sha256: String },
+    #[error("读写 WinSW 定位设置失败: {0}")]
+    ProvisionIo(String),
+    #[error("WinSW 定位设置格式错误: {0}")]
+    ProvisionParse(String),
+    #[error("WinSW v2 只能使用与可执行文件同目录、同名的配置文件: 期望 {expected}，实际 {actual}")]
+    V2ConfigLayout { expected: String, actual: String },
+    #[error("WinSW {version} 不支持命令或选项: {command}")]
+    UnsupportedByVersion { command: String, version: String },
+    #[error("命令选项无效: {0}")]
+    InvalidOption(String),
+    #[error("读写文件失败: {0}")]
+    FileIo(String),
+    #[error("配置历史中不存在版本 {0}")]
+    RevisionNotFound(u64),
+    #[error("时长格式无效: '{0}'（示例: \"10 sec\"、\"1 min\"、\"500 ms\"）")]
+    InvalidDuration(String),
+    #[error("服务包无效: {0}")]
+    Bundle(String),
+    #[error("{0}")]
+    Secret(#[from] crate::secrets::SecretError),
+    #[error("{0}")]
+    Profile(#[from] crate::env_profile::ProfileError),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct ActionReq {
+    /// 服务目录中的服务 ID，提供时使用目录中记录的配置文件与 WinSW 路径
+    service_id: Option<String>,
     /// WinSW 可执行文件路径，默认为 "winsw.exe"
     winsw_path: Option<String>,
     /// 配置文件路径（XML 格式）
     config: Option<String>,
     /// 超时时间（秒），默认为 30
     timeout_seconds: Option<u64>,
+    /// 持续无输出超过该秒数时终止，与总超时同时生效
+    idle_timeout_seconds: Option<u64>,
+    /// 输出的解码与样式选项（代码页、是否返回颜色分段）
+    output: Option<OutputOptions>,
+    /// 调用 ID，执行中可通过 `exec_cancel` 取消
+    invocation_id: Option<String>,
     /// 自定义环境变量
     env_vars: Option<HashMap<String, String>>,
+    /// 启动后的健康检查，未提供时使用服务目录中的定义
+    health: Option<HealthSpec>,
+    /// 仅解析并返回将要执行的调用，不启动任何进程
+    dry_run: Option<bool>,
+    /// 使用的环境配置名称（如 "prod"）
+    profile: Option<String>,
+}
+
+/// 解析后的执行目标
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActionTarget {
+    /// 服务目录中的服务 ID（直接给出路径时为 None）
+    pub service_id: Option<String>,
+    pub winsw_path: String,
+    pub config: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+    pub health: Option<HealthSpec>,
+    /// 选中的环境配置
+    pub profile: Option<EnvOverlay>,
+    /// 空闲超时（秒）
+    pub idle_timeout_secs: Option<u64>,
+    /// 输出的解码与样式选项
+    pub output: OutputOptions,
+    /// 调用 ID，可通过 `exec_cancel` 取消执行
+    pub invocation_id: Option<String>,
+}
+
+impl ActionTarget {
+    /// 由目录中的服务构建执行目标，并加载其环境配置
+    pub(crate) fn from_entry(
+        app: &AppHandle,
+        entry: &catalog::ServiceEntry,
+    ) -> Result<Self, WinswError> {
+        let profile = match entry.profile.as_deref() {
+            Some(name) => Some(env_profile::load_overlay(app, name)?),
+            None => None,
+        };
+        Ok(Self {
+            service_id: Some(entry.id.clone()),
+            winsw_path: entry.winsw_path.clone(),
+            config: Some(entry.config_path.clone()),
+            env: Some(entry.env_vars.clone()),
+            health: entry.health.clone(),
+            profile,
+            ..Default::default()
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ActionResp {
-    ok: bool,
-    stdout: Option<String>,
-    stderr: Option<String>,
-    code: i32,
-    error: Option<String>,
+    /// 进程执行结果（与 scoop 共用）
+    #[serde(flatten)]
+    exec: ExecResult,
+    /// start / restart 之后的健康检查结果
+    health: Option<HealthReport>,
+    /// dry_run 时返回的调用预览
+    dry_run: Option<preview::DryRunPlan>,
 }
 
-impl ActionResp {
-    fn success(stdout: Option<String>, stderr: Option<String>, code: i32) -> Self {
+impl From<ExecResult> for ActionResp {
+    fn from(exec: ExecResult) -> Self {
         Self {
-            ok: true,
-            stdout,
-            stderr,
-            code,
-            error: None,
+            exec,
+            health: None,
+            dry_run: None,
         }
     }
+}
+
+impl ActionResp {
+    fn success(stdout: Option<String>, stderr: Option<String>, code: i32) -> Self {
+        ExecResult::success(stdout, stderr, code).into()
+    }
 
     fn failure(code: i32, error: String) -> Self {
+        ExecResult::failure(code, error).into()
+    }
+
+    /// 错误转换为响应；超时保留触发的超时与最近输出
+    fn from_error(e: WinswError) -> Self {
+        match e {
+            WinswError::Timeout(result) | WinswError::Cancelled(result) => (*result).into(),
+            e => Self::failure(-1, e.to_string()),
+        }
+    }
+
+    /// 预览结果：stdout 为命令行，与 scoop 的 dry_run 一致
+    fn preview(plan: preview::DryRunPlan) -> Self {
         Self {
-            ok: false,
-            stdout: None,
-            stderr: None,
-            code,
-            error: Some(error),
+            dry_run: Some(plan.clone()),
+            ..Self::success(Some(plan.command_line()), None, 0)
         }
     }
 }
 
+/// WinSW 模块在应用数据目录下的存储目录
+pub(crate) fn data_dir(app: &AppHandle) -> Result<PathBuf, WinswError> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join("winsw"))
+        .map_err(|e| WinswError::DataDirUnavailable(e.to_string()))
+}
+
+/// 是否为启动类操作（完成后需要进行健康检查）
+fn starts_service(action: &str) -> bool {
+    matches!(action, "start" | "restart" | "restart!")
+}
+
+/// `status` 报告的服务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceStatus {
+    /// 服务未安装
+    NonExistent,
+    Stopped,
+    Running,
+    /// 正在启动或停止
+    Pending,
+    Unknown,
+}
+
+/// 解析 `status` 输出：v2 为 `Started` / `Stopped` / `NonExistent`，
+/// v3 为 `Active (running)` / `Inactive (stopped)` / `NonExistent` 等
+pub(crate) fn parse_status(output: &str) -> ServiceStatus {
+    let text = output.to_lowercase();
+    if text.contains("nonexistent") {
+        ServiceStatus::NonExistent
+    } else if text.contains("starting") || text.contains("stopping") || text.contains("pending") {
+        ServiceStatus::Pending
+    } else if text.contains("inactive") || text.contains("stopped") {
+        ServiceStatus::Stopped
+    } else if text.contains("active") || text.contains("started") || text.contains("running") {
+        ServiceStatus::Running
+    } else {
+        ServiceStatus::Unknown
+    }
+}
+
 /// 检查操作是否需要配置文件
 fn requires_config(action: &str) -> bool {
     matches!(
@@ -99,7 +290,14 @@ fn validate_action(action: &str) -> Result<String, WinswError> {
 }
 
 /// 构建 WinSW 命令参数
-fn build_command_args(action: &str, config: Option<&str>) -> Result<Vec<String>, WinswError> {
+///
+/// v3 将配置文件路径作为参数传入；v2 不接受该参数，只读取与可执行文件同目录、同名的 XML，
+/// 因此仅校验配置文件位置。版本未知时按 v3 处理。
+fn build_command_args(
+    action: &str,
+    config: Option<&str>,
+    binary: Option<&WinswBinary>,
+) -> Result<Vec<String>, WinswError> {
     let mut args = vec![action.to_string()];
 
     if requires_config(action) {
@@ -108,7 +306,10 @@ fn build_command_args(action: &str, config: Option<&str>) -> Result<Vec<String>,
             if !Path::new(cfg).exists() {
                 return Err(WinswError::ConfigNotFound(cfg.to_string()));
             }
-            args.push(cfg.to_string());
+            match binary {
+                Some(bin) if bin.major == Some(WinswMajor::V2) => check_v2_layout(&bin.path, cfg)?,
+                _ => args.push(cfg.to_string()),
+            }
         } else {
             return Err(WinswError::ConfigRequired(action.to_string()));
         }
@@ -117,29 +318,64 @@ fn build_command_args(action: &str, config: Option<&str>) -> Result<Vec<String>,
     Ok(args)
 }
 
-/// 获取增强的环境变量（合并系统环境和自定义环境）
-fn get_enhanced_env(custom_env: Option<&HashMap<String, String>>) -> HashMap<String, String> {
-    let mut env: HashMap<String, String> = std::env::vars().collect();
+/// v2 约定：配置文件须为 `<exe 所在目录>/<exe 文件名>.xml`
+fn check_v2_layout(exe: &Path, config: &str) -> Result<(), WinswError> {
+    let expected = exe.with_extension("xml");
+    let actual = Path::new(config);
+    let same_dir = match (expected.parent(), actual.parent()) {
+        (Some(a), Some(b)) => {
+            let a = a.canonicalize().unwrap_or_else(|_| a.to_path_buf());
+            let b = b.canonicalize().unwrap_or_else(|_| b.to_path_buf());
+            a == b
+        }
+        _ => false,
+    };
+    let same_name = match (expected.file_name(), actual.file_name()) {
+        (Some(a), Some(b)) => a
+            .to_string_lossy()
+            .eq_ignore_ascii_case(&b.to_string_lossy()),
+        _ => false,
+    };
+    if same_dir && same_name {
+        Ok(())
+    } else {
+        Err(WinswError::V2ConfigLayout {
+            expected: expected.display().to_string(),
+            actual: config.to_string(),
+        })
+    }
+}
 
-    // 确保关键的系统路径存在
-    if let Some(path) = env.get("PATH") {
-        let mut paths: Vec<String> = path.split(';').map(|s| s.to_string()).collect();
-
-        // 添加常见的 Windows 系统路径
-        let system_paths = vec![
-            r"C:\Windows\System32",
-            r"C:\Windows",
-            r"C:\Windows\System32\Wbem",
-            r"C:\Windows\System32\WindowsPowerShell\v1.0",
-        ];
-
-        for sys_path in system_paths {
-            if !paths.iter().any(|p| p.eq_ignore_ascii_case(sys_path)) {
-                paths.push(sys_path.to_string());
-            }
+/// 获取增强的环境变量
+///
+/// 依次合并系统环境、系统目录（基于 `SystemRoot`）、选中的环境配置与自定义环境变量。
+pub(crate) fn get_enhanced_env(
+    custom_env: Option<&HashMap<String, String>>,
+    profile: Option<&EnvOverlay>,
+) -> HashMap<String, String> {
+    let mut env = exec::inherited_env();
+
+    // 确保 SystemRoot 存在
+    let system_root = match env_profile::get_var(&env, "SystemRoot") {
+        Some(root) => root.trim_end_matches('\\').to_string(),
+        None => {
+            env.insert("SystemRoot".to_string(), r"C:\Windows".to_string());
+            r"C:\Windows".to_string()
         }
+    };
 
-        env.insert("PATH".to_string(), paths.join(";"));
+    // 确保关键的系统路径存在
+    if env_profile::get_var(&env, "PATH").is_some() {
+        let system_paths: Vec<String> = [
+            r"\System32",
+            "",
+            r"\System32\Wbem",
+            r"\System32\WindowsPowerShell\v1.0",
+        ]
+        .iter()
+        .map(|sub| format!("{}{}", system_root, sub))
+        .collect();
+        env_profile::append_path(&mut env, &system_paths);
     }
 
     // 确保 TEMP 和 TMP 环境变量存在
@@ -154,82 +390,239 @@ fn get_enhanced_env(custom_env: Option<&HashMap<String, String>>) -> HashMap<Str
         }
     }
 
-    // 确保 SystemRoot 存在
-    if !env.contains_key("SystemRoot") {
-        env.insert("SystemRoot".to_string(), r"C:\Windows".to_string());
-    }
+    // 合并环境配置与自定义环境变量（会覆盖现有值）
+    exec::overlay_env(&mut env, profile, custom_env);
+
+    env
+}
+
+/// 执行 WinSW 操作的核心逻辑
+async fn execute_winsw(
+    app: &AppHandle,
+    binary: &WinswBinary,
+    action: &str,
+    target: &ActionTarget,
+    timeout_secs: u64,
+    env: &ResolvedEnv,
+    on_line: Option<LineSink<'_>>,
+) -> Result<ActionResp, WinswError> {
+    // 构建命令参数
+    let args = build_command_args(action, target.config.as_deref(), Some(binary))?;
+
+    run_winsw(app, binary, &args, target, timeout_secs, env, on_line).await
+}
+
+/// 记录文件名中的标识：服务 ID 与操作
+fn transcript_label(target: &ActionTarget, args: &[String]) -> String {
+    format!(
+        "winsw-{}-{}",
+        target.service_id.as_deref().unwrap_or("adhoc"),
+        args.first().map(String::as_str).unwrap_or("run")
+    )
+}
 
-    // 合并自定义环境变量（会覆盖现有值）
-    if let Some(custom) = custom_env {
-        for (key, value) in custom {
-            env.insert(key.clone(), value.clone());
+/// 以给定参数运行 WinSW
+///
+/// 经由 [`exec::run`] 执行，超时后进程被终止；空闲超时与输出选项取自 `target`。
+/// 推送的输出行、返回的输出与完整输出记录中，已解析的密钥值均被替换为占位符。
+async fn run_winsw(
+    app: &AppHandle,
+    binary: &WinswBinary,
+    args: &[String],
+    target: &ActionTarget,
+    timeout_secs: u64,
+    resolved: &ResolvedEnv,
+    on_line: Option<LineSink<'_>>,
+) -> Result<ActionResp, WinswError> {
+    let redacting = |line: OutputLine| {
+        if let Some(sink) = on_line {
+            sink(line.redact(|s| resolved.redactor.redact(s)));
         }
+    };
+    let on_line: Option<LineSink<'_>> = on_line.map(|_| &redacting as LineSink<'_>);
+
+    let req = ExecRequest {
+        env: resolved.vars.clone(),
+        idle_timeout_secs: target.idle_timeout_secs,
+        output: target.output.clone(),
+        invocation_id: target.invocation_id.clone(),
+        // 状态查询（生命周期与看门狗的轮询）不写记录
+        transcript: match args.first().map(String::as_str) {
+            Some("status") => None,
+            _ => exec::transcript_options(app, &transcript_label(target, args)),
+        },
+        redactor: resolved.redactor.clone(),
+        ..ExecRequest::new(&binary.path, args.to_vec(), timeout_secs)
+    };
+    let result = exec::run(&req, on_line).await.map_err(|e| match e {
+        ExecError::Spawn { source, .. } => WinswError::SpawnFailed(source),
+        ExecError::Wait(e) => WinswError::WaitFailed(e),
+    })?;
+    let result = result.redact(|s| resolved.redactor.redact(s));
+    if result.timed_out() {
+        return Err(WinswError::Timeout(Box::new(result)));
+    }
+    if result.cancelled {
+        return Err(WinswError::Cancelled(Box::new(result)));
     }
 
-    env
+    Ok(result.into())
 }
 
-/// 读取进程输出流
-async fn read_output(mut stream: impl tokio::io::AsyncRead + Unpin) -> Option<String> {
-    let mut buf = Vec::new();
-    match stream.read_to_end(&mut buf).await {
-        Ok(_) if !buf.is_empty() => Some(String::from_utf8_lossy(&buf).to_string()),
-        _ => None,
-    }
+/// 在启动进程前构建完整环境，并解析环境配置、自定义变量与配置文件中的密钥引用
+pub(crate) fn resolve_env(
+    app: &AppHandle,
+    target: &ActionTarget,
+) -> Result<ResolvedEnv, WinswError> {
+    let config_text = target
+        .config
+        .as_deref()
+        .and_then(|c| std::fs::read_to_string(c).ok());
+    let custom = secrets::resolve_for_app(app, target.env.as_ref(), config_text.as_deref())?;
+    let (profile, mut redactor) = match &target.profile {
+        Some(p) => {
+            let (p, r) = p.resolve_secrets(app)?;
+            (Some(p), r)
+        }
+        None => (None, Default::default()),
+    };
+    redactor.merge(&custom.redactor);
+    Ok(ResolvedEnv {
+        vars: get_enhanced_env(Some(&custom.vars), profile.as_ref()),
+        redactor,
+    })
 }
 
-/// 执行 WinSW 操作的核心逻辑
-async fn execute_winsw(
-    winsw_path: &str,
+/// 对执行目标运行 WinSW 操作；目录中的服务同时更新其生命周期与看门狗状态
+pub(crate) async fn perform_action(
+    app: &AppHandle,
     action: &str,
-    config: Option<&str>,
+    target: &ActionTarget,
     timeout_secs: u64,
-    custom_env: Option<&HashMap<String, String>>,
+    on_line: Option<LineSink<'_>>,
 ) -> Result<ActionResp, WinswError> {
-    // 构建命令参数
-    let args = build_command_args(action, config)?;
-
-    // 获取增强的环境变量
-    let env = get_enhanced_env(custom_env);
-
-    // 启动 WinSW 进程
-    let mut child = Command::new(winsw_path)
-        .args(&args)
-        .envs(&env)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(WinswError::from)?;
-
-    // 等待进程退出，带超时控制
-    let status = match timeout(Duration::from_secs(timeout_secs), child.wait()).await {
-        Ok(Ok(s)) => s,
-        Ok(Err(e)) => return Err(WinswError::WaitFailed(e.to_string())),
-        Err(_) => {
-            // 超时，强制终止进程
-            let _ = child.kill().await;
-            let _ = child.wait().await;
-            return Err(WinswError::Timeout(timeout_secs));
+    let Some(id) = target.service_id.as_deref() else {
+        return execute_action(app, action, target, timeout_secs, on_line).await;
+    };
+
+    let _in_flight = lifecycle::begin_action(app, id, action);
+    let result = execute_action(app, action, target, timeout_secs, on_line).await;
+    lifecycle::finish_action(app, id, action, result.as_ref());
+    // 通过本应用停止的服务不应被看门狗拉起
+    watchdog::note_action(app, id, action, result.as_ref().is_ok_and(|r| r.exec.ok));
+    result
+}
+
+/// 运行 WinSW 操作；启动类操作成功后按定义等待服务健康
+async fn execute_action(
+    app: &AppHandle,
+    action: &str,
+    target: &ActionTarget,
+    timeout_secs: u64,
+    on_line: Option<LineSink<'_>>,
+) -> Result<ActionResp, WinswError> {
+    // 定位并校验 WinSW，识别主版本以决定参数形式
+    let binary = provision::resolve(app, &target.winsw_path).await?;
+
+    // 健康检查需在启动前就绪（日志匹配只关注启动后新写入的行）
+    let waiter = match (&target.health, starts_service(action)) {
+        (Some(spec), true) if !spec.checks.is_empty() => {
+            let cfg = target
+                .config
+                .as_deref()
+                .and_then(|c| config::ServiceConfig::load(c).ok());
+            Some(HealthWaiter::prepare(spec, cfg.as_ref()))
         }
+        _ => None,
     };
 
-    // 读取输出
-    let stdout = if let Some(s) = child.stdout.take() {
-        read_output(s).await
-    } else {
-        None
+    let env = resolve_env(app, target)?;
+    let mut resp = execute_winsw(app, &binary, action, target, timeout_secs, &env, on_line).await?;
+
+    // refresh 使配置生效，记录此时的配置
+    if action == "refresh" && resp.exec.code == 0 {
+        if let Some(cfg) = target.config.as_deref() {
+            history::record(app, Path::new(cfg), history::SnapshotReason::Refresh);
+        }
+    }
+
+    if let (Some(waiter), 0) = (waiter, resp.exec.code) {
+        let report = waiter.wait().await;
+        if !report.healthy {
+            resp.exec.ok = false;
+            resp.exec.error = Some(format!(
+                "服务启动后健康检查失败: {}",
+                report.failure_summary()
+            ));
+        }
+        resp.health = Some(report);
+    }
+
+    Ok(resp)
+}
+
+/// 执行 refresh 使配置修改生效（无需重新安装），错误转为失败响应
+pub(crate) async fn refresh_service(
+    app: &AppHandle,
+    target: &ActionTarget,
+    timeout_secs: u64,
+) -> ActionResp {
+    match perform_action(app, "refresh", target, timeout_secs, None).await {
+        Ok(resp) => resp,
+        Err(e) => ActionResp::failure(-1, e.to_string()),
+    }
+}
+
+/// 由请求解析执行目标与超时时间
+///
+/// 提供 `service_id` 时使用服务目录中的记录；目录中的环境变量在前，请求中的同名项覆盖之。
+fn resolve_target(
+    app: &AppHandle,
+    req: Option<&ActionReq>,
+) -> Result<(ActionTarget, u64), WinswError> {
+    // 通过服务 ID 解析目录中的服务
+    let entry = match req.and_then(|r| r.service_id.as_deref()) {
+        Some(id) => Some(catalog::load_catalog(app)?.require(id)?.clone()),
+        None => None,
     };
 
-    let stderr = if let Some(s) = child.stderr.take() {
-        read_output(s).await
-    } else {
-        None
+    let mut target = match &entry {
+        Some(e) => ActionTarget::from_entry(app, e)?,
+        None => ActionTarget {
+            service_id: None,
+            winsw_path: req
+                .and_then(|r| r.winsw_path.clone())
+                .unwrap_or_else(|| DEFAULT_WINSW_PATH.to_string()),
+            config: req.and_then(|r| r.config.clone()),
+            ..Default::default()
+        },
     };
 
-    let ok = status.success();
-    let code = status.code().unwrap_or(if ok { 0 } else { -1 });
+    let timeout_secs = req
+        .and_then(|r| r.timeout_seconds)
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+    if let Some(vars) = req.and_then(|r| r.env_vars.as_ref()) {
+        target
+            .env
+            .get_or_insert_with(HashMap::new)
+            .extend(vars.clone());
+    }
+    if let Some(spec) = req.and_then(|r| r.health.clone()) {
+        target.health = Some(spec);
+    }
+    if let Some(secs) = req.and_then(|r| r.idle_timeout_seconds) {
+        target.idle_timeout_secs = Some(secs);
+    }
+    if let Some(output) = req.and_then(|r| r.output.clone()) {
+        target.output = output;
+    }
+    target.invocation_id = req.and_then(|r| r.invocation_id.clone());
+    if let Some(name) = req.and_then(|r| r.profile.as_deref()) {
+        target.profile = Some(env_profile::load_overlay(app, name)?);
+    }
 
-    Ok(ActionResp::success(stdout, stderr, code))
+    Ok((target, timeout_secs))
 }
 
 /// Tauri 命令：执行 WinSW 操作
@@ -237,23 +630,37 @@ async fn execute_winsw(
 /// # 参数
 /// - `action`: WinSW 操作名称（install, uninstall, start, stop, restart, restart!, status, refresh）
 /// - `req`: 可选的请求参数，包含：
-///   - `winsw_path`: WinSW 可执行文件路径（默认: "winsw.exe"）
+///   - `service_id`: 服务目录中的服务 ID（提供时忽略 `winsw_path` 与 `config`）
+///   - `winsw_path`: WinSW 可执行文件路径（默认依次查找应用配置、资源目录与 PATH）
 ///   - `config`: 配置文件路径（XML 格式）
 ///   - `timeout_seconds`: 超时时间（秒，默认: 30）
-///   - `env_vars`: 自定义环境变量
+///   - `profile`: 使用的环境配置名称（如 "prod"），在系统环境之上、`env_vars` 之前应用
+///   - `env_vars`: 自定义环境变量，值中可用 `${secret:NAME}` 引用密钥库中的密钥（执行时解析）
+///   - `health`: 启动后的健康检查定义（默认使用服务目录中的定义）
+///   - `dry_run`: 仅返回解析后的可执行文件、参数、工作目录、环境变量（敏感值脱敏）与校验警告，不执行
+/// - `on_output`: 可选的输出通道，进程运行期间逐行推送 stdout/stderr
 ///
 /// # 返回
-/// 返回操作结果，包括是否成功、标准输出、标准错误、退出码和错误信息
+/// 返回操作结果，包括是否成功、标准输出、标准错误（聚合后的完整输出）、退出码和错误信息
 ///
 /// # 示例
 /// ```javascript
 /// // 前端调用示例 - 启动服务
+/// const onOutput = new Channel();
+/// onOutput.onmessage = ({ stream, line }) => console.log(stream, line);
 /// await invoke('winsw_action', {
 ///   action: 'start',
 ///   req: {
 ///     config: 'C:\\myapp\\service.xml',
 ///     timeout_seconds: 60
-///   }
+///   },
+///   onOutput
+/// });
+///
+/// // 前端调用示例 - 通过服务目录中的 ID 重启服务（不需要逐行输出时可省略 onOutput）
+/// await invoke('winsw_action', {
+///   action: 'restart',
+///   req: { service_id: 'postgres' }
 /// });
 ///
 /// // 前端调用示例 - 安装服务（带自定义环境变量）
@@ -265,11 +672,17 @@ async fn execute_winsw(
 ///       'APP_HOME': 'C:\\myapp',
 ///       'LOG_LEVEL': 'INFO'
 ///     }
-///   }
+///   },
+///   onOutput: new Channel()
 /// });
 /// ```
 #[tauri::command]
-pub async fn winsw_action(action: String, req: Option<ActionReq>) -> Result<ActionResp, String> {
+pub async fn winsw_action(
+    app: AppHandle,
+    action: String,
+    req: Option<ActionReq>,
+    on_output: Option<Channel<OutputLine>>,
+) -> Result<ActionResp, String> {
     // 验证操作名称
     let action_lc = match validate_action(&action) {
         Ok(a) => a,
@@ -277,24 +690,31 @@ pub async fn winsw_action(action: String, req: Option<ActionReq>) -> Result<Acti
     };
 
     // 解析请求参数
-    let winsw_path = req
-        .as_ref()
-        .and_then(|r| r.winsw_path.as_deref())
-        .unwrap_or(DEFAULT_WINSW_PATH);
-
-    let timeout_secs = req
-        .as_ref()
-        .and_then(|r| r.timeout_seconds)
-        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let (target, timeout_secs) = match resolve_target(&app, req.as_ref()) {
+        Ok(t) => t,
+        Err(e) => return Ok(ActionResp::failure(-1, e.to_string())),
+    };
 
-    let config = req.as_ref().and_then(|r| r.config.as_deref());
+    // 仅预览，不启动进程
+    if req.as_ref().and_then(|r| r.dry_run).unwrap_or(false) {
+        return Ok(
+            match preview::preview_action(&app, &action_lc, &target).await {
+                Ok(plan) => ActionResp::preview(plan),
+                Err(e) => ActionResp::failure(-1, e.to_string()),
+            },
+        );
+    }
 
-    let custom_env = req.as_ref().and_then(|r| r.env_vars.as_ref());
+    let sink = |line: OutputLine| {
+        if let Some(channel) = &on_output {
+            let _ = channel.send(line);
+        }
+    };
 
     // 执行 WinSW 操作
-    match execute_winsw(winsw_path, &action_lc, config, timeout_secs, custom_env).await {
+    match perform_action(&app, &action_lc, &target, timeout_secs, Some(&sink)).await {
         Ok(resp) => Ok(resp),
-        Err(e) => Ok(ActionResp::failure(-1, e.to_string())),
+        Err(e) => Ok(ActionResp::from_error(e)),
     }
 }
 
@@ -310,6 +730,17 @@ mod tests {
         assert!(validate_action("invalid").is_err());
     }
 
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("NonExistent\r\n"), ServiceStatus::NonExistent);
+        assert_eq!(parse_status("Started"), ServiceStatus::Running);
+        assert_eq!(parse_status("Active (running)"), ServiceStatus::Running);
+        assert_eq!(parse_status("Inactive (stopped)"), ServiceStatus::Stopped);
+        assert_eq!(parse_status("Stopped"), ServiceStatus::Stopped);
+        assert_eq!(parse_status("Active (stopping)"), ServiceStatus::Pending);
+        assert_eq!(parse_status(""), ServiceStatus::Unknown);
+    }
+
     #[test]
     fn test_requires_config() {
         assert!(requires_config("install"));
@@ -322,7 +753,7 @@ mod tests {
         let custom = HashMap::from([
             ("CUSTOM_VAR".to_string(), "value".to_string()),
         ]);
-        let env = get_enhanced_env(Some(&custom));
+        let env = get_enhanced_env(Some(&custom), None);
 
         assert!(env.contains_key("CUSTOM_VAR"));
         assert!(env.contains_key("PATH"));
@@ -332,22 +763,60 @@ mod tests {
     #[tokio::test]
     async fn test_action_resp_construction() {
         let resp = ActionResp::success(Some("ok".into()), None, 0);
-        assert!(resp.ok);
-        assert_eq!(resp.code, 0);
+        assert!(resp.exec.ok);
+        assert_eq!(resp.exec.code, 0);
 
         let resp = ActionResp::failure(-1, "error".into());
-        assert!(!resp.ok);
-        assert_eq!(resp.code, -1);
+        assert!(!resp.exec.ok);
+        assert_eq!(resp.exec.code, -1);
     }
 
     #[test]
     fn test_build_command_args() {
         // 需要配置的操作
-        let args = build_command_args("start", Some("test.xml"));
+        let args = build_command_args("start", Some("test.xml"), None);
         assert!(args.is_err()); // 因为 test.xml 文件不存在
 
         // 需要配置但未提供
-        let args = build_command_args("start", None);
+        let args = build_command_args("start", None, None);
         assert!(args.is_err());
     }
+
+    #[test]
+    fn test_build_command_args_by_version() {
+        let dir = std::env::temp_dir().join(format!("winsw-args-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let adjacent = dir.join("myapp.xml");
+        let other = dir.join("other.xml");
+        std::fs::write(&adjacent, "<service/>").unwrap();
+        std::fs::write(&other, "<service/>").unwrap();
+
+        let binary = |major| WinswBinary {
+            path: dir.join("myapp.exe"),
+            source: provision::BinarySource::Explicit,
+            sha256: String::new(),
+            verified: None,
+            version: None,
+            major,
+        };
+        let cfg = adjacent.to_str().unwrap();
+
+        // v3：配置文件路径作为参数
+        let args = build_command_args("start", Some(cfg), Some(&binary(Some(WinswMajor::V3))));
+        assert_eq!(args.unwrap(), vec!["start".to_string(), cfg.to_string()]);
+
+        // v2：同名 XML 不作为参数传入
+        let v2 = binary(Some(WinswMajor::V2));
+        assert_eq!(
+            build_command_args("start", Some(cfg), Some(&v2)).unwrap(),
+            vec!["start"]
+        );
+        assert!(matches!(
+            build_command_args("start", other.to_str(), Some(&v2)),
+            Err(WinswError::V2ConfigLayout { .. })
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,1152 @@
+//! 多服务打包：导出与导入
+//!
+//! 服务包是一个 zip 文件，根目录下的 `bundle.json` 描述其中的服务、服务组与环境配置，
+//! 配置文件与 WinSW 可执行文件按服务存放。导出时移除明文密钥（密钥引用保持原样），
+//! 被移除的项目记录在清单中，导入后需在目标机器上重新设置。
+//!
+//! 导入时先校验服务包，再按路径映射将源机器上的路径改写为目标机器上的路径，
+//! 最后写入文件并登记到服务目录；`dry_run` 只返回导入计划。
+
+use super::catalog::{
+    load_catalog, now_secs, update_catalog, validate_id, AddServiceReq, ServiceGroup,
+};
+use super::config::{ServiceConfig, XmlDocument, XmlElement, XmlNode};
+use super::health::HealthSpec;
+use super::history;
+use super::provision::{self, ProvisionSettings};
+use super::{data_dir, WinswError};
+use crate::env_profile::{self, EnvProfile, ProfileStore};
+use crate::secrets::{config_refs, has_refs, is_secret_name};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// 服务包格式版本
+pub const BUNDLE_FORMAT: u32 = 1;
+const MANIFEST_NAME: &str = "bundle.json";
+
+/// 服务包清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: u32,
+    pub created_at: u64,
+    /// 源机器名称
+    pub source: Option<String>,
+    pub services: Vec<BundleService>,
+    #[serde(default)]
+    pub groups: Vec<ServiceGroup>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, EnvProfile>,
+    /// 导出时移除的明文密钥，导入后需重新设置
+    #[serde(default)]
+    pub stripped: Vec<String>,
+}
+
+/// 服务包中的服务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleService {
+    pub id: String,
+    /// 源机器上的配置文件路径
+    pub config_path: String,
+    /// 包内的配置文件
+    pub config_file: String,
+    /// 源机器上的 WinSW 路径
+    pub winsw_path: String,
+    /// 包内的 WinSW 可执行文件；未指定路径（使用定位规则）时不打包
+    pub winsw_file: Option<String>,
+    pub winsw_sha256: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 使用的环境配置名称
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub health: Option<HealthSpec>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// 导出请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportReq {
+    /// 导出的服务 ID，为空时导出全部
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// 导出的环境配置名称，未提供时导出全部
+    pub profiles: Option<Vec<String>>,
+    /// 输出的 zip 文件路径
+    pub output: String,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub services: Vec<String>,
+    pub groups: Vec<String>,
+    pub profiles: Vec<String>,
+    pub stripped: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// 导入请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImportReq {
+    /// 服务包路径
+    pub bundle: String,
+    /// 路径映射：源机器上的目录前缀 → 目标机器上的目录
+    #[serde(default)]
+    pub path_map: BTreeMap<String, String>,
+    /// 仅返回导入计划，不做任何修改
+    pub dry_run: Option<bool>,
+    /// 覆盖已有的服务、文件与环境配置
+    pub overwrite: Option<bool>,
+}
+
+/// 导入计划中的服务
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedService {
+    pub id: String,
+    pub config_path: String,
+    pub winsw_path: String,
+    /// 服务目录中已有同 ID 的服务，将被更新
+    pub replaces: bool,
+}
+
+/// 导入计划与结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportPlan {
+    pub ok: bool,
+    pub dry_run: bool,
+    /// 是否已写入文件并登记
+    pub applied: bool,
+    pub services: Vec<PlannedService>,
+    pub groups: Vec<String>,
+    pub profiles: Vec<String>,
+    /// 将写入的文件
+    pub files: Vec<String>,
+    pub stripped: Vec<String>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+fn bundle_err(e: impl std::fmt::Display) -> WinswError {
+    WinswError::Bundle(e.to_string())
+}
+
+fn io_err(path: &Path, e: impl std::fmt::Display) -> WinswError {
+    WinswError::FileIo(format!("{}: {}", path.display(), e))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or(path).to_string()
+}
+
+/// 用于拼接到目标目录下的文件名；为空、`.`、`..` 或含盘符时返回 None
+fn safe_file_name(path: &str) -> Option<String> {
+    let name = file_name(path);
+    (!matches!(name.as_str(), "" | "." | "..") && !name.contains(':')).then_some(name)
+}
+
+/// 映射前缀之后的部分是否留在目标目录下：不含 `..` 与盘符（含 NTFS 数据流）
+fn is_contained(rest: &str) -> bool {
+    rest.split(['\\', '/'])
+        .all(|seg| seg != ".." && !seg.contains(':'))
+}
+
+/// 环境变量中的明文密钥替换为空值；密钥引用保留
+fn strip_env(env: &mut BTreeMap<String, String>, owner: &str, stripped: &mut Vec<String>) {
+    for (name, value) in env.iter_mut() {
+        if is_secret_name(name) && !value.is_empty() && !has_refs(value) {
+            value.clear();
+            stripped.push(format!("{}: {}", owner, name));
+        }
+    }
+}
+
+/// 是否为明文值（非空且不含密钥引用）
+fn is_literal(value: &str) -> bool {
+    !value.is_empty() && config_refs(value).is_empty()
+}
+
+/// 命令行参数中疑似明文密钥的选项名，如 `--password=pw`、`-token pw`、`/apikey:pw`
+fn argument_secrets(args: &str) -> Vec<String> {
+    let tokens: Vec<&str> = args.split_whitespace().collect();
+    let mut found = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let Some(option) = token.strip_prefix(['-', '/']) else {
+            continue;
+        };
+        let option = option.trim_start_matches('-');
+        let (name, value) = match option.split_once(['=', ':']) {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, tokens.get(i + 1).copied()),
+        };
+        if is_secret_name(name)
+            && value.is_some_and(|v| !v.starts_with(['-', '/']) && is_literal(v))
+        {
+            found.push(token.split(['=', ':']).next().unwrap_or(token).to_string());
+        }
+    }
+    found
+}
+
+/// 配置中的明文密钥：敏感名称的 `<env>` 值、敏感名称的元素（如 `<password>`）与属性
+/// （如 `<download password="…">`）。命令行参数无法安全地去除，只给出警告
+fn strip_config(
+    root: &mut XmlElement,
+    owner: &str,
+    stripped: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    for node in &mut root.children {
+        let XmlNode::Element(el) = node else {
+            continue;
+        };
+        if el.name == "env" {
+            let name = el.attr("name").unwrap_or_default().to_string();
+            for (key, value) in &mut el.attrs {
+                if key == "value" && is_secret_name(&name) && is_literal(value) {
+                    value.clear();
+                    stripped.push(format!("{}: <env name=\"{}\">", owner, name));
+                }
+            }
+            continue;
+        }
+        for (key, value) in &mut el.attrs {
+            if is_secret_name(key) && is_literal(value) {
+                value.clear();
+                stripped.push(format!("{}: <{} {}>", owner, el.name, key));
+            }
+        }
+        if el.name.ends_with("arguments") {
+            for option in argument_secrets(&el.text()) {
+                warnings.push(format!(
+                    "{}: <{}> 中的 {} 可能是明文密钥，未做处理；请改为通过服务的 env_vars 引用密钥",
+                    owner, el.name, option
+                ));
+            }
+        } else if is_secret_name(&el.name) && el.elements().next().is_none() {
+            if is_literal(&el.text()) {
+                el.children.clear();
+                stripped.push(format!("{}: <{}>", owner, el.name));
+            }
+        } else {
+            strip_config(el, owner, stripped, warnings);
+        }
+    }
+}
+
+/// 路径映射，前缀不区分大小写与分隔符
+struct PathMap {
+    entries: Vec<(String, String)>,
+    pattern: Option<Regex>,
+}
+
+impl PathMap {
+    fn new(map: &BTreeMap<String, String>) -> Result<Self, WinswError> {
+        let trim = |s: &str| s.trim().trim_end_matches(['\\', '/']).to_string();
+        let mut entries: Vec<(String, String)> = map
+            .iter()
+            .map(|(from, to)| (trim(from), trim(to)))
+            .filter(|(from, _)| !from.is_empty())
+            .collect();
+        if let Some((from, _)) = entries.iter().find(|(_, to)| to.is_empty()) {
+            return Err(WinswError::InvalidOption(format!(
+                "路径映射 {} 的目标为空",
+                from
+            )));
+        }
+        // 最长的前缀优先
+        entries.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+        let pattern = if entries.is_empty() {
+            None
+        } else {
+            let alternatives: Vec<String> = entries
+                .iter()
+                .map(|(from, _)| {
+                    from.chars()
+                        .map(|c| match c {
+                            '\\' | '/' => r"[\\/]".to_string(),
+                            c => regex::escape(&c.to_string()),
+                        })
+                        .collect::<String>()
+                })
+                .collect();
+            let re = format!(r#"(?i)({})([\\/"<\s]|$)"#, alternatives.join("|"));
+            Some(Regex::new(&re).map_err(|e| WinswError::InvalidOption(e.to_string()))?)
+        };
+        Ok(Self { entries, pattern })
+    }
+
+    fn target_of(&self, matched: &str) -> Option<&str> {
+        let key = matched.replace('/', "\\").to_lowercase();
+        self.entries
+            .iter()
+            .find(|(from, _)| from.replace('/', "\\").to_lowercase() == key)
+            .map(|(_, to)| to.as_str())
+    }
+
+    /// 改写路径；没有匹配的前缀时返回 None，改写后超出映射目标时返回错误
+    fn path(&self, path: &str) -> Option<Result<String, WinswError>> {
+        let re = self.pattern.as_ref()?;
+        let caps = re
+            .captures(path)
+            .filter(|c| c.get(0).unwrap().start() == 0)?;
+        let to = self.target_of(&caps[1])?;
+        let rest = &path[caps[1].len()..];
+        if !is_contained(rest) {
+            return Some(Err(bundle_err(format!(
+                "路径 {} 映射后超出目标目录 {}",
+                path, to
+            ))));
+        }
+        Some(Ok(format!("{}{}", to, rest)))
+    }
+
+    /// 改写文本中出现的所有路径前缀
+    fn text(&self, text: &str) -> String {
+        let Some(re) = &self.pattern else {
+            return text.to_string();
+        };
+        re.replace_all(text, |caps: &regex::Captures| {
+            let to = self.target_of(&caps[1]).unwrap_or(&caps[1]);
+            format!("{}{}", to, &caps[2])
+        })
+        .into_owned()
+    }
+}
+
+/// 导出服务包
+pub(crate) async fn export_bundle(
+    app: &AppHandle,
+    req: &ExportReq,
+) -> Result<ExportReport, WinswError> {
+    let catalog = load_catalog(app)?;
+    let entries: Vec<_> = if req.services.is_empty() {
+        catalog.list().to_vec()
+    } else {
+        req.services
+            .iter()
+            .map(|id| catalog.require(id).cloned())
+            .collect::<Result<_, _>>()?
+    };
+    let ids: BTreeSet<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+
+    let mut warnings = Vec::new();
+    let mut stripped = Vec::new();
+    // 包内文件名 → 内容
+    let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut services = Vec::new();
+
+    for entry in &entries {
+        for dep in entry
+            .depends_on
+            .iter()
+            .filter(|d| !ids.contains(d.as_str()))
+        {
+            warnings.push(format!("服务 {} 依赖的 {} 未导出", entry.id, dep));
+        }
+
+        let config_path = Path::new(&entry.config_path);
+        let text = std::fs::read_to_string(config_path).map_err(|e| io_err(config_path, e))?;
+        let mut doc = XmlDocument::parse(&text)?;
+        let before = stripped.len();
+        strip_config(
+            &mut doc.root,
+            &format!("服务 {} 配置", entry.id),
+            &mut stripped,
+            &mut warnings,
+        );
+        let xml = if stripped.len() > before {
+            doc.to_xml()
+        } else {
+            text
+        };
+        let config_file = format!("services/{}/{}", entry.id, file_name(&entry.config_path));
+        files.insert(config_file.clone(), xml.into_bytes());
+
+        let (winsw_file, winsw_sha256) = if provision::is_unspecified(&entry.winsw_path) {
+            (None, None)
+        } else {
+            let binary = provision::resolve(app, &entry.winsw_path).await?;
+            let data = std::fs::read(&binary.path).map_err(|e| io_err(&binary.path, e))?;
+            let sha = sha256_hex(&data);
+            // 相同的可执行文件只打包一次
+            let name = format!("binaries/{}/{}", &sha[..16], file_name(&entry.winsw_path));
+            files.entry(name.clone()).or_insert(data);
+            (Some(name), Some(sha))
+        };
+
+        let mut env: BTreeMap<String, String> = entry.env_vars.clone().into_iter().collect();
+        strip_env(
+            &mut env,
+            &format!("服务 {} 环境变量", entry.id),
+            &mut stripped,
+        );
+
+        services.push(BundleService {
+            id: entry.id.clone(),
+            config_path: entry.config_path.clone(),
+            config_file,
+            winsw_path: entry.winsw_path.clone(),
+            winsw_file,
+            winsw_sha256,
+            tags: entry.tags.clone(),
+            env,
+            profile: entry.profile.clone(),
+            health: entry.health.clone(),
+            depends_on: entry.depends_on.clone(),
+        });
+    }
+
+    // 只导出成员全部在包内的服务组
+    let groups: Vec<ServiceGroup> = catalog
+        .groups()
+        .iter()
+        .filter(|g| g.members.iter().all(|m| ids.contains(m.as_str())))
+        .cloned()
+        .collect();
+
+    let store = env_profile::load_store(app)?;
+    let mut profiles = BTreeMap::new();
+    for (name, profile) in store.profiles() {
+        if req.profiles.as_ref().is_some_and(|p| !p.contains(name)) {
+            continue;
+        }
+        let mut profile = profile.clone();
+        strip_env(
+            &mut profile.vars,
+            &format!("环境配置 {}", name),
+            &mut stripped,
+        );
+        profiles.insert(name.clone(), profile);
+    }
+    if let Some(requested) = &req.profiles {
+        for name in requested.iter().filter(|n| !profiles.contains_key(*n)) {
+            warnings.push(format!("环境配置 {} 不存在", name));
+        }
+    }
+
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT,
+        created_at: now_secs(),
+        source: std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .ok(),
+        services,
+        groups,
+        profiles,
+        stripped: stripped.clone(),
+    };
+    let output = PathBuf::from(&req.output);
+    write_bundle(&output, &manifest, &files)?;
+
+    Ok(ExportReport {
+        path: output.display().to_string(),
+        services: manifest.services.iter().map(|s| s.id.clone()).collect(),
+        groups: manifest.groups.iter().map(|g| g.id.clone()).collect(),
+        profiles: manifest.profiles.keys().cloned().collect(),
+        stripped,
+        warnings,
+    })
+}
+
+fn write_bundle(
+    output: &Path,
+    manifest: &BundleManifest,
+    files: &BTreeMap<String, Vec<u8>>,
+) -> Result<(), WinswError> {
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| io_err(parent, e))?;
+    }
+    let file = std::fs::File::create(output).map_err(|e| io_err(output, e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+    let json = serde_json::to_vec_pretty(manifest).map_err(bundle_err)?;
+    let entries = std::iter::once((MANIFEST_NAME, json.as_slice()))
+        .chain(files.iter().map(|(n, d)| (n.as_str(), d.as_slice())));
+    for (name, data) in entries {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(data).map_err(Into::into))
+            .map_err(|e| io_err(output, e))?;
+    }
+    zip.finish().map_err(|e| io_err(output, e))?;
+    Ok(())
+}
+
+/// 读取服务包：清单与全部文件
+fn read_bundle(path: &Path) -> Result<(BundleManifest, HashMap<String, Vec<u8>>), WinswError> {
+    let file = std::fs::File::open(path).map_err(|e| io_err(path, e))?;
+    let mut zip = zip::ZipArchive::new(file).map_err(bundle_err)?;
+    let mut files = HashMap::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(bundle_err)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(bundle_err)?;
+        files.insert(entry.name().to_string(), data);
+    }
+    let manifest = files
+        .remove(MANIFEST_NAME)
+        .ok_or_else(|| bundle_err(format!("缺少 {}", MANIFEST_NAME)))?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest).map_err(bundle_err)?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(bundle_err(format!(
+            "不支持的格式版本 {}（当前为 {}）",
+            manifest.format, BUNDLE_FORMAT
+        )));
+    }
+    Ok((manifest, files))
+}
+
+/// 导入时写入的一个文件
+struct PlannedFile {
+    path: PathBuf,
+    data: Vec<u8>,
+    /// 配置文件（写入时记录历史）
+    config: bool,
+}
+
+/// 校验服务包并生成导入计划；随附的 WinSW 须通过 `provision` 的允许列表
+fn plan_import(
+    manifest: &BundleManifest,
+    files: &HashMap<String, Vec<u8>>,
+    map: &PathMap,
+    default_dir: &Path,
+    existing: &BTreeSet<String>,
+    overwrite: bool,
+    provision: &ProvisionSettings,
+) -> (ImportPlan, Vec<PlannedFile>) {
+    let mut plan = ImportPlan {
+        stripped: manifest.stripped.clone(),
+        ..Default::default()
+    };
+    let mut writes = Vec::new();
+    let bundle_ids: BTreeSet<&str> = manifest.services.iter().map(|s| s.id.as_str()).collect();
+    if bundle_ids.len() != manifest.services.len() {
+        plan.errors.push("服务包中有重复的服务 ID".to_string());
+    }
+
+    for service in &manifest.services {
+        if let Err(e) = validate_id(&service.id) {
+            plan.errors.push(e.to_string());
+            continue;
+        }
+        let replaces = existing.contains(&service.id);
+        if replaces && !overwrite {
+            plan.errors
+                .push(format!("服务目录中已存在服务 {}（可选择覆盖）", service.id));
+        }
+        for dep in &service.depends_on {
+            if !bundle_ids.contains(dep.as_str()) && !existing.contains(dep) {
+                plan.errors.push(format!(
+                    "服务 {} 依赖的 {} 不在服务包或服务目录中",
+                    service.id, dep
+                ));
+            }
+        }
+
+        let service_dir = default_dir.join(&service.id);
+        let config_path = match map.path(&service.config_path) {
+            Some(Ok(path)) => PathBuf::from(path),
+            Some(Err(e)) => {
+                plan.errors.push(format!("服务 {}: {}", service.id, e));
+                continue;
+            }
+            None => match safe_file_name(&service.config_path) {
+                Some(name) => {
+                    plan.warnings.push(format!(
+                        "服务 {} 的配置路径 {} 不在路径映射中，将放在 {}",
+                        service.id,
+                        service.config_path,
+                        service_dir.display()
+                    ));
+                    service_dir.join(name)
+                }
+                None => {
+                    plan.errors.push(format!(
+                        "服务 {} 的配置路径无效: {}",
+                        service.id, service.config_path
+                    ));
+                    continue;
+                }
+            },
+        };
+
+        match files.get(&service.config_file) {
+            None => plan
+                .errors
+                .push(format!("服务包中缺少文件 {}", service.config_file)),
+            Some(data) => {
+                let text = map.text(&String::from_utf8_lossy(data));
+                match ServiceConfig::parse(&text, &config_path) {
+                    Ok(_) => writes.push(PlannedFile {
+                        path: config_path.clone(),
+                        data: text.into_bytes(),
+                        config: true,
+                    }),
+                    Err(e) => plan.errors.push(format!("服务 {}: {}", service.id, e)),
+                }
+            }
+        }
+
+        let winsw_path = match (&service.winsw_file, &service.winsw_sha256) {
+            (Some(file), Some(sha)) => {
+                // 未映射时与配置文件放在同一目录，满足 WinSW v2 的布局要求
+                let path = match map.path(&service.winsw_path) {
+                    Some(mapped) => mapped.map(PathBuf::from).map_err(|e| e.to_string()),
+                    None => safe_file_name(&service.winsw_path)
+                        .map(|name| config_path.parent().unwrap_or(&service_dir).join(name))
+                        .ok_or_else(|| format!("WinSW 路径无效: {}", service.winsw_path)),
+                };
+                let path = match path {
+                    Ok(path) => path,
+                    Err(e) => {
+                        plan.errors.push(format!("服务 {}: {}", service.id, e));
+                        continue;
+                    }
+                };
+                match files.get(file) {
+                    None => plan.errors.push(format!("服务包中缺少文件 {}", file)),
+                    Some(data) if sha256_hex(data) != *sha => plan
+                        .errors
+                        .push(format!("文件 {} 的 SHA-256 与清单不一致", file)),
+                    Some(_) if provision.rejects(sha) => plan.errors.push(format!(
+                        "文件 {} 不在 WinSW 允许列表中 (sha256: {})",
+                        file, sha
+                    )),
+                    Some(data) => writes.push(PlannedFile {
+                        path: path.clone(),
+                        data: data.clone(),
+                        config: false,
+                    }),
+                }
+                path.display().to_string()
+            }
+            _ => service.winsw_path.clone(),
+        };
+
+        plan.services.push(PlannedService {
+            id: service.id.clone(),
+            config_path: config_path.display().to_string(),
+            winsw_path,
+            replaces,
+        });
+    }
+
+    for group in &manifest.groups {
+        if let Some(m) = group
+            .members
+            .iter()
+            .find(|m| !bundle_ids.contains(m.as_str()))
+        {
+            plan.errors
+                .push(format!("服务组 {} 的成员 {} 不在服务包中", group.id, m));
+        }
+        plan.groups.push(group.id.clone());
+    }
+
+    // 同一路径只写一次；已存在且内容不同的文件需要覆盖许可
+    let mut seen = BTreeSet::new();
+    writes.retain(|w| seen.insert(w.path.clone()));
+    for w in &writes {
+        let differs = std::fs::read(&w.path).is_ok_and(|old| old != w.data);
+        if differs && !overwrite {
+            plan.errors
+                .push(format!("文件已存在且内容不同: {}", w.path.display()));
+        }
+        plan.files.push(w.path.display().to_string());
+    }
+
+    if !plan.stripped.is_empty() {
+        plan.warnings.push(format!(
+            "服务包中有 {} 项密钥在导出时被移除，导入后需重新设置",
+            plan.stripped.len()
+        ));
+    }
+    plan.ok = plan.errors.is_empty();
+    (plan, writes)
+}
+
+/// 按继承关系依次写入环境配置（只修改内存中的存储，由调用方保存）
+fn import_profiles(
+    store: &mut ProfileStore,
+    profiles: &BTreeMap<String, EnvProfile>,
+    overwrite: bool,
+    plan: &mut ImportPlan,
+) {
+    let mut pending: Vec<(&String, &EnvProfile)> = profiles
+        .iter()
+        .filter(|(name, profile)| match store.profiles().get(*name) {
+            Some(current) if current == *profile => false,
+            Some(_) if !overwrite => {
+                plan.warnings
+                    .push(format!("环境配置 {} 已存在且不同，未导入", name));
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    // 被继承的配置须先写入
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut failed = Vec::new();
+        for (name, profile) in pending {
+            match store.put(name, profile.clone()) {
+                Ok(()) => plan.profiles.push(name.clone()),
+                Err(e) => failed.push((name, profile, e)),
+            }
+        }
+        if failed.len() == before {
+            for (name, _, e) in failed {
+                plan.errors.push(format!("环境配置 {}: {}", name, e));
+            }
+            break;
+        }
+        pending = failed.into_iter().map(|(n, p, _)| (n, p)).collect();
+    }
+}
+
+fn write_planned(app: &AppHandle, w: &PlannedFile) -> Result<(), WinswError> {
+    if w.config {
+        history::write_config(app, &w.path, &String::from_utf8_lossy(&w.data))?;
+    } else {
+        if let Some(parent) = w.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| io_err(parent, e))?;
+        }
+        std::fs::write(&w.path, &w.data).map_err(|e| io_err(&w.path, e))?;
+    }
+    Ok(())
+}
+
+/// 还原导入时写入的文件：原本不存在的删除，其余写回原内容
+fn rollback(written: &[(&Path, Option<Vec<u8>>)]) {
+    for (path, old) in written.iter().rev() {
+        let result = match old {
+            Some(data) => std::fs::write(path, data),
+            None => std::fs::remove_file(path),
+        };
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                log::warn!("还原导入的文件失败 ({}): {}", path.display(), e);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 将服务包中的服务与服务组登记到服务目录
+async fn register_services(
+    app: &AppHandle,
+    manifest: &BundleManifest,
+    planned: &[PlannedService],
+) -> Result<(), WinswError> {
+    update_catalog(app, |catalog| {
+        for (service, target) in manifest.services.iter().zip(planned) {
+            let env: HashMap<String, String> = service.env.clone().into_iter().collect();
+            if target.replaces {
+                let entry = catalog.require_mut(&service.id)?;
+                entry.config_path = target.config_path.clone();
+                entry.winsw_path = target.winsw_path.clone();
+                entry.tags = service.tags.clone();
+                entry.env_vars = env;
+                entry.profile = service.profile.clone();
+                entry.health = service.health.clone();
+            } else {
+                catalog.add(AddServiceReq {
+                    id: service.id.clone(),
+                    config_path: target.config_path.clone(),
+                    winsw_path: Some(target.winsw_path.clone()),
+                    tags: Some(service.tags.clone()),
+                    env_vars: Some(env),
+                    profile: service.profile.clone(),
+                    health: service.health.clone(),
+                    depends_on: None,
+                })?;
+            }
+        }
+        // 全部服务登记后再设置依赖与服务组
+        for service in &manifest.services {
+            catalog.set_depends(&service.id, service.depends_on.clone())?;
+        }
+        for group in &manifest.groups {
+            catalog.save_group(group.clone())?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// 导入服务包
+pub(crate) async fn import_bundle(
+    app: &AppHandle,
+    req: &ImportReq,
+) -> Result<ImportPlan, WinswError> {
+    let dry_run = req.dry_run.unwrap_or(false);
+    let overwrite = req.overwrite.unwrap_or(false);
+    let (manifest, files) = read_bundle(Path::new(&req.bundle))?;
+    let map = PathMap::new(&req.path_map)?;
+    let default_dir = data_dir(app)?.join("services");
+    let existing: BTreeSet<String> = load_catalog(app)?
+        .list()
+        .iter()
+        .map(|e| e.id.clone())
+        .collect();
+
+    let settings = provision::app_settings(app)?;
+    let (mut plan, writes) = plan_import(
+        &manifest,
+        &files,
+        &map,
+        &default_dir,
+        &existing,
+        overwrite,
+        &settings,
+    );
+    plan.dry_run = dry_run;
+    let original = env_profile::load_store(app)?;
+    let mut profiles = original.clone();
+    import_profiles(&mut profiles, &manifest.profiles, overwrite, &mut plan);
+    plan.ok = plan.errors.is_empty();
+    if dry_run || !plan.ok {
+        return Ok(plan);
+    }
+
+    // 先写文件，再保存环境配置，最后登记服务目录；任一步失败时还原已写入的内容
+    let mut written: Vec<(&Path, Option<Vec<u8>>)> = Vec::new();
+    let mut profiles_saved = false;
+    let result = async {
+        for w in &writes {
+            written.push((&w.path, std::fs::read(&w.path).ok()));
+            write_planned(app, w)?;
+        }
+        if !plan.profiles.is_empty() {
+            profiles.save()?;
+            profiles_saved = true;
+        }
+        register_services(app, &manifest, &plan.services).await
+    }
+    .await;
+    if let Err(e) = result {
+        rollback(&written);
+        if profiles_saved {
+            if let Err(err) = original.save() {
+                log::warn!("还原环境配置失败: {}", err);
+            }
+        }
+        return Err(e);
+    }
+
+    plan.applied = true;
+    Ok(plan)
+}
+
+/// Tauri 命令：将目录中的服务导出为服务包（zip）
+///
+/// ```javascript
+/// const report = await invoke('winsw_bundle_export', {
+///   req: { services: ['api', 'worker'], output: 'D:\\backup\\services.zip' }
+/// });
+/// // report.stripped: 导出时移除的明文密钥
+/// ```
+#[tauri::command]
+pub async fn winsw_bundle_export(app: AppHandle, req: ExportReq) -> Result<ExportReport, String> {
+    export_bundle(&app, &req).await.map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：导入服务包；`dry_run` 时只返回导入计划
+///
+/// ```javascript
+/// const plan = await invoke('winsw_bundle_import', {
+///   req: {
+///     bundle: 'D:\\backup\\services.zip',
+///     path_map: { 'C:\\services': 'D:\\services' },
+///     dry_run: true
+///   }
+/// });
+/// // plan: { ok, services: [{ id, config_path, winsw_path, replaces }], files, warnings, errors }
+/// ```
+#[tauri::command]
+pub async fn winsw_bundle_import(app: AppHandle, req: ImportReq) -> Result<ImportPlan, String> {
+    import_bundle(&app, &req).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winsw::config::parse_document;
+
+    #[test]
+    fn test_strip_secrets() {
+        let mut env = BTreeMap::from([
+            ("DB_PASSWORD".to_string(), "hunter2".to_string()),
+            ("API_TOKEN".to_string(), "${secret:API_TOKEN}".to_string()),
+            ("APP_HOME".to_string(), "C:\\app".to_string()),
+        ]);
+        let mut stripped = Vec::new();
+        strip_env(&mut env, "svc", &mut stripped);
+        assert_eq!(env["DB_PASSWORD"], "");
+        assert_eq!(env["API_TOKEN"], "${secret:API_TOKEN}");
+        assert_eq!(env["APP_HOME"], "C:\\app");
+
+        let mut root = parse_document(
+            "<service><id>a</id><env name=\"DB_PASSWORD\" value=\"pw\"/>\
+             <env name=\"API_KEY\" value=\"%SECRET_API_KEY%\"/>\
+             <download from=\"https://x/a.zip\" to=\"a.zip\" user=\"u\" password=\"pw\"/>\
+             <arguments>-jar app.jar --db-password=pw --api-token %SECRET_T% --port 80</arguments>\
+             <serviceaccount><user>svc</user><password>pw</password></serviceaccount></service>",
+        )
+        .unwrap();
+        let mut warnings = Vec::new();
+        strip_config(&mut root, "cfg", &mut stripped, &mut warnings);
+        assert_eq!(stripped.len(), 4, "{:?}", stripped);
+        assert!(stripped.contains(&"cfg: <download password>".to_string()));
+        let xml = root.to_xml();
+        assert!(!xml.contains("\"pw\"") && !xml.contains(">pw<"), "{}", xml);
+        assert!(xml.contains("%SECRET_API_KEY%"));
+        assert!(xml.contains("user=\"u\""));
+        // 参数保持原样，只警告明文的 --db-password
+        assert!(xml.contains("--db-password=pw"));
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("--db-password"));
+    }
+
+    #[test]
+    fn test_argument_secrets() {
+        assert_eq!(
+            argument_secrets("-token abc /ApiKey:xyz --password %SECRET_P% --secret -v"),
+            vec!["-token", "/ApiKey"]
+        );
+        assert!(argument_secrets("--port 80 C:\\token.txt").is_empty());
+    }
+
+    #[test]
+    fn test_path_map() {
+        let map = PathMap::new(&BTreeMap::from([
+            ("C:\\services\\".to_string(), "D:\\svc".to_string()),
+            ("C:\\services\\api".to_string(), "E:\\api".to_string()),
+        ]))
+        .unwrap();
+        let mapped = |p: &str| map.path(p).map(|r| r.unwrap());
+        assert_eq!(
+            mapped("c:/services/web/web.xml").as_deref(),
+            Some("D:\\svc/web/web.xml")
+        );
+        assert_eq!(
+            mapped("C:\\services\\api\\api.xml").as_deref(),
+            Some("E:\\api\\api.xml")
+        );
+        assert_eq!(mapped("C:\\services2\\x.xml"), None);
+        assert_eq!(mapped("X:\\C:\\services\\x"), None);
+        // 改写后不得超出映射目标
+        assert!(matches!(
+            map.path("C:\\services\\..\\..\\Windows\\x.exe"),
+            Some(Err(_))
+        ));
+        assert!(matches!(map.path("C:\\services\\a\\D:\\x"), Some(Err(_))));
+        assert!(PathMap::new(&BTreeMap::from([("C:\\a".to_string(), " ".to_string())])).is_err());
+        assert_eq!(safe_file_name("C:\\a\\..").as_deref(), None);
+        assert_eq!(safe_file_name("C:\\a\\w.exe").as_deref(), Some("w.exe"));
+        assert_eq!(
+            map.text(
+                "<executable>C:\\services\\web\\w.exe</executable><logpath>C:\\services</logpath>"
+            ),
+            "<executable>D:\\svc\\web\\w.exe</executable><logpath>D:\\svc</logpath>"
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = std::env::temp_dir().join(format!("winsw-rollback-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("a.xml");
+        let created = dir.join("b.exe");
+        std::fs::write(&existing, "new").unwrap();
+        std::fs::write(&created, "new").unwrap();
+
+        rollback(&[
+            (existing.as_path(), Some(b"old".to_vec())),
+            (created.as_path(), None),
+            (dir.join("missing").as_path(), None),
+        ]);
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!created.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_and_plan() {
+        let dir = std::env::temp_dir().join(format!("winsw-bundle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let bin = b"MZ fake winsw".to_vec();
+        let sha = sha256_hex(&bin);
+        let service = |id: &str, depends_on: Vec<String>| BundleService {
+            id: id.to_string(),
+            config_path: format!("C:\\services\\{}\\{}.xml", id, id),
+            config_file: format!("services/{}/{}.xml", id, id),
+            winsw_path: format!("C:\\services\\{}\\{}.exe", id, id),
+            winsw_file: Some("binaries/x/winsw.exe".to_string()),
+            winsw_sha256: Some(sha.clone()),
+            tags: Vec::new(),
+            env: BTreeMap::new(),
+            profile: None,
+            health: None,
+            depends_on,
+        };
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT,
+            created_at: 1,
+            source: None,
+            services: vec![
+                service("api", vec!["db".into()]),
+                service("web", vec!["api".into()]),
+            ],
+            groups: vec![ServiceGroup {
+                id: "stack".into(),
+                description: None,
+                members: vec!["api".into(), "web".into()],
+            }],
+            profiles: BTreeMap::new(),
+            stripped: vec!["svc: DB_PASSWORD".into()],
+        };
+        let cfg = |id: &str| {
+            format!(
+                "<service><id>{}</id><executable>C:\\services\\{}\\app.exe</executable></service>",
+                id, id
+            )
+            .into_bytes()
+        };
+        let files = BTreeMap::from([
+            ("services/api/api.xml".to_string(), cfg("api")),
+            ("services/web/web.xml".to_string(), cfg("web")),
+            ("binaries/x/winsw.exe".to_string(), bin.clone()),
+        ]);
+        let zip_path = dir.join("out").join("bundle.zip");
+        write_bundle(&zip_path, &manifest, &files).unwrap();
+
+        let (read, files) = read_bundle(&zip_path).unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(files.len(), 3);
+
+        let target = dir.join("target");
+        let map = PathMap::new(&BTreeMap::from([(
+            "C:\\services".to_string(),
+            target.display().to_string(),
+        )]))
+        .unwrap();
+        let existing = BTreeSet::from(["db".to_string(), "web".to_string()]);
+        let open = ProvisionSettings::default();
+        let (plan, writes) = plan_import(
+            &read,
+            &files,
+            &map,
+            &dir.join("default"),
+            &existing,
+            false,
+            &open,
+        );
+        // web 已存在且未允许覆盖
+        assert!(!plan.ok);
+        assert_eq!(plan.errors.len(), 1, "{:?}", plan.errors);
+        assert_eq!(writes.len(), 4);
+        assert!(plan.services[0]
+            .config_path
+            .starts_with(&target.display().to_string()));
+        let api_xml = String::from_utf8(writes[0].data.clone()).unwrap();
+        assert!(
+            api_xml.contains(&target.display().to_string()),
+            "{}",
+            api_xml
+        );
+        assert_eq!(plan.warnings.len(), 1);
+
+        let (plan, _) = plan_import(
+            &read,
+            &files,
+            &map,
+            &dir.join("default"),
+            &existing,
+            true,
+            &open,
+        );
+        assert!(plan.ok, "{:?}", plan.errors);
+        assert!(plan.services[1].replaces);
+
+        // 随附的 WinSW 不在允许列表中
+        let listed = ProvisionSettings {
+            allowed_sha256: vec!["00".repeat(32)],
+            ..Default::default()
+        };
+        let (plan, _) = plan_import(
+            &read,
+            &files,
+            &map,
+            &dir.join("default"),
+            &existing,
+            true,
+            &listed,
+        );
+        assert!(
+            plan.errors.iter().any(|e| e.contains("允许列表")),
+            "{:?}",
+            plan.errors
+        );
+
+        // 路径映射后超出目标目录
+        let mut escaping = read.clone();
+        escaping.services[0].config_path = "C:\\services\\..\\..\\Windows\\api.xml".into();
+        let (plan, writes) = plan_import(
+            &escaping,
+            &files,
+            &map,
+            &dir.join("default"),
+            &existing,
+            true,
+            &open,
+        );
+        assert!(
+            plan.errors.iter().any(|e| e.contains("超出")),
+            "{:?}",
+            plan.errors
+        );
+        let target_prefix = target.display().to_string();
+        assert!(writes
+            .iter()
+            .all(|w| w.path.display().to_string().starts_with(&target_prefix)));
+
+        // 未映射：放在默认目录；可执行文件校验失败
+        let mut tampered = files.clone();
+        tampered.insert("binaries/x/winsw.exe".to_string(), b"other".to_vec());
+        let empty = PathMap::new(&BTreeMap::new()).unwrap();
+        let (plan, _) = plan_import(
+            &read,
+            &tampered,
+            &empty,
+            &dir.join("default"),
+            &existing,
+            true,
+            &open,
+        );
+        assert!(plan.errors.iter().any(|e| e.contains("SHA-256")));
+        assert!(plan.services[0].winsw_path.contains("default"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
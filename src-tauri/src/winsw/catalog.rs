@@ -0,0 +1,503 @@
+//! 应用托管的 WinSW 服务目录
+//!
+//! 目录以 JSON 形式保存在应用数据目录（`<app_data_dir>/winsw/catalog.json`）中，
+//! 应用重启后依然可以通过服务 ID 找到对应的配置文件与 WinSW 可执行文件。
+
+use super::health::HealthSpec;
+use super::{data_dir, WinswError, DEFAULT_WINSW_PATH};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+const CATALOG_FILE: &str = "catalog.json";
+
+/// 串行化目录文件的“读取-修改-写回”过程
+static CATALOG_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// 目录中的服务条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceEntry {
+    /// 服务 ID（目录内唯一）
+    pub id: String,
+    /// 配置文件路径（XML 格式）
+    pub config_path: String,
+    /// WinSW 可执行文件路径
+    pub winsw_path: String,
+    /// 标签，用于分组与筛选
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 服务的环境变量，执行时合并到进程环境（在环境配置之后应用）
+    #[serde(default, alias = "env_profile")]
+    pub env_vars: HashMap<String, String>,
+    /// 执行时使用的环境配置名称（如 "prod"），请求中的 `profile` 优先
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 启动后的健康检查定义
+    #[serde(default)]
+    pub health: Option<HealthSpec>,
+    /// 显式声明的依赖（目录中的服务 ID），与配置中的 `<depend>` 共同决定启动顺序
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// 创建时间（Unix 时间戳，秒）
+    pub created_at: u64,
+}
+
+/// 添加服务的请求参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddServiceReq {
+    pub id: String,
+    pub config_path: String,
+    /// WinSW 可执行文件路径，默认为 "winsw.exe"
+    pub winsw_path: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[serde(alias = "env_profile")]
+    pub env_vars: Option<HashMap<String, String>>,
+    pub profile: Option<String>,
+    pub health: Option<HealthSpec>,
+    pub depends_on: Option<Vec<String>>,
+}
+
+/// 服务组：按依赖顺序整体启动与停止的一组服务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceGroup {
+    pub id: String,
+    pub description: Option<String>,
+    /// 成员（目录中的服务 ID）
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CatalogFile {
+    services: Vec<ServiceEntry>,
+    #[serde(default)]
+    groups: Vec<ServiceGroup>,
+}
+
+/// 服务目录
+#[derive(Debug)]
+pub struct ServiceCatalog {
+    path: PathBuf,
+    services: Vec<ServiceEntry>,
+    groups: Vec<ServiceGroup>,
+}
+
+/// 校验服务 ID：仅允许字母、数字以及 `-`、`_`、`.`
+pub(crate) fn validate_id(id: &str) -> Result<(), WinswError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(WinswError::InvalidServiceId(id.to_string()))
+    }
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl ServiceCatalog {
+    /// 从文件加载目录，文件不存在时返回空目录
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, WinswError> {
+        let path = path.into();
+        let file = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<CatalogFile>(&text)
+                .map_err(|e| WinswError::CatalogParse(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CatalogFile::default(),
+            Err(e) => return Err(WinswError::CatalogIo(e.to_string())),
+        };
+        Ok(Self {
+            path,
+            services: file.services,
+            groups: file.groups,
+        })
+    }
+
+    /// 写回文件（先写临时文件再重命名，避免写入中断导致目录损坏）
+    pub fn save(&self) -> Result<(), WinswError> {
+        let io_err = |e: std::io::Error| WinswError::CatalogIo(e.to_string());
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let file = CatalogFile {
+            services: self.services.clone(),
+            groups: self.groups.clone(),
+        };
+        let text = serde_json::to_string_pretty(&file)
+            .map_err(|e| WinswError::CatalogParse(e.to_string()))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, text).map_err(io_err)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn list(&self) -> &[ServiceEntry] {
+        &self.services
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ServiceEntry> {
+        self.services.iter().find(|s| s.id == id)
+    }
+
+    /// 按 ID 查找服务，不存在时返回错误
+    pub fn require(&self, id: &str) -> Result<&ServiceEntry, WinswError> {
+        self.get(id)
+            .ok_or_else(|| WinswError::ServiceNotFound(id.to_string()))
+    }
+
+    /// 添加服务，ID 重复或配置文件不存在时返回错误
+    pub fn add(&mut self, req: AddServiceReq) -> Result<&ServiceEntry, WinswError> {
+        let id = req.id.trim().to_string();
+        validate_id(&id)?;
+        if self.get(&id).is_some() {
+            return Err(WinswError::DuplicateService(id));
+        }
+        if !Path::new(&req.config_path).exists() {
+            return Err(WinswError::ConfigNotFound(req.config_path));
+        }
+        let depends_on = req.depends_on.unwrap_or_default();
+        self.check_depends(&id, &depends_on)?;
+
+        self.services.push(ServiceEntry {
+            id,
+            config_path: req.config_path,
+            winsw_path: req
+                .winsw_path
+                .unwrap_or_else(|| DEFAULT_WINSW_PATH.to_string()),
+            tags: req.tags.unwrap_or_default(),
+            env_vars: req.env_vars.unwrap_or_default(),
+            profile: req.profile,
+            health: req.health,
+            depends_on,
+            created_at: now_secs(),
+        });
+        Ok(self.services.last().expect("just pushed"))
+    }
+
+    /// 按 ID 获取可修改的服务条目
+    pub fn require_mut(&mut self, id: &str) -> Result<&mut ServiceEntry, WinswError> {
+        self.services
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| WinswError::ServiceNotFound(id.to_string()))
+    }
+
+    /// 移除服务并返回被移除的条目，同时从服务组与其他服务的依赖中移除
+    pub fn remove(&mut self, id: &str) -> Result<ServiceEntry, WinswError> {
+        let idx = self
+            .services
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| WinswError::ServiceNotFound(id.to_string()))?;
+        for group in &mut self.groups {
+            group.members.retain(|m| m != id);
+        }
+        for service in &mut self.services {
+            service.depends_on.retain(|d| d != id);
+        }
+        Ok(self.services.remove(idx))
+    }
+
+    /// 校验依赖：必须是目录中已有的其他服务
+    fn check_depends(&self, id: &str, depends_on: &[String]) -> Result<(), WinswError> {
+        for dep in depends_on {
+            if dep == id {
+                return Err(WinswError::DependencyCycle(format!("{} -> {}", id, id)));
+            }
+            self.require(dep)?;
+        }
+        Ok(())
+    }
+
+    /// 设置服务的显式依赖
+    pub fn set_depends(
+        &mut self,
+        id: &str,
+        depends_on: Vec<String>,
+    ) -> Result<&ServiceEntry, WinswError> {
+        self.check_depends(id, &depends_on)?;
+        let entry = self.require_mut(id)?;
+        entry.depends_on = depends_on;
+        Ok(entry)
+    }
+
+    pub fn groups(&self) -> &[ServiceGroup] {
+        &self.groups
+    }
+
+    /// 按 ID 查找服务组，不存在时返回错误
+    pub fn require_group(&self, id: &str) -> Result<&ServiceGroup, WinswError> {
+        self.groups
+            .iter()
+            .find(|g| g.id == id)
+            .ok_or_else(|| WinswError::GroupNotFound(id.to_string()))
+    }
+
+    /// 新增或替换服务组，成员必须都在目录中
+    pub fn save_group(&mut self, group: ServiceGroup) -> Result<&ServiceGroup, WinswError> {
+        validate_id(&group.id)?;
+        for member in &group.members {
+            self.require(member)?;
+        }
+        let idx = match self.groups.iter().position(|g| g.id == group.id) {
+            Some(idx) => {
+                self.groups[idx] = group;
+                idx
+            }
+            None => {
+                self.groups.push(group);
+                self.groups.len() - 1
+            }
+        };
+        Ok(&self.groups[idx])
+    }
+
+    /// 移除服务组（不影响其中的服务）
+    pub fn remove_group(&mut self, id: &str) -> Result<ServiceGroup, WinswError> {
+        let idx = self
+            .groups
+            .iter()
+            .position(|g| g.id == id)
+            .ok_or_else(|| WinswError::GroupNotFound(id.to_string()))?;
+        Ok(self.groups.remove(idx))
+    }
+}
+
+/// 目录文件路径
+pub fn catalog_path(app: &AppHandle) -> Result<PathBuf, WinswError> {
+    Ok(data_dir(app)?.join(CATALOG_FILE))
+}
+
+/// 加载应用的服务目录
+pub fn load_catalog(app: &AppHandle) -> Result<ServiceCatalog, WinswError> {
+    ServiceCatalog::load(catalog_path(app)?)
+}
+
+/// 解析服务的配置文件路径：优先使用目录中的服务，其次使用直接给出的路径
+pub fn resolve_config(
+    app: &AppHandle,
+    service_id: Option<&str>,
+    config: Option<&str>,
+) -> Result<PathBuf, WinswError> {
+    match (service_id, config) {
+        (Some(id), _) => Ok(PathBuf::from(&load_catalog(app)?.require(id)?.config_path)),
+        (None, Some(cfg)) => Ok(PathBuf::from(cfg)),
+        (None, None) => Err(WinswError::ServiceRefRequired),
+    }
+}
+
+/// 在目录锁内执行“加载-修改-保存”
+pub async fn update_catalog<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut ServiceCatalog) -> Result<T, WinswError>,
+) -> Result<T, WinswError> {
+    let _guard = CATALOG_LOCK.lock().await;
+    let mut catalog = load_catalog(app)?;
+    let out = f(&mut catalog)?;
+    catalog.save()?;
+    Ok(out)
+}
+
+/// Tauri 命令：将服务添加到目录
+#[tauri::command]
+pub async fn winsw_catalog_add(app: AppHandle, req: AddServiceReq) -> Result<ServiceEntry, String> {
+    update_catalog(&app, |c| c.add(req).cloned())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：从目录移除服务（不会卸载服务本身）
+#[tauri::command]
+pub async fn winsw_catalog_remove(app: AppHandle, id: String) -> Result<ServiceEntry, String> {
+    update_catalog(&app, |c| c.remove(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：设置（或清除）服务的健康检查定义
+#[tauri::command]
+pub async fn winsw_catalog_set_health(
+    app: AppHandle,
+    id: String,
+    health: Option<HealthSpec>,
+) -> Result<ServiceEntry, String> {
+    update_catalog(&app, |c| {
+        let entry = c.require_mut(&id)?;
+        entry.health = health;
+        Ok(entry.clone())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：设置（或清除）服务使用的环境配置
+#[tauri::command]
+pub async fn winsw_catalog_set_profile(
+    app: AppHandle,
+    id: String,
+    profile: Option<String>,
+) -> Result<ServiceEntry, String> {
+    if let Some(name) = profile.as_deref() {
+        crate::env_profile::load_overlay(&app, name).map_err(|e| e.to_string())?;
+    }
+    update_catalog(&app, |c| {
+        let entry = c.require_mut(&id)?;
+        entry.profile = profile;
+        Ok(entry.clone())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：设置服务的显式依赖
+#[tauri::command]
+pub async fn winsw_catalog_set_depends(
+    app: AppHandle,
+    id: String,
+    depends_on: Vec<String>,
+) -> Result<ServiceEntry, String> {
+    update_catalog(&app, |c| c.set_depends(&id, depends_on).cloned())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：列出目录中的所有服务，可按标签筛选
+#[tauri::command]
+pub async fn winsw_catalog_list(
+    app: AppHandle,
+    tag: Option<String>,
+) -> Result<Vec<ServiceEntry>, String> {
+    let catalog = load_catalog(&app).map_err(|e| e.to_string())?;
+    Ok(catalog
+        .list()
+        .iter()
+        .filter(|s| tag.as_ref().map_or(true, |t| s.tags.contains(t)))
+        .cloned()
+        .collect())
+}
+
+/// Tauri 命令：按 ID 获取服务
+#[tauri::command]
+pub async fn winsw_catalog_get(app: AppHandle, id: String) -> Result<ServiceEntry, String> {
+    let catalog = load_catalog(&app).map_err(|e| e.to_string())?;
+    catalog.require(&id).cloned().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("winsw-catalog-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add_req(id: &str, config: &Path) -> AddServiceReq {
+        AddServiceReq {
+            id: id.to_string(),
+            config_path: config.to_string_lossy().to_string(),
+            winsw_path: None,
+            tags: Some(vec!["db".into()]),
+            env_vars: Some(HashMap::from([("JAVA_HOME".into(), "C:\\jdk".into())])),
+            profile: Some("prod".into()),
+            health: None,
+            depends_on: None,
+        }
+    }
+
+    #[test]
+    fn test_catalog_roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let config = dir.join("pg.xml");
+        std::fs::write(&config, "<service/>").unwrap();
+
+        let mut catalog = ServiceCatalog::load(dir.join(CATALOG_FILE)).unwrap();
+        assert!(catalog.list().is_empty());
+
+        let entry = catalog.add(add_req("postgres", &config)).unwrap().clone();
+        assert_eq!(entry.winsw_path, DEFAULT_WINSW_PATH);
+        assert!(entry.created_at > 0);
+        catalog.save().unwrap();
+
+        let reloaded = ServiceCatalog::load(dir.join(CATALOG_FILE)).unwrap();
+        assert_eq!(reloaded.list(), &[entry]);
+        assert_eq!(reloaded.require("postgres").unwrap().tags, vec!["db"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_entry_reads_legacy_env_field() {
+        let entry: ServiceEntry = serde_json::from_str(
+            r#"{"id":"api","config_path":"api.xml","winsw_path":"winsw.exe",
+                "env_profile":{"PORT":"8080"},"created_at":1}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.env_vars["PORT"], "8080");
+        assert_eq!(entry.profile, None);
+    }
+
+    #[test]
+    fn test_catalog_validation() {
+        let dir = temp_dir("validation");
+        let config = dir.join("svc.xml");
+        std::fs::write(&config, "<service/>").unwrap();
+        let mut catalog = ServiceCatalog::load(dir.join(CATALOG_FILE)).unwrap();
+
+        assert!(matches!(
+            catalog.add(add_req("bad id", &config)),
+            Err(WinswError::InvalidServiceId(_))
+        ));
+        assert!(matches!(
+            catalog.add(add_req("svc", &dir.join("missing.xml"))),
+            Err(WinswError::ConfigNotFound(_))
+        ));
+
+        catalog.add(add_req("svc", &config)).unwrap();
+        assert!(matches!(
+            catalog.add(add_req("svc", &config)),
+            Err(WinswError::DuplicateService(_))
+        ));
+
+        let mut dependent = add_req("web", &config);
+        dependent.depends_on = Some(vec!["missing".into()]);
+        assert!(matches!(
+            catalog.add(dependent.clone()),
+            Err(WinswError::ServiceNotFound(_))
+        ));
+        dependent.depends_on = Some(vec!["svc".into()]);
+        catalog.add(dependent).unwrap();
+        catalog
+            .save_group(ServiceGroup {
+                id: "stack".into(),
+                description: None,
+                members: vec!["svc".into(), "web".into()],
+            })
+            .unwrap();
+
+        // 移除服务时同步清理依赖与服务组成员
+        assert_eq!(catalog.remove("svc").unwrap().id, "svc");
+        assert!(catalog.require("web").unwrap().depends_on.is_empty());
+        assert_eq!(catalog.require_group("stack").unwrap().members, vec!["web"]);
+        assert!(matches!(
+            catalog.remove("svc"),
+            Err(WinswError::ServiceNotFound(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,641 @@
+//! WinSW 扩展命令：test、stop（可选不等待）、customize 与 dev 子命令
+//!
+//! 这些命令的参数与输出各不相同，不适合走 `winsw_action` 的字符串操作名。
+//! 每个命令都有独立的选项结构，并按 WinSW 主版本构建参数、解析输出。
+
+use super::provision::{self, WinswBinary, WinswMajor};
+use super::{
+    check_v2_layout, resolve_env, resolve_target, run_winsw, ActionReq, ActionResp, ActionTarget,
+    LineSink, OutputLine, WinswError,
+};
+use super::{lifecycle, preview, watchdog};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+/// `test` 默认运行时长（秒）
+const DEFAULT_TEST_SECS: u64 = 10;
+/// `test` 的进程超时在运行时长之外额外预留的时间（秒），用于启动与停止
+const TEST_GRACE_SECS: u64 = 30;
+
+/// `test`：不安装服务，在控制台中启动并在指定时间后停止
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TestOptions {
+    /// 运行时长（秒），默认为 10；仅 v3 支持
+    pub timeout_seconds: Option<u64>,
+    /// 不请求管理员权限
+    #[serde(default)]
+    pub no_elevate: bool,
+}
+
+/// `stop`：停止服务
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopOptions {
+    /// 是否等待服务完全停止；v2 对应 `stopwait` / `stop`，v3 对应 `stop` / `stop --no-wait`
+    #[serde(default = "default_true")]
+    pub wait: bool,
+    /// 同时停止依赖此服务的其他服务；仅 v3 支持
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub no_elevate: bool,
+}
+
+impl Default for StopOptions {
+    fn default() -> Self {
+        Self {
+            wait: true,
+            force: false,
+            no_elevate: false,
+        }
+    }
+}
+
+/// `customize`：生成修改了版本信息的 WinSW 副本；仅 v3 支持
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomizeOptions {
+    /// 输出的可执行文件路径
+    pub output: String,
+    /// 写入版本信息的厂商名称
+    pub manufacturer: String,
+}
+
+/// `dev ps`：列出服务关联的进程树；仅 v3 支持
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DevPsOptions {
+    /// 列出当前可执行文件管理的所有服务
+    #[serde(default)]
+    pub all: bool,
+    #[serde(default)]
+    pub no_elevate: bool,
+}
+
+/// `dev kill`：强制终止无响应的服务；仅 v3 支持
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DevKillOptions {
+    #[serde(default)]
+    pub no_elevate: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 扩展命令
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum WinswCommand {
+    Test(TestOptions),
+    Stop(StopOptions),
+    Customize(CustomizeOptions),
+    DevPs(DevPsOptions),
+    DevKill(DevKillOptions),
+    DevList,
+}
+
+/// 进程树中的一个进程
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub name: String,
+    /// 在树中的深度，根进程为 0
+    pub depth: usize,
+    pub parent_pid: Option<u32>,
+}
+
+/// 各命令的结构化结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandOutput {
+    Test {
+        /// 服务能否正常启动并停止
+        passed: bool,
+        timeout_seconds: Option<u64>,
+    },
+    Stop {
+        waited: bool,
+    },
+    Customize {
+        output: String,
+        /// 输出文件是否已生成
+        created: bool,
+    },
+    DevPs {
+        processes: Vec<ProcessNode>,
+    },
+    DevKill {
+        killed: bool,
+    },
+    DevList {
+        services: Vec<String>,
+    },
+}
+
+/// 扩展命令的执行结果：原始输出与结构化结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandResp {
+    #[serde(flatten)]
+    pub resp: ActionResp,
+    /// 命令成功执行时的结构化结果
+    pub output: Option<CommandOutput>,
+}
+
+impl WinswCommand {
+    /// 命令名称，用于错误信息
+    pub fn name(&self) -> &'static str {
+        match self {
+            WinswCommand::Test(_) => "test",
+            WinswCommand::Stop(_) => "stop",
+            WinswCommand::Customize(_) => "customize",
+            WinswCommand::DevPs(_) => "dev ps",
+            WinswCommand::DevKill(_) => "dev kill",
+            WinswCommand::DevList => "dev list",
+        }
+    }
+
+    /// 对服务状态的影响，按同名的服务操作记入生命周期与看门狗；只读命令与 `customize` 为 None
+    pub fn service_action(&self) -> Option<&'static str> {
+        match self {
+            WinswCommand::Stop(_) | WinswCommand::DevKill(_) => Some("stop"),
+            WinswCommand::Test(_) => Some("test"),
+            WinswCommand::Customize(_) | WinswCommand::DevPs(_) | WinswCommand::DevList => None,
+        }
+    }
+
+    /// 是否需要配置文件
+    fn requires_config(&self) -> bool {
+        !matches!(self, WinswCommand::Customize(_))
+    }
+
+    /// 进程超时：`test` 需覆盖其运行时长
+    pub fn process_timeout(&self, timeout_secs: u64) -> u64 {
+        match self {
+            WinswCommand::Test(opts) => {
+                let run = opts.timeout_seconds.unwrap_or(DEFAULT_TEST_SECS);
+                timeout_secs.max(run + TEST_GRACE_SECS)
+            }
+            _ => timeout_secs,
+        }
+    }
+
+    /// 按主版本构建命令参数；版本未知时按 v3 处理
+    pub fn build_args(
+        &self,
+        config: Option<&str>,
+        binary: &WinswBinary,
+    ) -> Result<Vec<String>, WinswError> {
+        let v2 = binary.major == Some(WinswMajor::V2);
+        let unsupported = || WinswError::UnsupportedByVersion {
+            command: self.name().to_string(),
+            version: binary.version.clone().unwrap_or_else(|| "2.x".to_string()),
+        };
+
+        let mut args: Vec<String> = match self {
+            WinswCommand::Test(opts) if v2 => {
+                if opts.timeout_seconds.is_some() {
+                    return Err(unsupported());
+                }
+                vec!["test".into()]
+            }
+            WinswCommand::Test(_) => vec!["test".into()],
+            WinswCommand::Stop(opts) if v2 => {
+                if opts.force {
+                    return Err(unsupported());
+                }
+                vec![if opts.wait { "stopwait" } else { "stop" }.into()]
+            }
+            WinswCommand::Stop(_) => vec!["stop".into()],
+            _ if v2 => return Err(unsupported()),
+            WinswCommand::Customize(_) => vec!["customize".into()],
+            WinswCommand::DevPs(_) => vec!["dev".into(), "ps".into()],
+            WinswCommand::DevKill(_) => vec!["dev".into(), "kill".into()],
+            WinswCommand::DevList => vec!["dev".into(), "list".into()],
+        };
+
+        if self.requires_config() {
+            let cfg = config.ok_or_else(|| WinswError::ConfigRequired(self.name().to_string()))?;
+            if !Path::new(cfg).exists() {
+                return Err(WinswError::ConfigNotFound(cfg.to_string()));
+            }
+            if v2 {
+                check_v2_layout(&binary.path, cfg)?;
+            } else {
+                args.push(cfg.to_string());
+            }
+        }
+
+        // v2 不接受以下选项，上面已排除
+        match self {
+            WinswCommand::Test(opts) if !v2 => {
+                let secs = opts.timeout_seconds.unwrap_or(DEFAULT_TEST_SECS);
+                args.extend(["--timeout".into(), secs.to_string(), "--no-break".into()]);
+                push_flag(&mut args, opts.no_elevate, "--no-elevate");
+            }
+            WinswCommand::Stop(opts) if !v2 => {
+                push_flag(&mut args, !opts.wait, "--no-wait");
+                push_flag(&mut args, opts.force, "--force");
+                push_flag(&mut args, opts.no_elevate, "--no-elevate");
+            }
+            WinswCommand::Customize(opts) => {
+                if opts.output.trim().is_empty() || opts.manufacturer.trim().is_empty() {
+                    return Err(WinswError::InvalidOption(
+                        "customize 需要提供 output 与 manufacturer".to_string(),
+                    ));
+                }
+                args.extend([
+                    "--output".into(),
+                    opts.output.clone(),
+                    "--manufacturer".into(),
+                    opts.manufacturer.clone(),
+                ]);
+            }
+            WinswCommand::DevPs(opts) => {
+                push_flag(&mut args, opts.all, "--all");
+                push_flag(&mut args, opts.no_elevate, "--no-elevate");
+            }
+            WinswCommand::DevKill(opts) => {
+                push_flag(&mut args, opts.no_elevate, "--no-elevate");
+            }
+            _ => {}
+        }
+
+        Ok(args)
+    }
+
+    /// 由执行结果解析结构化输出
+    fn parse_output(&self, resp: &ActionResp) -> CommandOutput {
+        let stdout = resp.exec.stdout.as_deref().unwrap_or("");
+        let ok = resp.exec.code == 0;
+        match self {
+            WinswCommand::Test(opts) => CommandOutput::Test {
+                passed: ok,
+                timeout_seconds: opts.timeout_seconds,
+            },
+            WinswCommand::Stop(opts) => CommandOutput::Stop { waited: opts.wait },
+            WinswCommand::Customize(opts) => CommandOutput::Customize {
+                output: opts.output.clone(),
+                created: ok && Path::new(&opts.output).is_file(),
+            },
+            WinswCommand::DevPs(_) => CommandOutput::DevPs {
+                processes: parse_process_tree(stdout),
+            },
+            WinswCommand::DevKill(_) => CommandOutput::DevKill { killed: ok },
+            WinswCommand::DevList => CommandOutput::DevList {
+                services: stdout
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            },
+        }
+    }
+}
+
+fn push_flag(args: &mut Vec<String>, enabled: bool, flag: &str) {
+    if enabled {
+        args.push(flag.to_string());
+    }
+}
+
+/// 解析 `dev ps` 输出的进程树
+///
+/// 每行形如 `├─ java (5678)`，前缀中每 3 个字符表示一层缩进，根进程没有前缀。
+pub fn parse_process_tree(text: &str) -> Vec<ProcessNode> {
+    let mut nodes = Vec::new();
+    // 当前路径上各层的 pid
+    let mut stack: Vec<u32> = Vec::new();
+
+    for raw in text.lines() {
+        let line = raw.trim_end();
+        let body = line.trim_start_matches([' ', '│', '├', '└', '─', '|', '`', '-', '+']);
+        let prefix_len = line[..line.len() - body.len()].chars().count();
+        let Some((name, pid)) = parse_process_label(body) else {
+            continue;
+        };
+        let depth = prefix_len.div_ceil(3);
+        stack.truncate(depth);
+        nodes.push(ProcessNode {
+            pid,
+            name,
+            depth,
+            parent_pid: stack.last().copied(),
+        });
+        stack.push(pid);
+    }
+
+    nodes
+}
+
+/// 解析 `name (pid)`
+fn parse_process_label(body: &str) -> Option<(String, u32)> {
+    let body = body.trim();
+    let open = body.rfind('(')?;
+    let pid = body[open + 1..].strip_suffix(')')?.trim().parse().ok()?;
+    let name = body[..open].trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), pid))
+}
+
+/// 对执行目标运行扩展命令；改变服务状态的命令同样进入生命周期与看门狗（与 [`perform_action`] 一致）
+///
+/// [`perform_action`]: super::perform_action
+pub(crate) async fn perform_command(
+    app: &AppHandle,
+    command: &WinswCommand,
+    target: &ActionTarget,
+    timeout_secs: u64,
+    on_line: Option<LineSink<'_>>,
+) -> Result<CommandResp, WinswError> {
+    let (Some(id), Some(action)) = (target.service_id.as_deref(), command.service_action()) else {
+        return execute_command(app, command, target, timeout_secs, on_line).await;
+    };
+
+    let _in_flight = lifecycle::begin_action(app, id, action);
+    let result = execute_command(app, command, target, timeout_secs, on_line).await;
+    lifecycle::finish_action(app, id, action, result.as_ref().map(|r| &r.resp));
+    // 通过本应用停止的服务不应被看门狗拉起
+    watchdog::note_action(
+        app,
+        id,
+        action,
+        result.as_ref().is_ok_and(|r| r.resp.exec.ok),
+    );
+    result
+}
+
+async fn execute_command(
+    app: &AppHandle,
+    command: &WinswCommand,
+    target: &ActionTarget,
+    timeout_secs: u64,
+    on_line: Option<LineSink<'_>>,
+) -> Result<CommandResp, WinswError> {
+    let binary = provision::resolve(app, &target.winsw_path).await?;
+    let args = command.build_args(target.config.as_deref(), &binary)?;
+    let env = resolve_env(app, target)?;
+    let mut resp = run_winsw(
+        app,
+        &binary,
+        &args,
+        target,
+        command.process_timeout(timeout_secs),
+        &env,
+        on_line,
+    )
+    .await?;
+
+    let output = command.parse_output(&resp);
+    if let CommandOutput::Customize { created: false, .. } = output {
+        resp.exec.ok = false;
+        resp.exec.error = Some("customize 未生成输出文件".to_string());
+    }
+    Ok(CommandResp {
+        resp,
+        output: Some(output),
+    })
+}
+
+/// Tauri 命令：执行 WinSW 扩展命令
+///
+/// `command` 以 `command` 字段区分命令，其余字段为该命令的选项；`req` 与 `winsw_action` 相同。
+///
+/// ```javascript
+/// await invoke('winsw_command', {
+///   command: { command: 'stop', wait: false },
+///   req: { service_id: 'postgres' },
+///   onOutput: new Channel()
+/// });
+///
+/// // 不需要逐行输出时可省略 onOutput
+/// const { output } = await invoke('winsw_command', {
+///   command: { command: 'dev_ps' },
+///   req: { service_id: 'postgres' }
+/// });
+/// // output: { kind: 'dev_ps', processes: [{ pid, name, depth, parent_pid }] }
+/// ```
+#[tauri::command]
+pub async fn winsw_command(
+    app: AppHandle,
+    command: WinswCommand,
+    req: Option<ActionReq>,
+    on_output: Option<Channel<OutputLine>>,
+) -> Result<CommandResp, String> {
+    let failure = |e: WinswError| CommandResp {
+        resp: ActionResp::from_error(e),
+        output: None,
+    };
+
+    let (target, timeout_secs) = match resolve_target(&app, req.as_ref()) {
+        Ok(t) => t,
+        Err(e) => return Ok(failure(e)),
+    };
+
+    if req.as_ref().and_then(|r| r.dry_run).unwrap_or(false) {
+        return Ok(
+            match preview::preview_command(&app, &command, &target).await {
+                Ok(plan) => CommandResp {
+                    resp: ActionResp::preview(plan),
+                    output: None,
+                },
+                Err(e) => failure(e),
+            },
+        );
+    }
+
+    let sink = |line: OutputLine| {
+        if let Some(channel) = &on_output {
+            let _ = channel.send(line);
+        }
+    };
+
+    match perform_command(&app, &command, &target, timeout_secs, Some(&sink)).await {
+        Ok(resp) => Ok(resp),
+        Err(e) => Ok(failure(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winsw::provision::BinarySource;
+    use std::path::PathBuf;
+
+    fn binary(dir: &Path, major: Option<WinswMajor>) -> WinswBinary {
+        WinswBinary {
+            path: dir.join("myapp.exe"),
+            source: BinarySource::Explicit,
+            sha256: String::new(),
+            verified: None,
+            version: None,
+            major,
+        }
+    }
+
+    fn setup(name: &str) -> (PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("winsw-cmd-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = dir.join("myapp.xml");
+        std::fs::write(&cfg, "<service/>").unwrap();
+        let cfg = cfg.to_str().unwrap().to_string();
+        (dir, cfg)
+    }
+
+    #[test]
+    fn test_build_args_v3() {
+        let (dir, cfg) = setup("v3");
+        let bin = binary(&dir, Some(WinswMajor::V3));
+        let args = |cmd: WinswCommand| cmd.build_args(Some(&cfg), &bin).unwrap();
+
+        assert_eq!(
+            args(WinswCommand::Test(TestOptions {
+                timeout_seconds: Some(5),
+                no_elevate: true,
+            })),
+            vec!["test", &cfg, "--timeout", "5", "--no-break", "--no-elevate"]
+        );
+        assert_eq!(
+            args(WinswCommand::Stop(StopOptions::default())),
+            vec!["stop", &cfg]
+        );
+        assert_eq!(
+            args(WinswCommand::Stop(StopOptions {
+                wait: false,
+                force: true,
+                no_elevate: false,
+            })),
+            vec!["stop", &cfg, "--no-wait", "--force"]
+        );
+        assert_eq!(
+            args(WinswCommand::Customize(CustomizeOptions {
+                output: "out.exe".into(),
+                manufacturer: "Acme".into(),
+            })),
+            vec!["customize", "--output", "out.exe", "--manufacturer", "Acme"]
+        );
+        assert_eq!(
+            args(WinswCommand::DevPs(DevPsOptions {
+                all: true,
+                no_elevate: false,
+            })),
+            vec!["dev", "ps", &cfg, "--all"]
+        );
+        assert_eq!(
+            args(WinswCommand::DevKill(DevKillOptions::default())),
+            vec!["dev", "kill", &cfg]
+        );
+        assert_eq!(args(WinswCommand::DevList), vec!["dev", "list", &cfg]);
+
+        // customize 不需要配置文件，但选项不能为空
+        let empty = WinswCommand::Customize(CustomizeOptions {
+            output: String::new(),
+            manufacturer: "Acme".into(),
+        });
+        assert!(matches!(
+            empty.build_args(None, &bin),
+            Err(WinswError::InvalidOption(_))
+        ));
+        // 其他命令需要配置文件
+        assert!(matches!(
+            WinswCommand::DevList.build_args(None, &bin),
+            Err(WinswError::ConfigRequired(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_build_args_v2() {
+        let (dir, cfg) = setup("v2");
+        let bin = binary(&dir, Some(WinswMajor::V2));
+
+        let stop = |wait| {
+            WinswCommand::Stop(StopOptions {
+                wait,
+                ..StopOptions::default()
+            })
+        };
+        assert_eq!(
+            stop(true).build_args(Some(&cfg), &bin).unwrap(),
+            vec!["stopwait"]
+        );
+        assert_eq!(
+            stop(false).build_args(Some(&cfg), &bin).unwrap(),
+            vec!["stop"]
+        );
+        assert_eq!(
+            WinswCommand::Test(TestOptions::default())
+                .build_args(Some(&cfg), &bin)
+                .unwrap(),
+            vec!["test"]
+        );
+
+        // v3 专有的命令与选项
+        for cmd in [
+            WinswCommand::DevList,
+            WinswCommand::DevPs(DevPsOptions::default()),
+            WinswCommand::Stop(StopOptions {
+                force: true,
+                ..StopOptions::default()
+            }),
+            WinswCommand::Test(TestOptions {
+                timeout_seconds: Some(5),
+                no_elevate: false,
+            }),
+        ] {
+            assert!(matches!(
+                cmd.build_args(Some(&cfg), &bin),
+                Err(WinswError::UnsupportedByVersion { .. })
+            ));
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_command_deserialize() {
+        let cmd: WinswCommand = serde_json::from_str(r#"{"command":"stop"}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            WinswCommand::Stop(StopOptions { wait: true, .. })
+        ));
+        let cmd: WinswCommand = serde_json::from_str(r#"{"command":"dev_ps","all":true}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            WinswCommand::DevPs(DevPsOptions { all: true, .. })
+        ));
+        let cmd: WinswCommand = serde_json::from_str(r#"{"command":"dev_list"}"#).unwrap();
+        assert_eq!(cmd.name(), "dev list");
+
+        // test 的进程超时覆盖运行时长
+        let test = WinswCommand::Test(TestOptions {
+            timeout_seconds: Some(60),
+            no_elevate: false,
+        });
+        assert_eq!(test.process_timeout(30), 90);
+        assert_eq!(WinswCommand::DevList.process_timeout(30), 30);
+    }
+
+    #[test]
+    fn test_parse_process_tree() {
+        let text = "myapp (100)\n├─ java (200)\n│  └─ conhost (300)\n└─ cmd (400)\n";
+        let nodes = parse_process_tree(text);
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(
+            (nodes[0].pid, nodes[0].depth, nodes[0].parent_pid),
+            (100, 0, None)
+        );
+        assert_eq!(
+            (nodes[1].name.as_str(), nodes[1].parent_pid),
+            ("java", Some(100))
+        );
+        assert_eq!((nodes[2].depth, nodes[2].parent_pid), (2, Some(200)));
+        assert_eq!((nodes[3].depth, nodes[3].parent_pid), (1, Some(100)));
+
+        assert!(parse_process_tree("no processes\n").is_empty());
+    }
+}
@@ -0,0 +1,702 @@
+//! WinSW XML 配置文件解析
+//!
+//! 先将 XML 解析为轻量的元素树（[`XmlElement`]），再从中提取类型化的 [`ServiceConfig`]。
+//! 元素树保留注释与空白文本；[`XmlDocument`] 另外按原文保留根元素之外的 XML 声明、注释与处理指令，
+//! 编辑配置后写回时不改动其余内容。
+
+use super::WinswError;
+use quick_xml::escape::{escape, partial_escape};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// XML 节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+    CData(String),
+    Comment(String),
+}
+
+/// XML 元素
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XmlElement {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 所有子元素
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|n| match n {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// 指定名称的子元素
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements().filter(move |e| e.name == name)
+    }
+
+    /// 第一个指定名称的子元素
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    /// 元素的文本内容（去除首尾空白）
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        for node in &self.children {
+            match node {
+                XmlNode::Text(t) | XmlNode::CData(t) => out.push_str(t),
+                _ => {}
+            }
+        }
+        out.trim().to_string()
+    }
+
+    /// 子元素的文本内容，元素不存在或为空时返回 None
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).map(|e| e.text()).filter(|t| !t.is_empty())
+    }
+
+    /// 仅包含文本的元素
+    pub fn with_text(name: &str, text: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attrs: Vec::new(),
+            children: vec![XmlNode::Text(text.to_string())],
+        }
+    }
+
+    /// 子元素的缩进：取第一个子元素之前的空白文本，没有时为空
+    fn child_indent(&self) -> String {
+        let mut prev: Option<&str> = None;
+        for node in &self.children {
+            match node {
+                XmlNode::Element(_) => break,
+                XmlNode::Text(t) => prev = Some(t),
+                _ => prev = None,
+            }
+        }
+        prev.filter(|t| t.trim().is_empty() && t.contains('\n'))
+            .map(|t| format!("\n{}", t.rsplit('\n').next().unwrap_or("")))
+            .unwrap_or_default()
+    }
+
+    /// 用 `new` 替换所有名为 `name` 的子元素，其余节点保持不变
+    ///
+    /// 新元素放在原第一个同名元素的位置，没有时追加到末尾；沿用已有子元素的缩进。
+    pub fn replace_elements(&mut self, name: &str, new: Vec<XmlElement>) {
+        let indent = self.child_indent();
+        let mut pending = Some(new);
+        let mut out: Vec<XmlNode> = Vec::with_capacity(self.children.len());
+
+        let insert = |out: &mut Vec<XmlNode>, items: Vec<XmlElement>| {
+            for el in items {
+                if !indent.is_empty() {
+                    out.push(XmlNode::Text(indent.clone()));
+                }
+                out.push(XmlNode::Element(el));
+            }
+        };
+
+        for node in std::mem::take(&mut self.children) {
+            match node {
+                XmlNode::Element(e) if e.name == name => {
+                    // 连同元素前的缩进一起移除
+                    if matches!(out.last(), Some(XmlNode::Text(t)) if t.trim().is_empty()) {
+                        out.pop();
+                    }
+                    if let Some(items) = pending.take() {
+                        insert(&mut out, items);
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+
+        if let Some(items) = pending.filter(|i| !i.is_empty()) {
+            // 结束标签前的空白保留在最后
+            let tail = match out.last() {
+                Some(XmlNode::Text(t)) if t.trim().is_empty() => out.pop(),
+                _ => None,
+            };
+            insert(&mut out, items);
+            out.extend(tail);
+        }
+
+        self.children = out;
+    }
+
+    /// 设置子元素的文本，`None` 时移除该元素
+    pub fn set_child_text(&mut self, name: &str, text: Option<&str>) {
+        let new = text
+            .map(|t| vec![XmlElement::with_text(name, t)])
+            .unwrap_or_default();
+        self.replace_elements(name, new);
+    }
+
+    /// 序列化为 XML 文本
+    pub fn to_xml(&self) -> String {
+        let mut out = String::new();
+        self.write_to(&mut out);
+        out
+    }
+
+    fn write_to(&self, out: &mut String) {
+        out.push('<');
+        out.push_str(&self.name);
+        for (k, v) in &self.attrs {
+            out.push_str(&format!(" {}=\"{}\"", k, escape(v.as_str())));
+        }
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+        for node in &self.children {
+            match node {
+                XmlNode::Element(e) => e.write_to(out),
+                XmlNode::Text(t) => out.push_str(&partial_escape(t.as_str())),
+                XmlNode::CData(t) => {
+                    out.push_str("<![CDATA[");
+                    out.push_str(t);
+                    out.push_str("]]>");
+                }
+                XmlNode::Comment(t) => {
+                    out.push_str("<!--");
+                    out.push_str(t);
+                    out.push_str("-->");
+                }
+            }
+        }
+        out.push_str("</");
+        out.push_str(&self.name);
+        out.push('>');
+    }
+}
+
+/// 新文档使用的 XML 声明
+const DEFAULT_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// 完整的 XML 文档
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XmlDocument {
+    /// 根元素之前的原文（XML 声明、注释、处理指令与空白）
+    pub prolog: String,
+    pub root: XmlElement,
+    /// 根元素之后的原文
+    pub epilog: String,
+}
+
+impl XmlDocument {
+    /// 解析 XML 文本，保留根元素前后的原文
+    pub fn parse(text: &str) -> Result<Self, WinswError> {
+        parse(text)
+    }
+
+    /// 序列化为 XML 文本；没有原文时（新文档）写入 UTF-8 声明
+    pub fn to_xml(&self) -> String {
+        let prolog = if self.prolog.is_empty() {
+            DEFAULT_DECLARATION
+        } else {
+            &self.prolog
+        };
+        let epilog = if self.epilog.is_empty() {
+            "\n"
+        } else {
+            &self.epilog
+        };
+        format!("{}{}{}", prolog, self.root.to_xml(), epilog)
+    }
+}
+
+/// 将根元素写为新的 XML 文档（带 UTF-8 声明）；修改已有文件时使用 [`XmlDocument`] 以保留原有声明与注释
+pub fn write_document(root: &XmlElement) -> String {
+    XmlDocument {
+        root: root.clone(),
+        ..Default::default()
+    }
+    .to_xml()
+}
+
+fn xml_err(e: impl std::fmt::Display) -> WinswError {
+    WinswError::ConfigInvalid(e.to_string())
+}
+
+/// 解析 XML 文本，返回根元素
+pub fn parse_document(text: &str) -> Result<XmlElement, WinswError> {
+    parse(text).map(|doc| doc.root)
+}
+
+fn parse(text: &str) -> Result<XmlDocument, WinswError> {
+    let mut reader = Reader::from_str(text);
+    // 栈底为虚拟的文档节点
+    let mut stack = vec![XmlElement::default()];
+    // 根元素在原文中的起止位置
+    let mut root_start = None;
+    let mut root_end = None;
+
+    fn start(e: &quick_xml::events::BytesStart<'_>) -> Result<XmlElement, WinswError> {
+        let mut attrs = Vec::new();
+        for a in e.attributes() {
+            let a = a.map_err(xml_err)?;
+            let key = String::from_utf8_lossy(a.key.as_ref()).to_string();
+            let value = a.unescape_value().map_err(xml_err)?.to_string();
+            attrs.push((key, value));
+        }
+        Ok(XmlElement {
+            name: String::from_utf8_lossy(e.name().as_ref()).to_string(),
+            attrs,
+            children: Vec::new(),
+        })
+    }
+
+    fn push_text(parent: &mut XmlElement, text: &str) {
+        if let Some(XmlNode::Text(prev)) = parent.children.last_mut() {
+            prev.push_str(text);
+        } else {
+            parent.children.push(XmlNode::Text(text.to_string()));
+        }
+    }
+
+    loop {
+        let position = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(xml_err)?;
+        if stack.len() == 1
+            && root_start.is_none()
+            && matches!(event, Event::Start(_) | Event::Empty(_))
+        {
+            root_start = Some(position);
+        }
+        let top = stack.last_mut().expect("document node");
+        match event {
+            Event::Start(e) => stack.push(start(&e)?),
+            Event::Empty(e) => top.children.push(XmlNode::Element(start(&e)?)),
+            Event::End(_) => {
+                let done = stack.pop().expect("element");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(XmlNode::Element(done)),
+                    None => return Err(xml_err("多余的结束标签")),
+                }
+            }
+            Event::Text(t) => push_text(top, &t.xml10_content().map_err(xml_err)?),
+            Event::GeneralRef(r) => {
+                let resolved = match r.resolve_char_ref().map_err(xml_err)? {
+                    Some(c) => c.to_string(),
+                    None => {
+                        let name = r.decode().map_err(xml_err)?;
+                        quick_xml::escape::resolve_predefined_entity(&name)
+                            .ok_or_else(|| xml_err(format!("未知的实体引用: &{};", name)))?
+                            .to_string()
+                    }
+                };
+                push_text(top, &resolved);
+            }
+            Event::CData(t) => top.children.push(XmlNode::CData(
+                String::from_utf8_lossy(&t.into_inner()).to_string(),
+            )),
+            Event::Comment(t) => top.children.push(XmlNode::Comment(
+                t.xml10_content().map_err(xml_err)?.to_string(),
+            )),
+            Event::Eof => break,
+            // 根元素之外的声明与处理指令随原文保留
+            Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+        if stack.len() == 1 && root_end.is_none() && stack[0].elements().next().is_some() {
+            root_end = Some(reader.buffer_position() as usize);
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(xml_err("XML 元素未闭合"));
+    }
+    let root = stack
+        .pop()
+        .and_then(|doc| doc.elements().next().cloned())
+        .ok_or_else(|| xml_err("缺少根元素"))?;
+    Ok(XmlDocument {
+        prolog: text[..root_start.unwrap_or(0)].to_string(),
+        root,
+        epilog: text[root_end.unwrap_or(text.len())..].to_string(),
+    })
+}
+
+/// WinSW 日志模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogMode {
+    Append,
+    Reset,
+    None,
+    Roll,
+    RollBySize,
+    RollByTime,
+    RollBySizeTime,
+}
+
+impl LogMode {
+    fn parse(s: &str) -> Result<Self, WinswError> {
+        Ok(match s.trim().to_lowercase().as_str() {
+            "append" => Self::Append,
+            "reset" => Self::Reset,
+            "none" => Self::None,
+            // v1 的 `<logmode>rotate</logmode>` 等价于 roll
+            "roll" | "rotate" => Self::Roll,
+            "roll-by-size" => Self::RollBySize,
+            "roll-by-time" => Self::RollByTime,
+            "roll-by-size-time" => Self::RollBySizeTime,
+            other => return Err(xml_err(format!("未知的日志模式: {}", other))),
+        })
+    }
+}
+
+/// `<log>` 配置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogConfig {
+    pub mode: LogMode,
+    /// roll-by-time 的时间格式（如 `yyyyMMdd`）
+    pub pattern: Option<String>,
+    /// roll-by-size 的大小阈值（KB）
+    pub size_threshold: Option<u64>,
+    /// roll-by-size 保留的文件数
+    pub keep_files: Option<u32>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            mode: LogMode::Append,
+            pattern: None,
+            size_threshold: None,
+            keep_files: None,
+        }
+    }
+}
+
+/// 类型化的 WinSW 服务配置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceConfig {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub executable: Option<String>,
+    pub arguments: Option<String>,
+    pub working_directory: Option<String>,
+    /// `<logpath>` 原始值（未展开环境变量）
+    pub log_path: Option<String>,
+    pub log: LogConfig,
+    /// `<depend>` 声明的依赖服务 ID
+    pub depends: Vec<String>,
+    /// 配置文件所在目录（即 `%BASE%`）
+    pub base_dir: PathBuf,
+    /// 配置文件名（不含扩展名），WinSW 以此命名日志文件
+    pub base_name: String,
+}
+
+impl ServiceConfig {
+    /// 从 XML 文本解析，`path` 为配置文件路径（用于确定 `%BASE%`）
+    pub fn parse(text: &str, path: &Path) -> Result<Self, WinswError> {
+        let root = parse_document(text)?;
+        if root.name != "service" {
+            return Err(xml_err(format!(
+                "根元素应为 <service>，实际为 <{}>",
+                root.name
+            )));
+        }
+
+        let id = root
+            .child_text("id")
+            .ok_or_else(|| xml_err("缺少 <id> 元素"))?;
+
+        let log = match root.child("log") {
+            Some(el) => LogConfig {
+                mode: el
+                    .attr("mode")
+                    .map(LogMode::parse)
+                    .transpose()?
+                    .unwrap_or(LogMode::Append),
+                pattern: el.child_text("pattern"),
+                size_threshold: el
+                    .child_text("sizeThreshold")
+                    .map(|v| v.parse().map_err(xml_err))
+                    .transpose()?,
+                keep_files: el
+                    .child_text("keepFiles")
+                    .map(|v| v.parse().map_err(xml_err))
+                    .transpose()?,
+            },
+            None => match root.child_text("logmode") {
+                Some(mode) => LogConfig {
+                    mode: LogMode::parse(&mode)?,
+                    ..Default::default()
+                },
+                None => LogConfig::default(),
+            },
+        };
+
+        Ok(Self {
+            id,
+            name: root.child_text("name"),
+            description: root.child_text("description"),
+            executable: root.child_text("executable"),
+            arguments: root.child_text("arguments"),
+            working_directory: root.child_text("workingdirectory"),
+            log_path: root.child_text("logpath"),
+            log,
+            depends: root
+                .children_named("depend")
+                .map(|e| e.text())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            base_dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            base_name: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// 读取并解析配置文件
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WinswError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                WinswError::ConfigNotFound(path.display().to_string())
+            } else {
+                WinswError::ConfigRead(e.to_string())
+            }
+        })?;
+        Self::parse(&text, path)
+    }
+
+    /// 展开 `%BASE%` 与 `%VAR%` 形式的环境变量
+    pub fn expand(&self, value: &str) -> String {
+        expand_vars(value, &self.base_dir)
+    }
+
+    /// 日志目录：`<logpath>` 展开后的路径，未配置时为配置文件所在目录
+    pub fn log_dir(&self) -> PathBuf {
+        match &self.log_path {
+            Some(p) => {
+                let dir = PathBuf::from(self.expand(p));
+                if dir.is_absolute() {
+                    dir
+                } else {
+                    self.base_dir.join(dir)
+                }
+            }
+            None => self.base_dir.clone(),
+        }
+    }
+}
+
+/// 位于 [`INSTALL_TIME_ELEMENTS`](crate::secrets::INSTALL_TIME_ELEMENTS) 之外、运行时无法展开的密钥引用
+pub fn misplaced_secret_refs(root: &XmlElement) -> Vec<String> {
+    fn walk(el: &XmlElement, out: &mut Vec<String>) {
+        let attrs = el.attrs.iter().map(|(_, v)| v.as_str());
+        let texts = el.children.iter().filter_map(|node| match node {
+            XmlNode::Text(t) | XmlNode::CData(t) => Some(t.as_str()),
+            _ => None,
+        });
+        for value in attrs.chain(texts) {
+            for name in crate::secrets::config_refs(value) {
+                out.push(format!(
+                    "<{}> 中的密钥引用 %{}{}% 不会被展开：仅 <serviceaccount> 中的引用在 install 时可用，运行时使用的密钥请通过服务的 env_vars 注入",
+                    el.name,
+                    crate::secrets::CONFIG_ENV_PREFIX,
+                    name
+                ));
+            }
+        }
+        for child in el.elements() {
+            if !crate::secrets::INSTALL_TIME_ELEMENTS.contains(&child.name.as_str()) {
+                walk(child, out);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, &mut out);
+    out
+}
+
+/// 展开 `%NAME%` 形式的变量；`%BASE%` 为配置文件所在目录，未知变量保持原样
+pub fn expand_vars(value: &str, base_dir: &Path) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) => {
+                let name = &after[..end];
+                let resolved = if name.eq_ignore_ascii_case("BASE") {
+                    Some(base_dir.display().to_string())
+                } else if name.is_empty() {
+                    None
+                } else {
+                    std::env::var(name).ok()
+                };
+                match resolved {
+                    Some(v) => out.push_str(&v),
+                    None => {
+                        out.push('%');
+                        out.push_str(name);
+                        out.push('%');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<service>
+  <!-- 示例服务 -->
+  <id>myapp</id>
+  <name>My App &amp; Co</name>
+  <executable>java</executable>
+  <arguments><![CDATA[-Xmx512m -jar app.jar]]></arguments>
+  <depend>postgres</depend>
+  <depend>redis</depend>
+  <logpath>%BASE%/logs</logpath>
+  <log mode="roll-by-size">
+    <sizeThreshold>10240</sizeThreshold>
+    <keepFiles>8</keepFiles>
+  </log>
+</service>"#;
+
+    #[test]
+    fn test_parse_service_config() {
+        let cfg = ServiceConfig::parse(SAMPLE, Path::new("/srv/myapp/myapp.xml")).unwrap();
+        assert_eq!(cfg.id, "myapp");
+        assert_eq!(cfg.name.as_deref(), Some("My App & Co"));
+        assert_eq!(cfg.arguments.as_deref(), Some("-Xmx512m -jar app.jar"));
+        assert_eq!(cfg.depends, vec!["postgres", "redis"]);
+        assert_eq!(cfg.log.mode, LogMode::RollBySize);
+        assert_eq!(cfg.log.size_threshold, Some(10240));
+        assert_eq!(cfg.log.keep_files, Some(8));
+        assert_eq!(cfg.base_name, "myapp");
+        assert_eq!(cfg.log_dir(), PathBuf::from("/srv/myapp/logs"));
+    }
+
+    #[test]
+    fn test_write_document_round_trip() {
+        let root = parse_document(SAMPLE).unwrap();
+        let written = write_document(&root);
+        assert!(written.starts_with("<?xml"));
+        assert!(written.contains("<!-- 示例服务 -->"));
+        assert!(written.contains("My App &amp; Co"));
+        assert!(written.contains("<![CDATA[-Xmx512m -jar app.jar]]>"));
+        assert_eq!(parse_document(&written).unwrap(), root);
+
+        let empty =
+            parse_document(r#"<service><env name="A" value="&quot;x&quot;"/></service>"#).unwrap();
+        assert_eq!(
+            empty.to_xml(),
+            r#"<service><env name="A" value="&quot;x&quot;"/></service>"#
+        );
+    }
+
+    #[test]
+    fn test_document_keeps_prolog_and_epilog() {
+        let text = "<?xml version=\"1.0\" encoding=\"windows-1252\"?>\r\n<!-- 由运维维护 -->\r\n<?app-meta v=\"2\"?>\r\n<service>\n  <id>a</id>\n</service>\r\n<!-- end -->\r\n";
+        let mut doc = XmlDocument::parse(text).unwrap();
+        assert_eq!(doc.to_xml(), text);
+        assert_eq!(doc.root.child_text("id").as_deref(), Some("a"));
+
+        doc.root.set_child_text("name", Some("A"));
+        let written = doc.to_xml();
+        assert!(written.starts_with(
+            "<?xml version=\"1.0\" encoding=\"windows-1252\"?>\r\n<!-- 由运维维护 -->\r\n<?app-meta v=\"2\"?>\r\n<service>"
+        ));
+        assert!(written.ends_with("</service>\r\n<!-- end -->\r\n"));
+        assert!(written.contains("<name>A</name>"));
+
+        // 新文档与没有声明的文档
+        let bare = XmlDocument::parse("<service/>").unwrap();
+        assert_eq!(
+            bare.to_xml(),
+            format!("{}<service/>\n", DEFAULT_DECLARATION)
+        );
+    }
+
+    #[test]
+    fn test_replace_elements_keeps_layout() {
+        let mut root = parse_document(
+            "<service>\n  <id>a</id>\n  <!-- keep -->\n  <depend>x</depend>\n  <depend>y</depend>\n</service>",
+        )
+        .unwrap();
+
+        root.replace_elements("depend", vec![XmlElement::with_text("depend", "z")]);
+        root.set_child_text("startmode", Some("Manual"));
+        assert_eq!(
+            root.to_xml(),
+            "<service>\n  <id>a</id>\n  <!-- keep -->\n  <depend>z</depend>\n  <startmode>Manual</startmode>\n</service>"
+        );
+
+        root.set_child_text("depend", None);
+        assert_eq!(
+            root.to_xml(),
+            "<service>\n  <id>a</id>\n  <!-- keep -->\n  <startmode>Manual</startmode>\n</service>"
+        );
+
+        // 无缩进的紧凑文档
+        let mut compact = parse_document("<service><id>a</id></service>").unwrap();
+        compact.set_child_text("name", Some("A"));
+        assert_eq!(
+            compact.to_xml(),
+            "<service><id>a</id><name>A</name></service>"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let p = Path::new("svc.xml");
+        assert!(ServiceConfig::parse("<service><name>x</name></service>", p).is_err());
+        assert!(ServiceConfig::parse("<config><id>x</id></config>", p).is_err());
+        assert!(ServiceConfig::parse("<service><id>x</id>", p).is_err());
+        assert!(
+            ServiceConfig::parse(r#"<service><id>x</id><log mode="weird"/></service>"#, p).is_err()
+        );
+
+        let legacy =
+            ServiceConfig::parse("<service><id>x</id><logmode>rotate</logmode></service>", p)
+                .unwrap();
+        assert_eq!(legacy.log.mode, LogMode::Roll);
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        let base = Path::new("/srv/app");
+        assert_eq!(expand_vars("%BASE%/logs", base), "/srv/app/logs");
+        assert_eq!(
+            expand_vars("%NO_SUCH_VAR_123%/x", base),
+            "%NO_SUCH_VAR_123%/x"
+        );
+        assert_eq!(expand_vars("50%", base), "50%");
+    }
+}
@@ -0,0 +1,668 @@
+//! 事务式部署：校验 → 安装（已安装时刷新）→ 启动 → 健康检查
+//!
+//! 任一步骤失败时回滚已完成的步骤：停止、卸载，并恢复升级前的配置文件与 WinSW 可执行文件；
+//! 升级前正在运行的服务会以原配置重新启动。正向与回滚步骤都记入日志返回。
+
+use super::catalog::now_secs;
+use super::config::ServiceConfig;
+use super::health::HealthSpec;
+use super::history::{self, SnapshotReason};
+use super::provision::{self, WinswMajor};
+use super::{
+    check_v2_layout, data_dir, parse_status, perform_action, resolve_target, ActionReq, ActionResp,
+    ActionTarget, LineSink, OutputLine, ServiceStatus, WinswError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+/// 部署请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeployReq {
+    /// 服务目录中的服务 ID，提供时使用目录中记录的配置文件与 WinSW 路径
+    pub service_id: Option<String>,
+    /// WinSW 可执行文件路径；替换可执行文件时必须提供
+    pub winsw_path: Option<String>,
+    /// 配置文件路径（XML 格式）
+    pub config: Option<String>,
+    /// 新的配置内容，提供时写入 `config`，原文件先备份
+    pub config_xml: Option<String>,
+    /// 新的 WinSW 可执行文件，提供时复制到 `winsw_path`，原文件先备份
+    pub winsw_source: Option<String>,
+    /// 每一步的超时时间（秒），默认为 30
+    pub timeout_seconds: Option<u64>,
+    pub env_vars: Option<HashMap<String, String>>,
+    /// 启动后的健康检查，未提供时使用服务目录中的定义
+    pub health: Option<HealthSpec>,
+    /// 使用的环境配置名称
+    pub profile: Option<String>,
+}
+
+/// 部署步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployStep {
+    Validate,
+    Status,
+    Stop,
+    WriteConfig,
+    ReplaceBinary,
+    Install,
+    Refresh,
+    Start,
+    Health,
+    Uninstall,
+    RestoreConfig,
+    RestoreBinary,
+}
+
+/// 部署日志中的一条记录
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub step: DeployStep,
+    /// 是否为回滚步骤
+    pub rollback: bool,
+    pub ok: bool,
+    pub message: Option<String>,
+    /// 调用 WinSW 的步骤附带其输出
+    pub resp: Option<ActionResp>,
+}
+
+/// 部署报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployReport {
+    pub ok: bool,
+    /// 部署前服务已安装（本次为升级）
+    pub upgrade: bool,
+    pub rolled_back: bool,
+    /// 回滚是否全部成功；未回滚时为 None
+    pub rollback_ok: Option<bool>,
+    pub journal: Vec<JournalEntry>,
+    pub error: Option<String>,
+}
+
+impl DeployReport {
+    fn failure(error: String) -> Self {
+        Self {
+            ok: false,
+            upgrade: false,
+            rolled_back: false,
+            rollback_ok: None,
+            journal: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// 已完成的正向步骤，决定需要回滚的内容
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Progress {
+    /// 部署前服务在运行，已被停止
+    stopped_running: bool,
+    config_written: bool,
+    binary_replaced: bool,
+    installed: bool,
+    refreshed: bool,
+    started: bool,
+}
+
+/// 回滚步骤（按执行顺序）：先停止、卸载，再恢复文件，最后恢复原有服务
+fn rollback_steps(p: &Progress) -> Vec<DeployStep> {
+    let mut steps = Vec::new();
+    if p.started {
+        steps.push(DeployStep::Stop);
+    }
+    if p.installed {
+        steps.push(DeployStep::Uninstall);
+    }
+    if p.binary_replaced {
+        steps.push(DeployStep::RestoreBinary);
+    }
+    if p.config_written {
+        steps.push(DeployStep::RestoreConfig);
+    }
+    if p.refreshed {
+        steps.push(DeployStep::Refresh);
+    }
+    if p.stopped_running {
+        steps.push(DeployStep::Start);
+    }
+    steps
+}
+
+fn io_err(path: &Path, e: std::io::Error) -> WinswError {
+    WinswError::FileIo(format!("{}: {}", path.display(), e))
+}
+
+/// 创建本次部署独占的备份目录；同一秒内开始的部署以序号区分
+fn reserve_backup_dir(root: &Path, stem: &str) -> Result<PathBuf, WinswError> {
+    std::fs::create_dir_all(root).map_err(|e| io_err(root, e))?;
+    let base = format!("{}-{}", stem, now_secs());
+    let mut n = 0u32;
+    loop {
+        let dir = match n {
+            0 => root.join(&base),
+            n => root.join(format!("{}-{}", base, n)),
+        };
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(io_err(&dir, e)),
+        }
+    }
+}
+
+/// 替换文件：目标已存在时先复制到备份目录，返回备份路径
+fn replace_file(
+    target: &Path,
+    backup_dir: &Path,
+    write: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> Result<Option<PathBuf>, WinswError> {
+    let backup = if target.exists() {
+        std::fs::create_dir_all(backup_dir).map_err(|e| io_err(backup_dir, e))?;
+        let name = target.file_name().unwrap_or_default();
+        let backup = backup_dir.join(name);
+        std::fs::copy(target, &backup).map_err(|e| io_err(&backup, e))?;
+        Some(backup)
+    } else {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| io_err(parent, e))?;
+        }
+        None
+    };
+    write(target).map_err(|e| io_err(target, e))?;
+    Ok(backup)
+}
+
+/// 恢复文件：有备份时复制回原位置，否则删除本次新建的文件
+fn restore_file(target: &Path, backup: Option<&Path>) -> Result<(), WinswError> {
+    match backup {
+        Some(b) => std::fs::copy(b, target)
+            .map(|_| ())
+            .map_err(|e| io_err(target, e)),
+        None => match std::fs::remove_file(target) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_err(target, e)),
+            _ => Ok(()),
+        },
+    }
+}
+
+struct Deployer<'a> {
+    app: &'a AppHandle,
+    req: &'a DeployReq,
+    target: ActionTarget,
+    timeout_secs: u64,
+    on_line: Option<LineSink<'a>>,
+    backup_dir: PathBuf,
+    config_backup: Option<PathBuf>,
+    binary_backup: Option<PathBuf>,
+    progress: Progress,
+    upgrade: bool,
+    journal: Vec<JournalEntry>,
+}
+
+impl Deployer<'_> {
+    fn record(
+        &mut self,
+        step: DeployStep,
+        rollback: bool,
+        result: Result<Option<String>, String>,
+        resp: Option<ActionResp>,
+    ) -> bool {
+        let ok = result.is_ok();
+        let message = result.unwrap_or_else(Some);
+        self.journal.push(JournalEntry {
+            step,
+            rollback,
+            ok,
+            message,
+            resp,
+        });
+        ok
+    }
+
+    /// 运行 WinSW 操作并记录；以退出码判断成功，健康检查单独记录
+    async fn action(
+        &mut self,
+        step: DeployStep,
+        action: &str,
+        rollback: bool,
+    ) -> Option<ActionResp> {
+        // 回滚时恢复的是原有配置，不再做健康检查
+        let target = if rollback {
+            ActionTarget {
+                health: None,
+                ..self.target.clone()
+            }
+        } else {
+            self.target.clone()
+        };
+        match perform_action(self.app, action, &target, self.timeout_secs, self.on_line).await {
+            Ok(resp) if resp.exec.code == 0 => {
+                self.record(step, rollback, Ok(None), Some(resp.clone()));
+                Some(resp)
+            }
+            Ok(resp) => {
+                let msg = resp
+                    .exec
+                    .stderr
+                    .clone()
+                    .filter(|s| !s.trim().is_empty())
+                    .unwrap_or_else(|| format!("退出码 {}", resp.exec.code));
+                self.record(step, rollback, Err(msg), Some(resp));
+                None
+            }
+            Err(e) => {
+                self.record(step, rollback, Err(e.to_string()), None);
+                None
+            }
+        }
+    }
+
+    fn config_path(&self) -> Result<PathBuf, WinswError> {
+        self.target
+            .config
+            .as_deref()
+            .map(PathBuf::from)
+            .ok_or_else(|| WinswError::ConfigRequired("deploy".to_string()))
+    }
+
+    /// 校验新配置与 WinSW 可执行文件，返回说明
+    async fn validate(&self) -> Result<String, WinswError> {
+        let config = self.config_path()?;
+        let parsed = match &self.req.config_xml {
+            Some(xml) => ServiceConfig::parse(xml, &config)?,
+            None => ServiceConfig::load(&config)?,
+        };
+
+        let binary = match self.req.winsw_source.as_deref() {
+            Some(source) => {
+                if provision::is_unspecified(&self.target.winsw_path) {
+                    return Err(WinswError::InvalidOption(
+                        "替换 WinSW 时需要提供目标路径 winsw_path".to_string(),
+                    ));
+                }
+                let mut bin = provision::resolve(self.app, source).await?;
+                bin.path = PathBuf::from(&self.target.winsw_path);
+                bin
+            }
+            None => provision::resolve(self.app, &self.target.winsw_path).await?,
+        };
+        if binary.major == Some(WinswMajor::V2) {
+            check_v2_layout(&binary.path, &config.to_string_lossy())?;
+        }
+
+        Ok(format!(
+            "服务 {}，WinSW {}",
+            parsed.id,
+            binary.version.as_deref().unwrap_or("版本未知")
+        ))
+    }
+
+    /// 查询部署前的服务状态；配置或可执行文件尚不存在时视为未安装
+    async fn status(&mut self) -> Option<ServiceStatus> {
+        let config_exists = self
+            .target
+            .config
+            .as_deref()
+            .is_some_and(|c| Path::new(c).is_file());
+        let binary_missing =
+            self.req.winsw_source.is_some() && !Path::new(&self.target.winsw_path).is_file();
+        if !config_exists || binary_missing {
+            self.record(
+                DeployStep::Status,
+                false,
+                Ok(Some("首次部署".to_string())),
+                None,
+            );
+            return Some(ServiceStatus::NonExistent);
+        }
+        let resp = self.action(DeployStep::Status, "status", false).await?;
+        Some(parse_status(resp.exec.stdout.as_deref().unwrap_or("")))
+    }
+
+    /// 正向执行，失败时返回错误信息（已记入日志）
+    async fn forward(&mut self) -> Result<(), String> {
+        let validated = self.validate().await.map_err(|e| e.to_string());
+        let failed = validated.clone().err();
+        self.record(DeployStep::Validate, false, validated.map(Some), None);
+        if let Some(e) = failed {
+            return Err(e);
+        }
+
+        let status = self
+            .status()
+            .await
+            .ok_or_else(|| "无法获取服务状态".to_string())?;
+        match status {
+            ServiceStatus::Pending => return Err("服务正在启动或停止，请稍后重试".to_string()),
+            ServiceStatus::Running => {
+                self.action(DeployStep::Stop, "stop", false)
+                    .await
+                    .ok_or_else(|| "停止原有服务失败".to_string())?;
+                self.progress.stopped_running = true;
+            }
+            _ => {}
+        }
+        self.upgrade = status != ServiceStatus::NonExistent;
+
+        if let Some(xml) = self.req.config_xml.clone() {
+            let config = self.config_path().map_err(|e| e.to_string())?;
+            if config.exists() {
+                history::record(self.app, &config, SnapshotReason::Baseline);
+            }
+            let result = replace_file(&config, &self.backup_dir, |p| std::fs::write(p, &xml));
+            if result.is_ok() {
+                history::record(self.app, &config, SnapshotReason::Write);
+            }
+            self.step_result(DeployStep::WriteConfig, result, |d, b| {
+                d.config_backup = b;
+                d.progress.config_written = true;
+            })?;
+        }
+
+        if let Some(source) = self.req.winsw_source.clone() {
+            let exe = PathBuf::from(&self.target.winsw_path);
+            let result = replace_file(&exe, &self.backup_dir, |p| {
+                std::fs::copy(&source, p).map(|_| ())
+            });
+            self.step_result(DeployStep::ReplaceBinary, result, |d, b| {
+                d.binary_backup = b;
+                d.progress.binary_replaced = true;
+            })?;
+        }
+
+        if self.upgrade {
+            self.action(DeployStep::Refresh, "refresh", false)
+                .await
+                .ok_or_else(|| "刷新服务配置失败".to_string())?;
+            self.progress.refreshed = true;
+        } else {
+            self.action(DeployStep::Install, "install", false)
+                .await
+                .ok_or_else(|| "安装服务失败".to_string())?;
+            self.progress.installed = true;
+        }
+
+        let resp = self
+            .action(DeployStep::Start, "start", false)
+            .await
+            .ok_or_else(|| "启动服务失败".to_string())?;
+        self.progress.started = true;
+
+        if let Some(report) = resp.health {
+            let healthy = report.healthy;
+            let summary = report.failure_summary();
+            let result = if healthy {
+                Ok(Some(format!("{} ms 内通过", report.elapsed_ms)))
+            } else {
+                Err(summary.clone())
+            };
+            self.record(DeployStep::Health, false, result, None);
+            if !healthy {
+                return Err(format!("服务启动后健康检查失败: {}", summary));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 记录文件替换步骤，成功时更新状态
+    fn step_result(
+        &mut self,
+        step: DeployStep,
+        result: Result<Option<PathBuf>, WinswError>,
+        apply: impl FnOnce(&mut Self, Option<PathBuf>),
+    ) -> Result<(), String> {
+        match result {
+            Ok(backup) => {
+                let msg = backup.as_ref().map(|b| format!("已备份至 {}", b.display()));
+                self.record(step, false, Ok(msg), None);
+                apply(self, backup);
+                Ok(())
+            }
+            Err(e) => {
+                self.record(step, false, Err(e.to_string()), None);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// 回滚已完成的步骤，返回是否全部成功
+    async fn rollback(&mut self) -> bool {
+        let mut all_ok = true;
+        for step in rollback_steps(&self.progress) {
+            let ok = match step {
+                DeployStep::Stop => self.action(step, "stop", true).await.is_some(),
+                DeployStep::Uninstall => self.action(step, "uninstall", true).await.is_some(),
+                DeployStep::Refresh => self.action(step, "refresh", true).await.is_some(),
+                DeployStep::Start => self.action(step, "start", true).await.is_some(),
+                DeployStep::RestoreBinary => {
+                    let exe = PathBuf::from(&self.target.winsw_path);
+                    let result = restore_file(&exe, self.binary_backup.as_deref());
+                    self.record(
+                        step,
+                        true,
+                        result.map(|_| None).map_err(|e| e.to_string()),
+                        None,
+                    )
+                }
+                DeployStep::RestoreConfig => {
+                    let result = self.config_path().and_then(|c| {
+                        restore_file(&c, self.config_backup.as_deref())?;
+                        if c.exists() {
+                            history::record(self.app, &c, SnapshotReason::Rollback);
+                        }
+                        Ok(())
+                    });
+                    self.record(
+                        step,
+                        true,
+                        result.map(|_| None).map_err(|e| e.to_string()),
+                        None,
+                    )
+                }
+                _ => true,
+            };
+            all_ok &= ok;
+        }
+        all_ok
+    }
+}
+
+/// 执行部署，失败时自动回滚
+pub(crate) async fn deploy(
+    app: &AppHandle,
+    req: &DeployReq,
+    target: ActionTarget,
+    timeout_secs: u64,
+    on_line: Option<LineSink<'_>>,
+) -> DeployReport {
+    let stem = target
+        .config
+        .as_deref()
+        .and_then(|c| Path::new(c).file_stem())
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "service".to_string());
+    let backup_dir = match data_dir(app).and_then(|d| reserve_backup_dir(&d.join("deploy"), &stem))
+    {
+        Ok(d) => d,
+        Err(e) => return DeployReport::failure(e.to_string()),
+    };
+
+    let mut deployer = Deployer {
+        app,
+        req,
+        target,
+        timeout_secs,
+        on_line,
+        backup_dir,
+        config_backup: None,
+        binary_backup: None,
+        progress: Progress::default(),
+        upgrade: false,
+        journal: Vec::new(),
+    };
+
+    let result = deployer.forward().await;
+    let (rolled_back, rollback_ok) = match &result {
+        Ok(()) => (false, None),
+        // 尚未改动任何内容，无需回滚
+        Err(_) if rollback_steps(&deployer.progress).is_empty() => (false, None),
+        Err(_) => {
+            let ok = deployer.rollback().await;
+            (true, Some(ok))
+        }
+    };
+
+    // 回滚未完全成功时保留备份，便于手工恢复
+    let mut error = result.err();
+    if rollback_ok == Some(false) && deployer.backup_dir.exists() {
+        error = error.map(|e| {
+            format!(
+                "{}；回滚未完全成功，备份保留在 {}",
+                e,
+                deployer.backup_dir.display()
+            )
+        });
+    } else {
+        let _ = std::fs::remove_dir_all(&deployer.backup_dir);
+    }
+
+    DeployReport {
+        ok: error.is_none(),
+        upgrade: deployer.upgrade,
+        rolled_back,
+        rollback_ok,
+        journal: deployer.journal,
+        error,
+    }
+}
+
+/// Tauri 命令：事务式部署服务（校验 → 安装/刷新 → 启动 → 健康检查），失败时自动回滚
+///
+/// ```javascript
+/// const report = await invoke('winsw_deploy', {
+///   req: {
+///     winsw_path: 'C:\\services\\myapp\\myapp.exe',
+///     config: 'C:\\services\\myapp\\myapp.xml',
+///     config_xml: '<service>...</service>',
+///     health: { checks: [{ type: 'tcp', host: '127.0.0.1', port: 8080 }] }
+///   },
+///   onOutput: new Channel()
+/// });
+/// // report.journal: [{ step: 'validate', rollback: false, ok: true, ... }, ...]
+/// ```
+#[tauri::command]
+pub async fn winsw_deploy(
+    app: AppHandle,
+    req: DeployReq,
+    on_output: Option<Channel<OutputLine>>,
+) -> Result<DeployReport, String> {
+    let action_req = ActionReq {
+        service_id: req.service_id.clone(),
+        winsw_path: req.winsw_path.clone(),
+        config: req.config.clone(),
+        timeout_seconds: req.timeout_seconds,
+        env_vars: req.env_vars.clone(),
+        health: req.health.clone(),
+        profile: req.profile.clone(),
+        ..Default::default()
+    };
+    let (target, timeout_secs) = match resolve_target(&app, Some(&action_req)) {
+        Ok(t) => t,
+        Err(e) => return Ok(DeployReport::failure(e.to_string())),
+    };
+
+    let sink = |line: OutputLine| {
+        if let Some(channel) = &on_output {
+            let _ = channel.send(line);
+        }
+    };
+    Ok(deploy(&app, &req, target, timeout_secs, Some(&sink)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_steps() {
+        // 首次部署：启动后失败
+        let fresh = Progress {
+            config_written: true,
+            installed: true,
+            started: true,
+            ..Progress::default()
+        };
+        assert_eq!(
+            rollback_steps(&fresh),
+            vec![
+                DeployStep::Stop,
+                DeployStep::Uninstall,
+                DeployStep::RestoreConfig
+            ]
+        );
+
+        // 升级：恢复文件后刷新并重新启动原服务
+        let upgrade = Progress {
+            stopped_running: true,
+            config_written: true,
+            binary_replaced: true,
+            refreshed: true,
+            started: true,
+            ..Progress::default()
+        };
+        assert_eq!(
+            rollback_steps(&upgrade),
+            vec![
+                DeployStep::Stop,
+                DeployStep::RestoreBinary,
+                DeployStep::RestoreConfig,
+                DeployStep::Refresh,
+                DeployStep::Start
+            ]
+        );
+
+        assert!(rollback_steps(&Progress::default()).is_empty());
+    }
+
+    #[test]
+    fn test_replace_and_restore_file() {
+        let dir = std::env::temp_dir().join(format!("winsw-deploy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let backup_dir = dir.join("backup");
+        let existing = dir.join("svc").join("myapp.xml");
+        std::fs::create_dir_all(existing.parent().unwrap()).unwrap();
+        std::fs::write(&existing, "old").unwrap();
+
+        // 替换已有文件：先备份
+        let backup = replace_file(&existing, &backup_dir, |p| std::fs::write(p, "new")).unwrap();
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "new");
+        assert_eq!(
+            std::fs::read_to_string(backup.as_ref().unwrap()).unwrap(),
+            "old"
+        );
+        restore_file(&existing, backup.as_deref()).unwrap();
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+
+        // 新建文件：无备份，恢复时删除
+        let created = dir.join("new").join("other.xml");
+        let backup = replace_file(&created, &backup_dir, |p| std::fs::write(p, "x")).unwrap();
+        assert!(backup.is_none());
+        assert!(created.exists());
+        restore_file(&created, None).unwrap();
+        assert!(!created.exists());
+        restore_file(&created, None).unwrap();
+
+        // 同一秒内的部署使用不同的备份目录
+        let first = reserve_backup_dir(&dir.join("deploy"), "myapp").unwrap();
+        let second = reserve_backup_dir(&dir.join("deploy"), "myapp").unwrap();
+        assert_ne!(first, second);
+        assert!(first.is_dir() && second.is_dir());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,392 @@
+//! 发现磁盘上已有的 WinSW 服务并纳入服务目录
+//!
+//! 在给定目录中查找 WinSW 可执行文件与配置文件的组合：
+//! - 同名约定（v2，v3 也支持）：`myapp.exe` 与同目录的 `myapp.xml`；
+//! - 显式配置（v3）：同目录下名为 `winsw*.exe` 的可执行文件，配合任意根元素为 `<service>` 的 XML。
+
+use super::catalog::{load_catalog, update_catalog, AddServiceReq, ServiceEntry};
+use super::config::{parse_document, ServiceConfig};
+use super::provision::{self, ProvisionSettings};
+use super::{parse_status, perform_action, ActionTarget, ServiceStatus, DEFAULT_TIMEOUT_SECS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const DEFAULT_MAX_DEPTH: usize = 3;
+
+/// 配置文件的关联方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Convention {
+    /// 与可执行文件同目录、同名
+    SameBasename,
+    /// 由 `winsw*.exe` 通过参数指定
+    ExplicitConfig,
+}
+
+/// 扫描参数
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DiscoverReq {
+    /// 需要扫描的目录
+    pub roots: Vec<String>,
+    /// 最大递归深度，默认为 3（0 表示只扫描根目录本身）
+    pub max_depth: Option<usize>,
+    /// 是否查询服务状态，默认为 false；只运行在 WinSW 允许列表中的可执行文件
+    pub include_status: Option<bool>,
+    /// 每次查询状态的超时时间（秒），默认为 30
+    pub timeout_seconds: Option<u64>,
+}
+
+/// 发现的服务
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredService {
+    pub winsw_path: String,
+    pub config_path: String,
+    pub convention: Convention,
+    /// 解析后的配置，解析失败时为 None
+    pub config: Option<ServiceConfig>,
+    pub error: Option<String>,
+    pub status: Option<ServiceStatus>,
+    /// 已在目录中时对应的服务 ID
+    pub catalog_id: Option<String>,
+    /// 纳入目录时建议使用的服务 ID
+    pub suggested_id: String,
+}
+
+/// 纳入目录的请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdoptReq {
+    pub config_path: String,
+    pub winsw_path: String,
+    /// 默认使用配置中的 `<id>`
+    pub id: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// 单个服务的纳入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AdoptResult {
+    pub config_path: String,
+    pub entry: Option<ServiceEntry>,
+    pub error: Option<String>,
+}
+
+/// 扫描得到的候选组合（尚未解析）
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Candidate {
+    exe: PathBuf,
+    config: PathBuf,
+    convention: Convention,
+}
+
+fn has_ext(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(ext))
+}
+
+fn stem_lower(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 是否为 WinSW 配置文件（根元素为 `<service>`）
+fn is_service_xml(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|t| parse_document(&t).ok())
+        .is_some_and(|root| root.name == "service")
+}
+
+/// 在单个目录中配对可执行文件与配置文件
+fn pair_in_dir(files: &[PathBuf]) -> Vec<Candidate> {
+    let exes: BTreeMap<String, &PathBuf> = files
+        .iter()
+        .filter(|p| has_ext(p, "exe"))
+        .map(|p| (stem_lower(p), p))
+        .collect();
+    let winsw_exe = exes
+        .iter()
+        .find(|(stem, _)| stem.starts_with("winsw"))
+        .map(|(_, p)| *p);
+
+    let mut out = Vec::new();
+    for xml in files.iter().filter(|p| has_ext(p, "xml")) {
+        let candidate = match exes.get(&stem_lower(xml)) {
+            Some(exe) => Candidate {
+                exe: (*exe).clone(),
+                config: xml.clone(),
+                convention: Convention::SameBasename,
+            },
+            None => match winsw_exe {
+                Some(exe) => Candidate {
+                    exe: exe.clone(),
+                    config: xml.clone(),
+                    convention: Convention::ExplicitConfig,
+                },
+                None => continue,
+            },
+        };
+        if is_service_xml(xml) {
+            out.push(candidate);
+        }
+    }
+    out
+}
+
+/// 递归扫描目录（不跟随符号链接）
+fn scan(root: &Path, max_depth: usize, out: &mut Vec<Candidate>) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(ft) = entry.file_type() else {
+            continue;
+        };
+        if ft.is_dir() {
+            dirs.push(entry.path());
+        } else if ft.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    dirs.sort();
+
+    out.extend(pair_in_dir(&files));
+    if max_depth > 0 {
+        for dir in dirs {
+            scan(&dir, max_depth - 1, out);
+        }
+    }
+}
+
+/// 由配置中的 ID 生成合法的目录 ID
+fn suggest_id(raw: &str) -> String {
+    let id: String = raw
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if id.is_empty() {
+        "service".to_string()
+    } else {
+        id
+    }
+}
+
+/// 比较路径时使用的规范形式（Windows 路径不区分大小写）
+fn path_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+/// 解析候选并标记已在目录中的服务
+fn describe(candidate: Candidate, known: &BTreeMap<String, String>) -> DiscoveredService {
+    let (config, error) = match ServiceConfig::load(&candidate.config) {
+        Ok(c) => (Some(c), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let suggested_id = suggest_id(
+        &config
+            .as_ref()
+            .map(|c| c.id.clone())
+            .unwrap_or_else(|| stem_lower(&candidate.config)),
+    );
+    DiscoveredService {
+        winsw_path: candidate.exe.display().to_string(),
+        config_path: candidate.config.display().to_string(),
+        convention: candidate.convention,
+        config,
+        error,
+        status: None,
+        catalog_id: known.get(&path_key(&candidate.config)).cloned(),
+        suggested_id,
+    }
+}
+
+/// Tauri 命令：扫描目录，列出已有的 WinSW 服务及其状态
+#[tauri::command]
+pub async fn winsw_discover(
+    app: AppHandle,
+    req: DiscoverReq,
+) -> Result<Vec<DiscoveredService>, String> {
+    let known: BTreeMap<String, String> = load_catalog(&app)
+        .map_err(|e| e.to_string())?
+        .list()
+        .iter()
+        .map(|s| (path_key(Path::new(&s.config_path)), s.id.clone()))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for root in &req.roots {
+        scan(
+            Path::new(root),
+            req.max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            &mut candidates,
+        );
+    }
+
+    let timeout_secs = req.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let include_status = req.include_status.unwrap_or(false);
+    let settings = if include_status {
+        provision::app_settings(&app).map_err(|e| e.to_string())?
+    } else {
+        ProvisionSettings::default()
+    };
+    let mut found = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let mut service = describe(candidate, &known);
+        if include_status && service.config.is_some() {
+            // 扫描到的可执行文件来源未知，不在允许列表中的不运行
+            match provision::sha256_file(Path::new(&service.winsw_path)) {
+                Ok(sha) if settings.verify(&sha) == Some(true) => {}
+                Ok(_) => {
+                    service.error = Some("WinSW 可执行文件不在允许列表中，未查询状态".to_string());
+                    found.push(service);
+                    continue;
+                }
+                Err(e) => {
+                    service.error = Some(format!("读取 WinSW 可执行文件失败: {}", e));
+                    found.push(service);
+                    continue;
+                }
+            }
+            let target = ActionTarget {
+                service_id: service.catalog_id.clone(),
+                winsw_path: service.winsw_path.clone(),
+                config: Some(service.config_path.clone()),
+                ..Default::default()
+            };
+            match perform_action(&app, "status", &target, timeout_secs, None).await {
+                Ok(resp) => {
+                    service.status = Some(parse_status(resp.exec.stdout.as_deref().unwrap_or("")))
+                }
+                Err(e) => service.error = Some(e.to_string()),
+            }
+        }
+        found.push(service);
+    }
+    Ok(found)
+}
+
+/// Tauri 命令：将选中的服务纳入目录，逐个返回结果
+#[tauri::command]
+pub async fn winsw_discover_adopt(
+    app: AppHandle,
+    items: Vec<AdoptReq>,
+) -> Result<Vec<AdoptResult>, String> {
+    update_catalog(&app, |catalog| {
+        Ok(items
+            .into_iter()
+            .map(|item| {
+                let id = item.id.clone().unwrap_or_else(|| {
+                    let cfg = ServiceConfig::load(&item.config_path).ok();
+                    suggest_id(
+                        &cfg.map(|c| c.id)
+                            .unwrap_or_else(|| stem_lower(Path::new(&item.config_path))),
+                    )
+                });
+                let req = AddServiceReq {
+                    id,
+                    config_path: item.config_path.clone(),
+                    winsw_path: Some(item.winsw_path),
+                    tags: item.tags,
+                    env_vars: None,
+                    profile: None,
+                    health: None,
+                    depends_on: None,
+                };
+                match catalog.add(req) {
+                    Ok(entry) => AdoptResult {
+                        config_path: item.config_path,
+                        entry: Some(entry.clone()),
+                        error: None,
+                    },
+                    Err(e) => AdoptResult {
+                        config_path: item.config_path,
+                        entry: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_pairs_conventions() {
+        let dir = std::env::temp_dir().join(format!("winsw-discover-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let write = |rel: &str, content: &str| {
+            let p = dir.join(rel);
+            std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+            std::fs::write(p, content).unwrap();
+        };
+        // v2 同名约定
+        write("a/MyApp.exe", "");
+        write("a/myapp.xml", "<service><id>myapp</id></service>");
+        // v3 显式配置，非服务 XML 被忽略
+        write("b/WinSW-x64.exe", "");
+        write("b/api.xml", "<service><id>api svc</id></service>");
+        write("b/worker.xml", "<service><id>worker</id></service>");
+        write("b/notes.xml", "<notes/>");
+        // 没有可执行文件的配置被忽略
+        write("c/orphan.xml", "<service><id>orphan</id></service>");
+        // 超出深度
+        write("d/1/2/3/deep.exe", "");
+        write("d/1/2/3/deep.xml", "<service><id>deep</id></service>");
+
+        let mut found = Vec::new();
+        scan(&dir, 3, &mut found);
+        let names: Vec<(String, Convention)> = found
+            .iter()
+            .map(|c| {
+                (
+                    c.config.file_name().unwrap().to_string_lossy().to_string(),
+                    c.convention,
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("myapp.xml".to_string(), Convention::SameBasename),
+                ("api.xml".to_string(), Convention::ExplicitConfig),
+                ("worker.xml".to_string(), Convention::ExplicitConfig),
+            ]
+        );
+        assert!(found[1].exe.ends_with("WinSW-x64.exe"));
+
+        // 已在目录中的服务被标记，ID 被规范化
+        let known = BTreeMap::from([(path_key(&found[0].config), "legacy".to_string())]);
+        let first = describe(found[0].clone(), &known);
+        assert_eq!(first.catalog_id.as_deref(), Some("legacy"));
+        assert_eq!(first.suggested_id, "myapp");
+        let api = describe(found[1].clone(), &known);
+        assert_eq!(api.catalog_id, None);
+        assert_eq!(api.suggested_id, "api_svc");
+
+        let mut deeper = Vec::new();
+        scan(&dir, 4, &mut deeper);
+        assert_eq!(deeper.len(), 4);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
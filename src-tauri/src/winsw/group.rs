@@ -0,0 +1,473 @@
+//! 服务组的依赖排序与整体启停
+//!
+//! 启动顺序由两部分依赖共同决定：配置文件中的 `<depend>`（按 WinSW 服务 ID 匹配组内成员）
+//! 与目录中显式声明的 `depends_on`。排序结果按“层”给出，同一层的服务互不依赖、并行执行；
+//! 启动按层正序，停止按层逆序。组外的依赖不参与排序。
+
+use super::catalog::{load_catalog, update_catalog, ServiceCatalog, ServiceGroup};
+use super::config::ServiceConfig;
+use super::{perform_action, ActionResp, ActionTarget, WinswError, DEFAULT_TIMEOUT_SECS};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tauri::AppHandle;
+use tokio::task::{self, JoinSet};
+
+/// 服务组的执行计划
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupPlan {
+    pub group_id: String,
+    /// 启动顺序，每一层内的服务可并行执行
+    pub layers: Vec<Vec<String>>,
+    /// 组内的依赖关系：服务 → 其依赖的组内服务
+    pub depends: BTreeMap<String, Vec<String>>,
+}
+
+/// 单个成员的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStep {
+    pub service_id: String,
+    pub layer: usize,
+    pub ok: bool,
+    pub resp: ActionResp,
+}
+
+/// 服务组启停报告
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupReport {
+    pub ok: bool,
+    pub plan: Option<GroupPlan>,
+    pub steps: Vec<GroupStep>,
+    /// 执行失败的服务
+    pub failed: Vec<String>,
+    /// 因前序失败而未执行的服务
+    pub skipped: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl GroupReport {
+    fn failure(error: String) -> Self {
+        Self {
+            ok: false,
+            plan: None,
+            steps: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// 服务组启停参数
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GroupRunReq {
+    /// 每个服务的超时时间（秒），默认为 30
+    pub timeout_seconds: Option<u64>,
+}
+
+/// 按依赖分层排序（Kahn 算法），存在循环依赖时返回包含循环路径的错误
+pub fn layered_order(
+    depends: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Vec<String>>, WinswError> {
+    let mut remaining: BTreeMap<&str, BTreeSet<&str>> = depends
+        .iter()
+        .map(|(k, deps)| {
+            (
+                k.as_str(),
+                deps.iter()
+                    .map(String::as_str)
+                    .filter(|d| depends.contains_key(*d))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let mut layers = Vec::new();
+    while !remaining.is_empty() {
+        let ready: Vec<&str> = remaining
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(k, _)| *k)
+            .collect();
+        if ready.is_empty() {
+            return Err(WinswError::DependencyCycle(find_cycle(&remaining)));
+        }
+        for id in &ready {
+            remaining.remove(id);
+        }
+        for deps in remaining.values_mut() {
+            for id in &ready {
+                deps.remove(id);
+            }
+        }
+        layers.push(ready.into_iter().map(String::from).collect());
+    }
+    Ok(layers)
+}
+
+/// 在剩余（均有未满足依赖）的节点中找出一条循环路径
+fn find_cycle(remaining: &BTreeMap<&str, BTreeSet<&str>>) -> String {
+    let Some(start) = remaining.keys().next() else {
+        return String::new();
+    };
+    // 每个剩余节点都至少有一个剩余依赖，沿依赖走必然回到走过的节点
+    let mut path: Vec<&str> = vec![start];
+    loop {
+        let current = *path.last().expect("non-empty path");
+        let next = remaining[current]
+            .iter()
+            .next()
+            .copied()
+            .expect("remaining node has deps");
+        if let Some(pos) = path.iter().position(|p| *p == next) {
+            let mut cycle: Vec<&str> = path[pos..].to_vec();
+            cycle.push(next);
+            return cycle.join(" -> ");
+        }
+        path.push(next);
+    }
+}
+
+/// 计算服务组的依赖关系与执行计划
+pub fn plan_group(catalog: &ServiceCatalog, group: &ServiceGroup) -> Result<GroupPlan, WinswError> {
+    let members: Vec<&str> = group.members.iter().map(String::as_str).collect();
+
+    // 加载成员配置，建立 WinSW 服务 ID → 目录服务 ID 的映射
+    let mut configs = HashMap::new();
+    let mut by_service_id: HashMap<String, String> = HashMap::new();
+    for id in &members {
+        let entry = catalog.require(id)?;
+        let cfg = ServiceConfig::load(&entry.config_path)?;
+        by_service_id.insert(cfg.id.to_lowercase(), id.to_string());
+        configs.insert(*id, cfg);
+    }
+
+    let mut depends = BTreeMap::new();
+    for id in &members {
+        let entry = catalog.require(id)?;
+        let mut deps: BTreeSet<String> = BTreeSet::new();
+        // Windows 服务名不区分大小写
+        for dep in &configs[id].depends {
+            if let Some(member) = by_service_id.get(&dep.to_lowercase()) {
+                deps.insert(member.clone());
+            }
+        }
+        for dep in &entry.depends_on {
+            if members.contains(&dep.as_str()) {
+                deps.insert(dep.clone());
+            }
+        }
+        deps.remove(*id);
+        depends.insert(id.to_string(), deps.into_iter().collect());
+    }
+
+    Ok(GroupPlan {
+        group_id: group.id.clone(),
+        layers: layered_order(&depends)?,
+        depends,
+    })
+}
+
+/// 等待一层的全部任务，按服务 ID 排序返回各步结果
+///
+/// 任务异常退出（panic 或被取消）时记为该服务失败，使后续层（依赖它的服务）被跳过。
+async fn join_layer(
+    mut tasks: JoinSet<ActionResp>,
+    task_ids: &HashMap<task::Id, String>,
+    layer: usize,
+    action: &str,
+) -> Vec<GroupStep> {
+    let mut steps = Vec::new();
+    while let Some(joined) = tasks.join_next_with_id().await {
+        let (task_id, resp) = match joined {
+            Ok((task_id, resp)) => (task_id, resp),
+            Err(e) => {
+                log::error!("服务组任务异常: {}", e);
+                let resp = ActionResp::failure(-1, format!("执行 {} 时任务异常: {}", action, e));
+                (e.id(), resp)
+            }
+        };
+        let Some(id) = task_ids.get(&task_id) else {
+            continue;
+        };
+        let ok = resp.exec.ok && resp.exec.code == 0;
+        steps.push(GroupStep {
+            service_id: id.clone(),
+            layer,
+            ok,
+            resp,
+        });
+    }
+    steps.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+    steps
+}
+
+/// 按计划逐层执行操作；某层有失败时不再执行后续层
+async fn run_plan(
+    app: &AppHandle,
+    catalog: &ServiceCatalog,
+    plan: GroupPlan,
+    action: &'static str,
+    timeout_secs: u64,
+) -> GroupReport {
+    let layers: Vec<Vec<String>> = if action == "stop" {
+        plan.layers.iter().rev().cloned().collect()
+    } else {
+        plan.layers.clone()
+    };
+
+    let mut steps = Vec::new();
+    let mut failed = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (layer_idx, layer) in layers.iter().enumerate() {
+        if !failed.is_empty() {
+            skipped.extend(layer.iter().cloned());
+            continue;
+        }
+
+        let mut tasks = JoinSet::new();
+        // 任务 ID → 服务 ID，任务异常退出时据此记录失败
+        let mut task_ids = HashMap::new();
+        for id in layer {
+            let target = match catalog
+                .require(id)
+                .and_then(|entry| ActionTarget::from_entry(app, entry))
+            {
+                Ok(target) => target,
+                Err(e) => {
+                    failed.push(id.clone());
+                    steps.push(GroupStep {
+                        service_id: id.clone(),
+                        layer: layer_idx,
+                        ok: false,
+                        resp: ActionResp::failure(-1, e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            let app = app.clone();
+            let handle = tasks.spawn(async move {
+                match perform_action(&app, action, &target, timeout_secs, None).await {
+                    Ok(resp) => resp,
+                    Err(e) => ActionResp::from_error(e),
+                }
+            });
+            task_ids.insert(handle.id(), id.clone());
+        }
+
+        let layer_steps = join_layer(tasks, &task_ids, layer_idx, action).await;
+        failed.extend(
+            layer_steps
+                .iter()
+                .filter(|s| !s.ok)
+                .map(|s| s.service_id.clone()),
+        );
+        steps.extend(layer_steps);
+    }
+
+    let error = if failed.is_empty() {
+        None
+    } else {
+        Some(format!("服务执行 {} 失败: {}", action, failed.join(", ")))
+    };
+    GroupReport {
+        ok: failed.is_empty(),
+        plan: Some(plan),
+        steps,
+        failed,
+        skipped,
+        error,
+    }
+}
+
+async fn run_group(
+    app: &AppHandle,
+    group_id: &str,
+    action: &'static str,
+    req: Option<GroupRunReq>,
+) -> GroupReport {
+    let timeout_secs = req
+        .and_then(|r| r.timeout_seconds)
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let prepared = load_catalog(app).and_then(|catalog| {
+        let plan = plan_group(&catalog, catalog.require_group(group_id)?)?;
+        Ok((catalog, plan))
+    });
+    match prepared {
+        Ok((catalog, plan)) => run_plan(app, &catalog, plan, action, timeout_secs).await,
+        Err(e) => GroupReport::failure(e.to_string()),
+    }
+}
+
+/// Tauri 命令：新增或替换服务组
+#[tauri::command]
+pub async fn winsw_group_save(app: AppHandle, group: ServiceGroup) -> Result<ServiceGroup, String> {
+    update_catalog(&app, |c| c.save_group(group).cloned())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：移除服务组
+#[tauri::command]
+pub async fn winsw_group_remove(app: AppHandle, id: String) -> Result<ServiceGroup, String> {
+    update_catalog(&app, |c| c.remove_group(&id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：列出所有服务组
+#[tauri::command]
+pub async fn winsw_group_list(app: AppHandle) -> Result<Vec<ServiceGroup>, String> {
+    load_catalog(&app)
+        .map(|c| c.groups().to_vec())
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：计算服务组的启动顺序（不执行任何操作）
+#[tauri::command]
+pub async fn winsw_group_plan(app: AppHandle, id: String) -> Result<GroupPlan, String> {
+    load_catalog(&app)
+        .and_then(|c| plan_group(&c, c.require_group(&id)?))
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：按依赖顺序启动服务组，某个服务失败时停止后续启动
+#[tauri::command]
+pub async fn winsw_group_start(
+    app: AppHandle,
+    id: String,
+    req: Option<GroupRunReq>,
+) -> Result<GroupReport, String> {
+    Ok(run_group(&app, &id, "start", req).await)
+}
+
+/// Tauri 命令：按依赖逆序停止服务组
+#[tauri::command]
+pub async fn winsw_group_stop(
+    app: AppHandle,
+    id: String,
+    req: Option<GroupRunReq>,
+) -> Result<GroupReport, String> {
+    Ok(run_group(&app, &id, "stop", req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winsw::catalog::AddServiceReq;
+    use std::path::PathBuf;
+
+    fn deps(pairs: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layered_order() {
+        let layers = layered_order(&deps(&[
+            ("gateway", &["api"]),
+            ("api", &["db", "mq"]),
+            ("mq", &["db"]),
+            ("db", &[]),
+            ("metrics", &[]),
+        ]))
+        .unwrap();
+        assert_eq!(
+            layers,
+            vec![
+                vec!["db", "metrics"],
+                vec!["mq"],
+                vec!["api"],
+                vec!["gateway"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let err = layered_order(&deps(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["a"]),
+            ("d", &[]),
+        ]))
+        .unwrap_err();
+        match err {
+            WinswError::DependencyCycle(path) => assert_eq!(path, "a -> b -> c -> a"),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_plan_group_merges_config_and_catalog_deps() {
+        let dir: PathBuf = std::env::temp_dir().join(format!("winsw-group-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut catalog = ServiceCatalog::load(dir.join("catalog.json")).unwrap();
+        let services = [
+            ("db", "<service><id>PostgreSQL</id></service>", vec![]),
+            (
+                "api",
+                "<service><id>api</id><depend>postgresql</depend><depend>Tcpip</depend></service>",
+                vec![],
+            ),
+            ("gateway", "<service><id>gw</id></service>", vec!["api"]),
+        ];
+        for (id, xml, depends_on) in services {
+            let path = dir.join(format!("{}.xml", id));
+            std::fs::write(&path, xml).unwrap();
+            catalog
+                .add(AddServiceReq {
+                    id: id.into(),
+                    config_path: path.to_string_lossy().to_string(),
+                    winsw_path: None,
+                    tags: None,
+                    env_vars: None,
+                    profile: None,
+                    health: None,
+                    depends_on: Some(depends_on.into_iter().map(String::from).collect()),
+                })
+                .unwrap();
+        }
+        let group = ServiceGroup {
+            id: "stack".into(),
+            description: None,
+            members: vec!["gateway".into(), "api".into(), "db".into()],
+        };
+
+        let plan = plan_group(&catalog, &group).unwrap();
+        assert_eq!(plan.layers, vec![vec!["db"], vec!["api"], vec!["gateway"]]);
+        assert_eq!(plan.depends["api"], vec!["db"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_join_layer_records_panicked_task() {
+        let mut tasks = JoinSet::new();
+        let mut task_ids = HashMap::new();
+        let ok = tasks.spawn(async { ActionResp::success(None, None, 0) });
+        task_ids.insert(ok.id(), "db".to_string());
+        let panicked = tasks.spawn(async {
+            panic!("boom");
+        });
+        task_ids.insert(panicked.id(), "api".to_string());
+
+        let steps = join_layer(tasks, &task_ids, 0, "start").await;
+        let summary: Vec<(&str, bool)> = steps
+            .iter()
+            .map(|s| (s.service_id.as_str(), s.ok))
+            .collect();
+        assert_eq!(summary, vec![("api", false), ("db", true)]);
+        assert!(steps[0]
+            .resp
+            .exec
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("任务异常"));
+    }
+}
@@ -0,0 +1,550 @@
+//! WinSW 服务启动后的健康检查
+//!
+//! WinSW 的 `start` 成功只代表包装进程已启动，被托管的程序仍可能在几秒后退出。
+//! 这里在 `start` / `restart` 之后轮询一组检查项，直到全部通过（并保持稳定）或超过期限。
+
+use super::config::ServiceConfig;
+use super::logs::{LogFollower, LogLocator, LogStream};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout, Duration};
+
+const DEFAULT_HEALTH_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const PROBE_TIMEOUT_SECS: u64 = 5;
+
+/// 单个检查项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HealthCheck {
+    /// TCP 端口可连接
+    Tcp {
+        /// 主机，默认为 127.0.0.1
+        host: Option<String>,
+        port: u16,
+    },
+    /// HTTP GET 返回期望的状态码（仅支持 http://）
+    Http {
+        url: String,
+        /// 期望的状态码，默认为 200
+        expect_status: Option<u16>,
+    },
+    /// 进程存活
+    Process {
+        /// 进程映像名（如 `java.exe`），默认取配置中 `<executable>` 的文件名
+        name: Option<String>,
+    },
+    /// 启动后新写入的日志行匹配正则表达式
+    LogMatch {
+        pattern: String,
+        /// 日志类型，默认为 out
+        stream: Option<LogStream>,
+    },
+}
+
+/// 服务的健康检查定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthSpec {
+    pub checks: Vec<HealthCheck>,
+    /// 等待健康的期限（秒），默认为 60
+    pub timeout_seconds: Option<u64>,
+    /// 轮询间隔（毫秒），默认为 1000
+    pub interval_ms: Option<u64>,
+    /// 全部通过后需保持健康的时长（秒），默认为 0
+    pub stable_seconds: Option<u64>,
+}
+
+/// 单个检查项的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub check: HealthCheck,
+    pub passed: bool,
+    pub attempts: u32,
+    /// 最近一次失败的原因
+    pub message: Option<String>,
+}
+
+/// 健康检查报告
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub elapsed_ms: u64,
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    /// 未通过的检查项描述，用于错误信息
+    pub fn failure_summary(&self) -> String {
+        self.checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| {
+                format!(
+                    "{}: {}",
+                    describe(&c.check),
+                    c.message.as_deref().unwrap_or("未通过")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+fn describe(check: &HealthCheck) -> String {
+    match check {
+        HealthCheck::Tcp { host, port } => {
+            format!("tcp {}:{}", host.as_deref().unwrap_or("127.0.0.1"), port)
+        }
+        HealthCheck::Http { url, .. } => format!("http {}", url),
+        HealthCheck::Process { name } => format!("process {}", name.as_deref().unwrap_or("?")),
+        HealthCheck::LogMatch { pattern, .. } => format!("log /{}/", pattern),
+    }
+}
+
+/// 检查项的运行时状态
+enum Probe {
+    Tcp(String),
+    Http {
+        url: String,
+        expect: u16,
+    },
+    Process(Option<String>),
+    /// 日志匹配一旦命中即视为通过
+    Log {
+        regex: Regex,
+        follower: Option<LogFollower>,
+        locator: Option<LogLocator>,
+        matched: bool,
+    },
+    /// 定义本身无效（如正则错误），始终失败
+    Invalid(String),
+}
+
+impl Probe {
+    fn prepare(check: &HealthCheck, cfg: Option<&ServiceConfig>) -> Self {
+        match check {
+            HealthCheck::Tcp { host, port } => Probe::Tcp(format!(
+                "{}:{}",
+                host.as_deref().unwrap_or("127.0.0.1"),
+                port
+            )),
+            HealthCheck::Http { url, expect_status } => Probe::Http {
+                url: url.clone(),
+                expect: expect_status.unwrap_or(200),
+            },
+            HealthCheck::Process { name } => Probe::Process(name.clone().or_else(|| {
+                cfg.and_then(|c| c.executable.as_deref())
+                    .map(executable_image_name)
+            })),
+            HealthCheck::LogMatch { pattern, stream } => match Regex::new(pattern) {
+                Ok(regex) => {
+                    // 在服务启动前定位到日志末尾，只匹配之后新写入的行
+                    let locator = cfg.map(LogLocator::new);
+                    let follower = locator
+                        .as_ref()
+                        .map(|l| LogFollower::new(l, stream.unwrap_or(LogStream::Out)));
+                    Probe::Log {
+                        regex,
+                        follower,
+                        locator,
+                        matched: false,
+                    }
+                }
+                Err(e) => Probe::Invalid(format!("正则表达式无效: {}", e)),
+            },
+        }
+    }
+
+    async fn run(&mut self) -> Result<(), String> {
+        let probe_timeout = Duration::from_secs(PROBE_TIMEOUT_SECS);
+        match self {
+            Probe::Tcp(addr) => {
+                match timeout(probe_timeout, TcpStream::connect(addr.as_str())).await {
+                    Ok(Ok(_)) => Ok(()),
+                    Ok(Err(e)) => Err(format!("无法连接: {}", e)),
+                    Err(_) => Err("连接超时".into()),
+                }
+            }
+            Probe::Http { url, expect } => {
+                match timeout(probe_timeout, http_get_status(url)).await {
+                    Ok(Ok(status)) if status == *expect => Ok(()),
+                    Ok(Ok(status)) => Err(format!("状态码 {}，期望 {}", status, expect)),
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err("请求超时".into()),
+                }
+            }
+            Probe::Process(None) => Err("未指定进程名且配置中没有 <executable>".into()),
+            Probe::Process(Some(name)) => {
+                if process_running(name).await {
+                    Ok(())
+                } else {
+                    Err("进程未运行".into())
+                }
+            }
+            Probe::Log {
+                regex,
+                follower,
+                locator,
+                matched,
+            } => {
+                if *matched {
+                    return Ok(());
+                }
+                let (Some(follower), Some(locator)) = (follower.as_mut(), locator.as_ref()) else {
+                    return Err("缺少服务配置，无法定位日志".into());
+                };
+                let lines = follower.poll(locator).map_err(|e| e.to_string())?;
+                if lines.iter().any(|l| regex.is_match(&l.line)) {
+                    *matched = true;
+                    Ok(())
+                } else {
+                    Err("尚未出现匹配的日志行".into())
+                }
+            }
+            Probe::Invalid(msg) => Err(msg.clone()),
+        }
+    }
+}
+
+/// 立即执行一轮检查（用于运行期间的周期巡检）
+///
+/// 日志匹配只关注启动后新写入的行，不适用于巡检，因此被跳过。
+pub async fn check_once(spec: &HealthSpec, cfg: Option<&ServiceConfig>) -> HealthReport {
+    let spec = HealthSpec {
+        checks: spec
+            .checks
+            .iter()
+            .filter(|c| !matches!(c, HealthCheck::LogMatch { .. }))
+            .cloned()
+            .collect(),
+        timeout_seconds: Some(0),
+        interval_ms: Some(0),
+        stable_seconds: Some(0),
+    };
+    HealthWaiter::prepare(&spec, cfg).wait().await
+}
+
+/// 一次健康等待：在服务启动前创建，启动完成后调用 [`HealthWaiter::wait`]
+pub struct HealthWaiter {
+    spec: HealthSpec,
+    probes: Vec<Probe>,
+}
+
+impl HealthWaiter {
+    pub fn prepare(spec: &HealthSpec, cfg: Option<&ServiceConfig>) -> Self {
+        Self {
+            spec: spec.clone(),
+            probes: spec.checks.iter().map(|c| Probe::prepare(c, cfg)).collect(),
+        }
+    }
+
+    /// 轮询所有检查项，直到全部通过并保持 `stable_seconds`，或超过期限
+    pub async fn wait(mut self) -> HealthReport {
+        let started = Instant::now();
+        let deadline = Duration::from_secs(
+            self.spec
+                .timeout_seconds
+                .unwrap_or(DEFAULT_HEALTH_TIMEOUT_SECS),
+        );
+        let interval = Duration::from_millis(self.spec.interval_ms.unwrap_or(DEFAULT_INTERVAL_MS));
+        let stable = Duration::from_secs(self.spec.stable_seconds.unwrap_or(0));
+
+        let mut results: Vec<CheckResult> = self
+            .spec
+            .checks
+            .iter()
+            .map(|c| CheckResult {
+                check: c.clone(),
+                passed: false,
+                attempts: 0,
+                message: None,
+            })
+            .collect();
+        let mut healthy_since: Option<Instant> = None;
+
+        loop {
+            for (probe, result) in self.probes.iter_mut().zip(results.iter_mut()) {
+                result.attempts += 1;
+                match probe.run().await {
+                    Ok(()) => {
+                        result.passed = true;
+                        result.message = None;
+                    }
+                    Err(msg) => {
+                        result.passed = false;
+                        result.message = Some(msg);
+                    }
+                }
+            }
+
+            if results.iter().all(|r| r.passed) {
+                let since = *healthy_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= stable {
+                    return HealthReport {
+                        healthy: true,
+                        elapsed_ms: started.elapsed().as_millis() as u64,
+                        checks: results,
+                    };
+                }
+            } else {
+                healthy_since = None;
+            }
+
+            if started.elapsed() >= deadline {
+                return HealthReport {
+                    healthy: false,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    checks: results,
+                };
+            }
+            sleep(interval).await;
+        }
+    }
+}
+
+/// `<executable>` 对应的进程映像名，如 `C:\jdk\bin\java` → `java.exe`
+fn executable_image_name(executable: &str) -> String {
+    let file = executable
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(executable)
+        .trim_matches('"');
+    if cfg!(windows) && !file.to_lowercase().ends_with(".exe") {
+        format!("{}.exe", file)
+    } else {
+        file.to_string()
+    }
+}
+
+/// 发送 HTTP GET 请求并返回状态码
+async fn http_get_status(url: &str) -> Result<u16, String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| "仅支持 http:// 地址".to_string())?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let addr = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut stream = TcpStream::connect(&addr)
+        .await
+        .map_err(|e| format!("无法连接: {}", e))?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nUser-Agent: winsw-health\r\n\r\n",
+        path, authority
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut head = Vec::new();
+    let mut buf = [0u8; 512];
+    while !head.windows(2).any(|w| w == b"\r\n") {
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let status_line = String::from_utf8_lossy(&head);
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| {
+            format!(
+                "无效的 HTTP 响应: {}",
+                status_line.lines().next().unwrap_or("")
+            )
+        })
+}
+
+/// 按映像名检查进程是否存在
+#[cfg(windows)]
+async fn process_running(name: &str) -> bool {
+    let filter = format!("IMAGENAME eq {}", name);
+    match tokio::process::Command::new("tasklist")
+        .args(["/FI", &filter, "/NH", "/FO", "CSV"])
+        .output()
+        .await
+    {
+        Ok(out) => String::from_utf8_lossy(&out.stdout)
+            .to_lowercase()
+            .contains(&format!("\"{}\"", name.to_lowercase())),
+        Err(_) => false,
+    }
+}
+
+/// 按进程名检查进程是否存在（非 Windows 平台读取 /proc，便于测试）
+///
+/// 优先比较 `/proc/<pid>/exe` 的文件名；无权读取时退回 `comm`，它只保留前 15 字节。
+#[cfg(not(windows))]
+async fn process_running(name: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.flatten().any(|e| {
+        if let Ok(exe) = std::fs::read_link(e.path().join("exe")) {
+            return exe.file_name().is_some_and(|n| n == name);
+        }
+        std::fs::read_to_string(e.path().join("comm"))
+            .map(|comm| comm_matches(comm.trim_end_matches('\n'), name))
+            .unwrap_or(false)
+    })
+}
+
+/// `comm` 被内核截断为 15 字节（TASK_COMM_LEN - 1），较长的名称按前缀比较
+#[cfg(not(windows))]
+fn comm_matches(comm: &str, name: &str) -> bool {
+    const COMM_LEN: usize = 15;
+    comm == name || (name.len() > COMM_LEN && name.as_bytes()[..COMM_LEN] == *comm.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn spec(checks: Vec<HealthCheck>, timeout_seconds: u64) -> HealthSpec {
+        HealthSpec {
+            checks,
+            timeout_seconds: Some(timeout_seconds),
+            interval_ms: Some(50),
+            stable_seconds: None,
+        }
+    }
+
+    async fn serve_http(status: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = sock.read(&mut buf).await;
+                let resp = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_tcp_and_http_checks_pass() {
+        let port = serve_http("200 OK").await;
+        let report = HealthWaiter::prepare(
+            &spec(
+                vec![
+                    HealthCheck::Tcp { host: None, port },
+                    HealthCheck::Http {
+                        url: format!("http://127.0.0.1:{}/health", port),
+                        expect_status: None,
+                    },
+                ],
+                5,
+            ),
+            None,
+        )
+        .wait()
+        .await;
+
+        assert!(report.healthy, "{}", report.failure_summary());
+        assert!(report.checks.iter().all(|c| c.passed && c.attempts == 1));
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_process_running_long_name() {
+        assert!(comm_matches("my-long-service", "my-long-service-name"));
+        assert!(!comm_matches("my-long-servic", "my-long-service-name"));
+        assert!(comm_matches("short", "short"));
+
+        // 测试程序的文件名通常超过 15 字节
+        let exe = std::env::current_exe().unwrap();
+        let name = exe.file_name().unwrap().to_string_lossy().to_string();
+        assert!(process_running(&name).await, "{}", name);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_after_deadline() {
+        let port = serve_http("503 Service Unavailable").await;
+        let report = HealthWaiter::prepare(
+            &spec(
+                vec![
+                    HealthCheck::Http {
+                        url: format!("http://127.0.0.1:{}/", port),
+                        expect_status: Some(200),
+                    },
+                    HealthCheck::Process {
+                        name: Some("no-such-process-xyz".into()),
+                    },
+                    HealthCheck::LogMatch {
+                        pattern: "(".into(),
+                        stream: None,
+                    },
+                ],
+                0,
+            ),
+            None,
+        )
+        .wait()
+        .await;
+
+        assert!(!report.healthy);
+        assert!(report.checks.iter().all(|c| !c.passed));
+        assert!(report.checks[0].message.as_deref().unwrap().contains("503"));
+        assert!(report.failure_summary().contains("正则表达式无效"));
+    }
+
+    #[tokio::test]
+    async fn test_log_match_only_sees_new_lines() {
+        let dir = std::env::temp_dir().join(format!("winsw-health-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("svc.out.log");
+        std::fs::write(&log, "Started old run\n").unwrap();
+        let cfg =
+            ServiceConfig::parse("<service><id>svc</id></service>", &dir.join("svc.xml")).unwrap();
+
+        let waiter = HealthWaiter::prepare(
+            &spec(
+                vec![HealthCheck::LogMatch {
+                    pattern: r"Started \w+ in".into(),
+                    stream: None,
+                }],
+                5,
+            ),
+            Some(&cfg),
+        );
+        std::fs::write(
+            &log,
+            "Started old run\nStarted Application in 3.2 seconds\n",
+        )
+        .unwrap();
+
+        let report = waiter.wait().await;
+        assert!(report.healthy, "{}", report.failure_summary());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_executable_image_name() {
+        let name = executable_image_name(r"C:\jdk\bin\java");
+        if cfg!(windows) {
+            assert_eq!(name, "java.exe");
+        } else {
+            assert_eq!(name, "java");
+        }
+        assert_eq!(executable_image_name("/usr/bin/nginx.exe"), "nginx.exe");
+    }
+}
@@ -0,0 +1,646 @@
+//! 配置文件历史
+//!
+//! 应用写入配置文件或执行 `refresh` 时保存一份快照，存放在
+//! `<app_data_dir>/winsw/history/<配置文件名>-<路径哈希>/` 下，由 `index.json` 记录各个版本。
+//! 内容与上一版本相同时不重复保存。版本之间按元素对比，回滚时写回所选版本。
+
+use super::catalog::{now_secs, resolve_config};
+use super::config::{parse_document, XmlElement, XmlNode};
+use super::{data_dir, refresh_service, resolve_target, ActionReq, ActionResp, WinswError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const HISTORY_DIR: &str = "history";
+const INDEX_FILE: &str = "index.json";
+
+/// 历史读写锁，避免并发快照交错写入索引
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// 快照原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotReason {
+    /// 应用写入配置之前的原始内容
+    Baseline,
+    /// 应用写入的新内容
+    Write,
+    /// 执行 `refresh` 时的内容
+    Refresh,
+    /// 回滚写回的内容
+    Rollback,
+}
+
+/// 一个历史版本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    /// 版本号（毫秒时间戳，单调递增）
+    pub id: u64,
+    pub created_at: u64,
+    pub reason: SnapshotReason,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryIndex {
+    config_path: String,
+    revisions: Vec<Revision>,
+}
+
+/// 单个配置文件的历史
+#[derive(Debug)]
+pub struct ConfigHistory {
+    dir: PathBuf,
+    config: PathBuf,
+    index: HistoryIndex,
+}
+
+fn io_err(e: std::io::Error) -> WinswError {
+    WinswError::FileIo(e.to_string())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 历史目录名：配置文件名加路径哈希，避免同名配置互相覆盖
+///
+/// 只规范化所在目录，配置文件尚未创建时与创建之后得到相同的目录名。
+fn history_key(config: &Path) -> String {
+    let parent = match config.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let parent = parent.canonicalize().unwrap_or_else(|_| {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(&parent))
+            .unwrap_or(parent)
+    });
+    let absolute = match config.file_name() {
+        Some(name) => parent.join(name),
+        None => config.to_path_buf(),
+    };
+    // Windows 路径不区分大小写
+    let hash = sha256_hex(absolute.to_string_lossy().to_lowercase().as_bytes());
+    let stem: String = config
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}", stem, &hash[..8])
+}
+
+impl ConfigHistory {
+    /// 打开配置文件的历史，`root` 为历史根目录
+    pub fn open(root: &Path, config: &Path) -> Result<Self, WinswError> {
+        let dir = root.join(history_key(config));
+        let index = match std::fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|e| WinswError::CatalogParse(e.to_string()))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HistoryIndex {
+                config_path: config.display().to_string(),
+                revisions: Vec::new(),
+            },
+            Err(e) => return Err(io_err(e)),
+        };
+        Ok(Self {
+            dir,
+            config: config.to_path_buf(),
+            index,
+        })
+    }
+
+    /// 所有版本，按时间先后排列
+    pub fn revisions(&self) -> &[Revision] {
+        &self.index.revisions
+    }
+
+    pub fn get(&self, id: u64) -> Result<&Revision, WinswError> {
+        self.index
+            .revisions
+            .iter()
+            .find(|r| r.id == id)
+            .ok_or(WinswError::RevisionNotFound(id))
+    }
+
+    fn revision_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{}.xml", id))
+    }
+
+    /// 读取某个版本的内容
+    pub fn read(&self, id: u64) -> Result<String, WinswError> {
+        self.get(id)?;
+        std::fs::read_to_string(self.revision_path(id)).map_err(io_err)
+    }
+
+    /// 保存配置文件当前内容；与最新版本相同时返回最新版本而不重复保存
+    pub fn snapshot(&mut self, reason: SnapshotReason) -> Result<Revision, WinswError> {
+        let bytes = std::fs::read(&self.config).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                WinswError::ConfigNotFound(self.config.display().to_string())
+            } else {
+                WinswError::ConfigRead(e.to_string())
+            }
+        })?;
+        let sha256 = sha256_hex(&bytes);
+        if let Some(last) = self.index.revisions.last() {
+            if last.sha256 == sha256 {
+                return Ok(last.clone());
+            }
+        }
+
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let id = self
+            .index
+            .revisions
+            .last()
+            .map_or(now_ms, |last| now_ms.max(last.id + 1));
+        let revision = Revision {
+            id,
+            created_at: now_secs(),
+            reason,
+            sha256,
+            size: bytes.len() as u64,
+        };
+
+        std::fs::create_dir_all(&self.dir).map_err(io_err)?;
+        std::fs::write(self.revision_path(id), &bytes).map_err(io_err)?;
+        self.index.revisions.push(revision.clone());
+        self.save()?;
+        Ok(revision)
+    }
+
+    fn save(&self) -> Result<(), WinswError> {
+        let text = serde_json::to_string_pretty(&self.index)
+            .map_err(|e| WinswError::CatalogParse(e.to_string()))?;
+        let tmp = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&tmp, text).map_err(io_err)?;
+        std::fs::rename(&tmp, self.dir.join(INDEX_FILE)).map_err(io_err)
+    }
+}
+
+fn history_root(app: &AppHandle) -> Result<PathBuf, WinswError> {
+    Ok(data_dir(app)?.join(HISTORY_DIR))
+}
+
+/// 为配置文件保存快照
+pub(crate) fn snapshot(
+    app: &AppHandle,
+    config: &Path,
+    reason: SnapshotReason,
+) -> Result<Revision, WinswError> {
+    let _guard = HISTORY_LOCK.lock().expect("history lock");
+    ConfigHistory::open(&history_root(app)?, config)?.snapshot(reason)
+}
+
+/// 写入配置文件并记录历史：写入前保存原内容，写入后保存新内容
+pub(crate) fn write_config(
+    app: &AppHandle,
+    config: &Path,
+    content: &str,
+) -> Result<Revision, WinswError> {
+    let _guard = HISTORY_LOCK.lock().expect("history lock");
+    write_with_history(&history_root(app)?, config, content)
+}
+
+fn write_with_history(root: &Path, config: &Path, content: &str) -> Result<Revision, WinswError> {
+    // 先创建所在目录，使历史目录名与文件创建之后一致
+    if let Some(parent) = config.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    let mut history = ConfigHistory::open(root, config)?;
+    if config.exists() {
+        history.snapshot(SnapshotReason::Baseline)?;
+    }
+    std::fs::write(config, content).map_err(io_err)?;
+    history.snapshot(SnapshotReason::Write)
+}
+
+/// 保存快照，失败时仅记录日志（不影响调用方的主要操作）
+pub(crate) fn record(app: &AppHandle, config: &Path, reason: SnapshotReason) {
+    if let Err(e) = snapshot(app, config, reason) {
+        log::warn!("保存配置历史失败 ({}): {}", config.display(), e);
+    }
+}
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// 一处元素级变更
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct XmlChange {
+    /// 如 `service/onfailure[2]/@delay`、`service/env[@name=JAVA_HOME]`
+    pub path: String,
+    pub kind: ChangeKind,
+    /// 属性值、文本或元素的 XML
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// 子元素的匹配键：有 name / id 属性时按属性匹配，同名元素有多个时按序号匹配
+fn child_keys(parent: &XmlElement, multi: &BTreeMap<&str, bool>) -> Vec<(String, usize)> {
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    parent
+        .children
+        .iter()
+        .enumerate()
+        .filter_map(|(i, n)| match n {
+            XmlNode::Element(e) => Some((i, e)),
+            _ => None,
+        })
+        .map(|(i, e)| {
+            let n = seen.entry(e.name.as_str()).or_default();
+            *n += 1;
+            let key = match e.attr("name").or_else(|| e.attr("id")) {
+                Some(v) => {
+                    let attr = if e.attr("name").is_some() {
+                        "name"
+                    } else {
+                        "id"
+                    };
+                    format!("{}[@{}={}]", e.name, attr, v)
+                }
+                None if multi.get(e.name.as_str()) == Some(&true) => {
+                    format!("{}[{}]", e.name, n)
+                }
+                None => e.name.clone(),
+            };
+            (key, i)
+        })
+        .collect()
+}
+
+fn element_at(parent: &XmlElement, index: usize) -> &XmlElement {
+    match &parent.children[index] {
+        XmlNode::Element(e) => e,
+        _ => unreachable!("child_keys 只返回元素"),
+    }
+}
+
+fn diff_into(path: &str, old: &XmlElement, new: &XmlElement, out: &mut Vec<XmlChange>) {
+    // 属性
+    for (k, v) in &old.attrs {
+        let attr_path = format!("{}/@{}", path, k);
+        match new.attr(k) {
+            None => out.push(XmlChange {
+                path: attr_path,
+                kind: ChangeKind::Removed,
+                before: Some(v.clone()),
+                after: None,
+            }),
+            Some(nv) if nv != v => out.push(XmlChange {
+                path: attr_path,
+                kind: ChangeKind::Changed,
+                before: Some(v.clone()),
+                after: Some(nv.to_string()),
+            }),
+            _ => {}
+        }
+    }
+    for (k, v) in &new.attrs {
+        if old.attr(k).is_none() {
+            out.push(XmlChange {
+                path: format!("{}/@{}", path, k),
+                kind: ChangeKind::Added,
+                before: None,
+                after: Some(v.clone()),
+            });
+        }
+    }
+
+    // 叶子元素比较文本
+    if old.elements().next().is_none() && new.elements().next().is_none() {
+        let (a, b) = (old.text(), new.text());
+        if a != b {
+            out.push(XmlChange {
+                path: path.to_string(),
+                kind: ChangeKind::Changed,
+                before: Some(a),
+                after: Some(b),
+            });
+        }
+        return;
+    }
+
+    // 任一侧出现多个同名元素时，两侧都按序号匹配
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for e in old.elements() {
+        counts.entry(e.name.as_str()).or_default().0 += 1;
+    }
+    for e in new.elements() {
+        counts.entry(e.name.as_str()).or_default().1 += 1;
+    }
+    let multi: BTreeMap<&str, bool> = counts
+        .iter()
+        .map(|(k, (a, b))| (*k, *a > 1 || *b > 1))
+        .collect();
+
+    let old_keys = child_keys(old, &multi);
+    let new_keys = child_keys(new, &multi);
+    for (key, i) in &old_keys {
+        let child_path = format!("{}/{}", path, key);
+        let before = element_at(old, *i);
+        match new_keys.iter().find(|(k, _)| k == key) {
+            Some((_, j)) => diff_into(&child_path, before, element_at(new, *j), out),
+            None => out.push(XmlChange {
+                path: child_path,
+                kind: ChangeKind::Removed,
+                before: Some(before.to_xml()),
+                after: None,
+            }),
+        }
+    }
+    for (key, j) in &new_keys {
+        if !old_keys.iter().any(|(k, _)| k == key) {
+            out.push(XmlChange {
+                path: format!("{}/{}", path, key),
+                kind: ChangeKind::Added,
+                before: None,
+                after: Some(element_at(new, *j).to_xml()),
+            });
+        }
+    }
+}
+
+/// 按元素对比两个文档（忽略注释与缩进）
+pub fn diff_documents(old: &XmlElement, new: &XmlElement) -> Vec<XmlChange> {
+    let mut out = Vec::new();
+    if old.name != new.name {
+        out.push(XmlChange {
+            path: String::new(),
+            kind: ChangeKind::Changed,
+            before: Some(old.to_xml()),
+            after: Some(new.to_xml()),
+        });
+        return out;
+    }
+    diff_into(&old.name, old, new, &mut out);
+    out
+}
+
+/// 历史查询参数
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryReq {
+    /// 服务目录中的服务 ID
+    pub service_id: Option<String>,
+    /// 配置文件路径（未提供 service_id 时使用）
+    pub config: Option<String>,
+    /// 回滚后执行 refresh 时使用的 WinSW 路径（未提供 service_id 时使用）
+    pub winsw_path: Option<String>,
+}
+
+/// 两个版本的对比结果
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryDiff {
+    pub from: u64,
+    /// 为 None 时表示与配置文件当前内容对比
+    pub to: Option<u64>,
+    pub changes: Vec<XmlChange>,
+}
+
+/// 回滚结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackResp {
+    /// 回滚后记录的新版本
+    pub revision: Revision,
+    /// 执行 refresh 时的结果
+    pub refresh: Option<ActionResp>,
+}
+
+fn open_history(app: &AppHandle, req: &HistoryReq) -> Result<ConfigHistory, WinswError> {
+    let config = resolve_config(app, req.service_id.as_deref(), req.config.as_deref())?;
+    ConfigHistory::open(&history_root(app)?, &config)
+}
+
+fn diff(
+    app: &AppHandle,
+    req: &HistoryReq,
+    from: u64,
+    to: Option<u64>,
+) -> Result<HistoryDiff, WinswError> {
+    let history = open_history(app, req)?;
+    let old = parse_document(&history.read(from)?)?;
+    let new_text = match to {
+        Some(id) => history.read(id)?,
+        None => std::fs::read_to_string(&history.config)
+            .map_err(|e| WinswError::ConfigRead(e.to_string()))?,
+    };
+    let new = parse_document(&new_text)?;
+    Ok(HistoryDiff {
+        from,
+        to,
+        changes: diff_documents(&old, &new),
+    })
+}
+
+/// Tauri 命令：列出配置文件的历史版本
+#[tauri::command]
+pub async fn winsw_history_list(app: AppHandle, req: HistoryReq) -> Result<Vec<Revision>, String> {
+    let _guard = HISTORY_LOCK.lock().expect("history lock");
+    open_history(&app, &req)
+        .map(|h| h.revisions().to_vec())
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：按元素对比两个版本；`to` 为空时与当前文件对比
+#[tauri::command]
+pub async fn winsw_history_diff(
+    app: AppHandle,
+    req: HistoryReq,
+    from: u64,
+    to: Option<u64>,
+) -> Result<HistoryDiff, String> {
+    let _guard = HISTORY_LOCK.lock().expect("history lock");
+    diff(&app, &req, from, to).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：将配置文件回滚到指定版本，可选执行 refresh 使其生效
+///
+/// 写回前会先为当前内容保存快照，回滚本身也可以再回滚。
+#[tauri::command]
+pub async fn winsw_history_rollback(
+    app: AppHandle,
+    req: HistoryReq,
+    revision: u64,
+    refresh: Option<bool>,
+) -> Result<RollbackResp, String> {
+    let action_req = ActionReq {
+        service_id: req.service_id.clone(),
+        winsw_path: req.winsw_path.clone(),
+        config: req.config.clone(),
+        ..Default::default()
+    };
+    let (target, timeout_secs) =
+        resolve_target(&app, Some(&action_req)).map_err(|e| e.to_string())?;
+
+    let revision = {
+        let _guard = HISTORY_LOCK.lock().expect("history lock");
+        let restore = || -> Result<Revision, WinswError> {
+            let mut history = open_history(&app, &req)?;
+            let content = history.read(revision)?;
+            // 当前内容可能是手工修改的，先保存
+            if history.config.exists() {
+                history.snapshot(SnapshotReason::Baseline)?;
+            }
+            std::fs::write(&history.config, content).map_err(io_err)?;
+            history.snapshot(SnapshotReason::Rollback)
+        };
+        restore().map_err(|e| e.to_string())?
+    };
+
+    let refresh = if refresh.unwrap_or(false) {
+        Some(refresh_service(&app, &target, timeout_secs).await)
+    } else {
+        None
+    };
+
+    Ok(RollbackResp { revision, refresh })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_read() {
+        let dir = std::env::temp_dir().join(format!("winsw-history-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("my app.xml");
+        let root = dir.join("history");
+
+        std::fs::write(&config, "<service><id>a</id></service>").unwrap();
+        let mut history = ConfigHistory::open(&root, &config).unwrap();
+        let first = history.snapshot(SnapshotReason::Write).unwrap();
+        // 内容未变，不重复保存
+        let same = history.snapshot(SnapshotReason::Refresh).unwrap();
+        assert_eq!(first, same);
+
+        std::fs::write(&config, "<service><id>b</id></service>").unwrap();
+        let second = history.snapshot(SnapshotReason::Refresh).unwrap();
+        assert!(second.id > first.id);
+        assert_eq!(second.reason, SnapshotReason::Refresh);
+
+        // 重新打开后可读取各版本
+        let reopened = ConfigHistory::open(&root, &config).unwrap();
+        assert_eq!(reopened.revisions().len(), 2);
+        assert_eq!(
+            reopened.read(first.id).unwrap(),
+            "<service><id>a</id></service>"
+        );
+        assert!(matches!(
+            reopened.read(42),
+            Err(WinswError::RevisionNotFound(42))
+        ));
+        assert!(history_key(&config).starts_with("my_app-"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_new_config() {
+        let dir = std::env::temp_dir().join(format!("winsw-history-new-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = dir.join("services").join("app.xml");
+        let root = dir.join("history");
+
+        let first = write_with_history(&root, &config, "<service><id>a</id></service>").unwrap();
+        assert_eq!(first.reason, SnapshotReason::Write);
+        write_with_history(&root, &config, "<service><id>b</id></service>").unwrap();
+
+        // 文件创建前后使用同一历史目录
+        let history = ConfigHistory::open(&root, &config).unwrap();
+        let reasons: Vec<_> = history.revisions().iter().map(|r| r.reason).collect();
+        // 写入前的内容与上一版本相同，不单独保存基线
+        assert_eq!(reasons, vec![SnapshotReason::Write, SnapshotReason::Write]);
+        assert_eq!(
+            history.read(first.id).unwrap(),
+            "<service><id>a</id></service>"
+        );
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diff_documents() {
+        let old = parse_document(
+            r#"<service>
+  <id>app</id>
+  <env name="A" value="1"/>
+  <env name="B" value="2"/>
+  <onfailure action="restart" delay="10 sec"/>
+  <depend>db</depend>
+</service>"#,
+        )
+        .unwrap();
+        let new = parse_document(
+            r#"<service>
+  <!-- 注释不参与对比 -->
+  <id>app</id>
+  <env name="A" value="9"/>
+  <onfailure action="restart" delay="20 sec"/>
+  <onfailure action="none"/>
+  <depend>db</depend>
+  <startmode>Manual</startmode>
+</service>"#,
+        )
+        .unwrap();
+
+        let changes = diff_documents(&old, &new);
+        let find = |path: &str| changes.iter().find(|c| c.path == path);
+
+        let a = find("service/env[@name=A]/@value").unwrap();
+        assert_eq!(a.kind, ChangeKind::Changed);
+        assert_eq!(
+            (a.before.as_deref(), a.after.as_deref()),
+            (Some("1"), Some("9"))
+        );
+        assert_eq!(
+            find("service/env[@name=B]").unwrap().kind,
+            ChangeKind::Removed
+        );
+        assert_eq!(
+            find("service/onfailure[1]/@delay")
+                .unwrap()
+                .after
+                .as_deref(),
+            Some("20 sec")
+        );
+        assert_eq!(
+            find("service/onfailure[2]").unwrap().kind,
+            ChangeKind::Added
+        );
+        assert_eq!(
+            find("service/startmode").unwrap().after.as_deref(),
+            Some("<startmode>Manual</startmode>")
+        );
+        assert!(find("service/id").is_none());
+        assert_eq!(changes.len(), 5, "{:?}", changes);
+    }
+}
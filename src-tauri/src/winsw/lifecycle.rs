@@ -0,0 +1,540 @@
+//! 目录中服务的生命周期状态机
+//!
+//! 状态来源有两个：本应用执行的操作（执行前进入过渡状态，执行后按结果落定），
+//! 以及对 `status` 的查询（后台定期轮询与任何显式的 status 操作）。
+//! 每次状态变化都以 [`LIFECYCLE_EVENT`] 事件推送给前端，前端无需再轮询 `winsw_action("status")`。
+//!
+//! 后台轮询可通过 [`PollConfig`] 调整间隔或关闭；轮询跳过正在执行操作的服务，
+//! 以及看门狗正在巡检的服务（看门狗的状态查询同样会更新状态机）。
+
+use super::catalog::{load_catalog, now_secs};
+use super::{
+    data_dir, parse_status, perform_action, watchdog, ActionResp, ActionTarget, ServiceStatus,
+    WinswError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+use tokio::time::{sleep, Duration};
+
+/// 状态变化事件名
+pub const LIFECYCLE_EVENT: &str = "winsw://lifecycle";
+
+const CONFIG_FILE: &str = "lifecycle.json";
+
+/// 后台轮询配置，保存在 `<app_data_dir>/winsw/lifecycle.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollConfig {
+    /// 为 false 时不轮询，状态只来自本应用执行的操作与看门狗的巡检
+    pub enabled: bool,
+    /// 轮询间隔（秒）
+    pub interval_seconds: u64,
+    /// 每次状态查询的超时时间（秒）
+    pub timeout_seconds: u64,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 15,
+            timeout_seconds: 30,
+        }
+    }
+}
+
+/// 服务生命周期状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleState {
+    /// 尚未观察到
+    #[default]
+    Unknown,
+    Installing,
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+    /// 最近一次操作失败
+    Failed,
+    Uninstalled,
+}
+
+/// 状态变化的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransitionCause {
+    /// 本应用执行的操作（开始或成功完成）
+    Action { action: String },
+    /// 本应用执行的操作失败
+    ActionFailed { action: String, error: String },
+    /// `status` 查询的结果
+    Observed,
+}
+
+/// 状态变化事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LifecycleEvent {
+    pub service_id: String,
+    pub previous: LifecycleState,
+    pub state: LifecycleState,
+    pub cause: TransitionCause,
+    pub timestamp: u64,
+}
+
+/// 单个服务的当前状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceLifecycle {
+    pub service_id: String,
+    pub state: LifecycleState,
+    /// 进入当前状态的时间（从未观察到时为 None）
+    pub since: Option<u64>,
+    /// 进入当前状态的原因
+    pub cause: Option<TransitionCause>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    state: LifecycleState,
+    since: u64,
+    cause: TransitionCause,
+}
+
+/// 所有服务的状态
+#[derive(Debug, Default)]
+struct Machine {
+    services: HashMap<String, Entry>,
+    /// 正在执行的操作数（不含 status）
+    in_flight: HashMap<String, usize>,
+}
+
+impl Machine {
+    fn state(&self, id: &str) -> LifecycleState {
+        self.services.get(id).map(|e| e.state).unwrap_or_default()
+    }
+
+    /// 设置状态；状态未变化时返回 None
+    fn apply(
+        &mut self,
+        id: &str,
+        state: LifecycleState,
+        cause: TransitionCause,
+        now: u64,
+    ) -> Option<LifecycleEvent> {
+        let previous = self.state(id);
+        if previous == state {
+            return None;
+        }
+        self.services.insert(
+            id.to_string(),
+            Entry {
+                state,
+                since: now,
+                cause: cause.clone(),
+            },
+        );
+        Some(LifecycleEvent {
+            service_id: id.to_string(),
+            previous,
+            state,
+            cause,
+            timestamp: now,
+        })
+    }
+
+    fn enter(&mut self, id: &str) {
+        *self.in_flight.entry(id.to_string()).or_default() += 1;
+    }
+
+    fn leave(&mut self, id: &str) {
+        if let Some(n) = self.in_flight.get_mut(id) {
+            *n -= 1;
+            if *n == 0 {
+                self.in_flight.remove(id);
+            }
+        }
+    }
+
+    fn busy(&self, id: &str) -> bool {
+        self.in_flight.contains_key(id)
+    }
+}
+
+/// 操作执行期间持有，释放前后台轮询跳过该服务
+pub(crate) struct InFlight(String);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        machine().lock().expect("lifecycle lock").leave(&self.0);
+    }
+}
+
+fn machine() -> &'static Mutex<Machine> {
+    static MACHINE: OnceLock<Mutex<Machine>> = OnceLock::new();
+    MACHINE.get_or_init(|| Mutex::new(Machine::default()))
+}
+
+/// 操作执行期间的过渡状态
+fn transitional_state(action: &str) -> Option<LifecycleState> {
+    match action {
+        "install" => Some(LifecycleState::Installing),
+        "start" | "restart" | "restart!" => Some(LifecycleState::Starting),
+        "stop" | "uninstall" => Some(LifecycleState::Stopping),
+        _ => None,
+    }
+}
+
+/// 操作成功后的状态
+fn settled_state(action: &str, stdout: &str) -> Option<LifecycleState> {
+    match action {
+        "install" | "stop" => Some(LifecycleState::Stopped),
+        "uninstall" => Some(LifecycleState::Uninstalled),
+        "start" | "restart" | "restart!" => Some(LifecycleState::Running),
+        "status" => observed_state(parse_status(stdout)),
+        _ => None,
+    }
+}
+
+/// `status` 结果对应的状态；过渡中或无法识别时不改变状态
+fn observed_state(status: ServiceStatus) -> Option<LifecycleState> {
+    match status {
+        ServiceStatus::NonExistent => Some(LifecycleState::Uninstalled),
+        ServiceStatus::Stopped => Some(LifecycleState::Stopped),
+        ServiceStatus::Running => Some(LifecycleState::Running),
+        ServiceStatus::Pending | ServiceStatus::Unknown => None,
+    }
+}
+
+fn publish(app: &AppHandle, event: Option<LifecycleEvent>) {
+    if let Some(event) = event {
+        log::debug!(
+            "服务 {} 状态 {:?} -> {:?}",
+            event.service_id,
+            event.previous,
+            event.state
+        );
+        let _ = app.emit(LIFECYCLE_EVENT, event);
+    }
+}
+
+fn transition(app: &AppHandle, id: &str, state: LifecycleState, cause: TransitionCause) {
+    let event = machine()
+        .lock()
+        .expect("lifecycle lock")
+        .apply(id, state, cause, now_secs());
+    publish(app, event);
+}
+
+/// 操作开始前调用（由 [`perform_action`] 调用）；返回值需持有到操作结束
+pub(crate) fn begin_action(app: &AppHandle, service_id: &str, action: &str) -> Option<InFlight> {
+    if let Some(state) = transitional_state(action) {
+        let cause = TransitionCause::Action {
+            action: action.to_string(),
+        };
+        transition(app, service_id, state, cause);
+    }
+    if action == "status" {
+        return None;
+    }
+    machine().lock().expect("lifecycle lock").enter(service_id);
+    Some(InFlight(service_id.to_string()))
+}
+
+/// 服务是否有正在执行的操作（status 除外）
+pub(crate) fn is_busy(service_id: &str) -> bool {
+    machine().lock().expect("lifecycle lock").busy(service_id)
+}
+
+/// 操作结束后调用（由 [`perform_action`] 与扩展命令调用）
+pub(crate) fn finish_action(
+    app: &AppHandle,
+    service_id: &str,
+    action: &str,
+    result: Result<&ActionResp, &WinswError>,
+) {
+    let error = match result {
+        Ok(resp) if resp.exec.ok => {
+            let stdout = resp.exec.stdout.as_deref().unwrap_or("");
+            if let Some(state) = settled_state(action, stdout) {
+                let cause = if action == "status" {
+                    TransitionCause::Observed
+                } else {
+                    TransitionCause::Action {
+                        action: action.to_string(),
+                    }
+                };
+                transition(app, service_id, state, cause);
+            }
+            return;
+        }
+        Ok(resp) => resp
+            .exec
+            .error
+            .clone()
+            .or_else(|| resp.exec.stderr.clone())
+            .unwrap_or_else(|| format!("退出码 {}", resp.exec.code)),
+        Err(e) => e.to_string(),
+    };
+    // 查询失败不代表服务故障
+    if transitional_state(action).is_some() {
+        let cause = TransitionCause::ActionFailed {
+            action: action.to_string(),
+            error,
+        };
+        transition(app, service_id, LifecycleState::Failed, cause);
+    }
+}
+
+/// 轮询一次目录中的服务
+async fn poll(app: &AppHandle, config: &PollConfig) {
+    let catalog = match load_catalog(app) {
+        Ok(c) => c,
+        Err(e) => {
+            log::debug!("读取服务目录失败: {}", e);
+            return;
+        }
+    };
+    let targets: Vec<ActionTarget> = catalog
+        .list()
+        .iter()
+        .filter_map(|entry| {
+            ActionTarget::from_entry(app, entry)
+                .map_err(|e| log::debug!("服务 {} 无法轮询: {}", entry.id, e))
+                .ok()
+        })
+        .collect();
+
+    machine()
+        .lock()
+        .expect("lifecycle lock")
+        .services
+        .retain(|id, _| catalog.get(id).is_some());
+
+    for target in targets {
+        let Some(id) = target.service_id.as_deref() else {
+            continue;
+        };
+        // 操作进行中的状态由操作结果落定；看门狗巡检的服务无需重复查询
+        if is_busy(id) || watchdog::is_watching(id) {
+            continue;
+        }
+        // 结果经由 finish_action 进入状态机
+        if let Err(e) = perform_action(app, "status", &target, config.timeout_seconds, None).await {
+            log::debug!("轮询服务状态失败: {}", e);
+        }
+    }
+}
+
+static LOOP: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+
+fn io_err(e: impl std::fmt::Display) -> WinswError {
+    WinswError::FileIo(e.to_string())
+}
+
+/// 读取轮询配置，文件不存在时使用默认值
+fn load_config(app: &AppHandle) -> Result<PollConfig, WinswError> {
+    let path = data_dir(app)?.join(CONFIG_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(io_err),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PollConfig::default()),
+        Err(e) => Err(io_err(e)),
+    }
+}
+
+fn save_config(app: &AppHandle, config: &PollConfig) -> Result<(), WinswError> {
+    let dir = data_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(io_err)?;
+    let text = serde_json::to_string_pretty(config).map_err(io_err)?;
+    let tmp = dir.join(format!("{}.tmp", CONFIG_FILE));
+    std::fs::write(&tmp, text).map_err(io_err)?;
+    std::fs::rename(&tmp, dir.join(CONFIG_FILE)).map_err(io_err)
+}
+
+fn validate_config(config: &PollConfig) -> Result<(), WinswError> {
+    if config.interval_seconds == 0 || config.timeout_seconds == 0 {
+        return Err(WinswError::InvalidOption(
+            "interval_seconds 与 timeout_seconds 必须大于 0".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn stop_loop() {
+    if let Some(tx) = LOOP.lock().expect("lifecycle loop lock").take() {
+        let _ = tx.send(());
+    }
+}
+
+/// 按配置（重新）启动后台轮询；关闭时只停止
+fn start_loop(app: &AppHandle, config: PollConfig) {
+    stop_loop();
+    if !config.enabled {
+        return;
+    }
+    let (tx, mut rx) = oneshot::channel();
+    *LOOP.lock().expect("lifecycle loop lock") = Some(tx);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            poll(&app, &config).await;
+            tokio::select! {
+                _ = &mut rx => break,
+                _ = sleep(Duration::from_secs(config.interval_seconds)) => {}
+            }
+        }
+    });
+}
+
+/// 应用启动时按保存的配置开始后台轮询
+pub fn init(app: &AppHandle) {
+    let config = load_config(app).unwrap_or_else(|e| {
+        log::warn!("读取生命周期轮询配置失败，使用默认配置: {}", e);
+        PollConfig::default()
+    });
+    start_loop(app, config);
+}
+
+/// Tauri 命令：读取后台轮询配置
+#[tauri::command]
+pub async fn winsw_lifecycle_poll_get(app: AppHandle) -> Result<PollConfig, String> {
+    load_config(&app).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：保存后台轮询配置并立即生效
+///
+/// ```javascript
+/// await invoke('winsw_lifecycle_poll_set', {
+///   config: { enabled: true, interval_seconds: 60, timeout_seconds: 30 }
+/// });
+/// ```
+#[tauri::command]
+pub async fn winsw_lifecycle_poll_set(
+    app: AppHandle,
+    config: PollConfig,
+) -> Result<PollConfig, String> {
+    validate_config(&config).map_err(|e| e.to_string())?;
+    save_config(&app, &config).map_err(|e| e.to_string())?;
+    start_loop(&app, config.clone());
+    Ok(config)
+}
+
+/// Tauri 命令：目录中每个服务的当前生命周期状态
+///
+/// ```javascript
+/// const services = await invoke('winsw_lifecycle_snapshot');
+/// await listen('winsw://lifecycle', ({ payload }) => {
+///   // payload: { service_id, previous, state, cause: { kind, action?, error? }, timestamp }
+/// });
+/// ```
+#[tauri::command]
+pub async fn winsw_lifecycle_snapshot(app: AppHandle) -> Result<Vec<ServiceLifecycle>, String> {
+    let catalog = load_catalog(&app).map_err(|e| e.to_string())?;
+    let machine = machine().lock().expect("lifecycle lock");
+    Ok(catalog
+        .list()
+        .iter()
+        .map(|entry| match machine.services.get(&entry.id) {
+            Some(e) => ServiceLifecycle {
+                service_id: entry.id.clone(),
+                state: e.state,
+                since: Some(e.since),
+                cause: Some(e.cause.clone()),
+            },
+            None => ServiceLifecycle {
+                service_id: entry.id.clone(),
+                state: LifecycleState::Unknown,
+                since: None,
+                cause: None,
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_states() {
+        assert_eq!(
+            transitional_state("install"),
+            Some(LifecycleState::Installing)
+        );
+        assert_eq!(
+            transitional_state("restart!"),
+            Some(LifecycleState::Starting)
+        );
+        assert_eq!(transitional_state("status"), None);
+        assert_eq!(
+            settled_state("uninstall", ""),
+            Some(LifecycleState::Uninstalled)
+        );
+        assert_eq!(
+            settled_state("status", "Active (running)"),
+            Some(LifecycleState::Running)
+        );
+        assert_eq!(settled_state("status", "Pending"), None);
+        assert_eq!(settled_state("refresh", ""), None);
+    }
+
+    #[test]
+    fn test_machine_transitions() {
+        let mut m = Machine::default();
+        let start = TransitionCause::Action {
+            action: "start".into(),
+        };
+
+        let event = m
+            .apply("api", LifecycleState::Starting, start.clone(), 10)
+            .unwrap();
+        assert_eq!(event.previous, LifecycleState::Unknown);
+        assert_eq!(event.state, LifecycleState::Starting);
+        assert_eq!(event.timestamp, 10);
+
+        let event = m.apply("api", LifecycleState::Running, start, 12).unwrap();
+        assert_eq!(event.previous, LifecycleState::Starting);
+
+        // 状态未变化不产生事件，也不更新进入时间
+        assert!(m
+            .apply(
+                "api",
+                LifecycleState::Running,
+                TransitionCause::Observed,
+                20
+            )
+            .is_none());
+        assert_eq!(m.services["api"].since, 12);
+        assert_eq!(m.state("other"), LifecycleState::Unknown);
+    }
+
+    #[test]
+    fn test_in_flight_counts() {
+        let mut m = Machine::default();
+        m.enter("api");
+        m.enter("api");
+        m.leave("api");
+        assert!(m.busy("api"));
+        m.leave("api");
+        assert!(!m.busy("api"));
+        // 多余的 leave 不会出错
+        m.leave("api");
+        assert!(!m.busy("worker"));
+    }
+
+    #[test]
+    fn test_poll_config() {
+        let config: PollConfig = serde_json::from_str(r#"{"enabled":false}"#).unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.interval_seconds, 15);
+        assert!(validate_config(&config).is_ok());
+        let config = PollConfig {
+            interval_seconds: 0,
+            ..PollConfig::default()
+        };
+        assert!(validate_config(&config).is_err());
+    }
+}
@@ -0,0 +1,574 @@
+//! WinSW 服务日志的定位、读取与跟踪
+//!
+//! WinSW 在日志目录中写入 `<base>.out.log`、`<base>.err.log` 与 `<base>.wrapper.log`，
+//! 滚动模式下还会生成 `<base>.1.out.log`（roll-by-size）或 `<base>.20240101.out.log`
+//! （roll-by-time）等文件。这里总是选取同类文件中最新的一个作为“当前”日志，
+//! 跟踪过程中发生滚动时自动切换到新文件。
+
+use super::catalog::resolve_config;
+use super::config::{LogMode, ServiceConfig};
+use super::WinswError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_TAIL_LINES: usize = 200;
+const FOLLOW_INTERVAL_MS: u64 = 500;
+const READ_BLOCK: u64 = 64 * 1024;
+
+/// 日志类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Out,
+    Err,
+    Wrapper,
+}
+
+impl LogStream {
+    pub const ALL: [LogStream; 3] = [LogStream::Out, LogStream::Err, LogStream::Wrapper];
+
+    fn suffix(self) -> &'static str {
+        match self {
+            LogStream::Out => ".out.log",
+            LogStream::Err => ".err.log",
+            LogStream::Wrapper => ".wrapper.log",
+        }
+    }
+}
+
+/// 解析后的日志位置
+#[derive(Debug, Clone, Serialize)]
+pub struct LogFiles {
+    pub dir: PathBuf,
+    pub mode: LogMode,
+    /// 日志文件名前缀
+    pub base_names: Vec<String>,
+    pub out: Option<PathBuf>,
+    pub err: Option<PathBuf>,
+    pub wrapper: Option<PathBuf>,
+}
+
+/// 某类日志的末尾若干行
+#[derive(Debug, Clone, Serialize)]
+pub struct LogTail {
+    pub stream: LogStream,
+    pub path: Option<PathBuf>,
+    pub lines: Vec<String>,
+}
+
+/// 跟踪过程中推送给前端的日志行
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub path: PathBuf,
+    pub line: String,
+}
+
+/// 日志请求参数
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogReq {
+    /// 服务目录中的服务 ID
+    pub service_id: Option<String>,
+    /// 配置文件路径（未提供 service_id 时使用）
+    pub config: Option<String>,
+    /// 需要读取的日志类型，默认全部
+    pub streams: Option<Vec<LogStream>>,
+    /// 读取末尾的行数，默认为 200
+    pub lines: Option<usize>,
+}
+
+/// 日志定位器：根据配置确定各类日志的当前文件
+#[derive(Debug, Clone)]
+pub struct LogLocator {
+    dir: PathBuf,
+    mode: LogMode,
+    base_names: Vec<String>,
+}
+
+impl LogLocator {
+    pub fn new(cfg: &ServiceConfig) -> Self {
+        // WinSW 以配置文件名作为日志前缀；常见做法是配置文件与服务 ID 同名，两者都尝试
+        let mut base_names = vec![cfg.base_name.clone()];
+        if !base_names.contains(&cfg.id) {
+            base_names.push(cfg.id.clone());
+        }
+        Self {
+            dir: cfg.log_dir(),
+            mode: cfg.log.mode,
+            base_names,
+        }
+    }
+
+    /// 文件名是否属于指定类型的日志
+    fn matches(&self, file_name: &str, stream: LogStream) -> bool {
+        let suffix = stream.suffix();
+        self.base_names.iter().any(|base| {
+            if file_name == format!("{}{}", base, suffix) {
+                return stream == LogStream::Wrapper || self.mode != LogMode::None;
+            }
+            if stream == LogStream::Wrapper {
+                return false;
+            }
+            // 中间部分：roll 为序号，roll-by-time 为时间戳
+            let middle = file_name
+                .strip_prefix(base.as_str())
+                .and_then(|r| r.strip_prefix('.'))
+                .and_then(|r| r.strip_suffix(suffix));
+            match (self.mode, middle) {
+                (_, None) => false,
+                (LogMode::Roll | LogMode::RollBySize, Some(m)) => {
+                    !m.is_empty() && m.chars().all(|c| c.is_ascii_digit())
+                }
+                (LogMode::RollByTime | LogMode::RollBySizeTime, Some(m)) => !m.is_empty(),
+                _ => false,
+            }
+        })
+    }
+
+    /// 当前日志文件：同类文件中修改时间最新者，时间相同时优先不带序号的文件名
+    pub fn current(&self, stream: LogStream) -> Option<PathBuf> {
+        let entries = std::fs::read_dir(&self.dir).ok()?;
+        let mut best: Option<(SystemTime, bool, PathBuf)> = None;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !self.matches(&name, stream) {
+                continue;
+            }
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let plain = self
+                .base_names
+                .iter()
+                .any(|b| name == format!("{}{}", b, stream.suffix()));
+            let candidate = (modified, plain, entry.path());
+            if best
+                .as_ref()
+                .map_or(true, |b| (candidate.0, candidate.1) > (b.0, b.1))
+            {
+                best = Some(candidate);
+            }
+        }
+        best.map(|(_, _, p)| p)
+    }
+
+    pub fn files(&self) -> LogFiles {
+        LogFiles {
+            dir: self.dir.clone(),
+            mode: self.mode,
+            base_names: self.base_names.clone(),
+            out: self.current(LogStream::Out),
+            err: self.current(LogStream::Err),
+            wrapper: self.current(LogStream::Wrapper),
+        }
+    }
+}
+
+fn split_lines(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|l| l.trim_end_matches('\r').to_string())
+        .collect()
+}
+
+/// 从文件末尾向前按块读取，返回最后 `n` 行
+pub fn read_last_lines(path: &Path, n: usize) -> std::io::Result<Vec<String>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut pos = len;
+    let mut buf: Vec<u8> = Vec::new();
+
+    while pos > 0 {
+        let start = pos.saturating_sub(READ_BLOCK);
+        let mut block = vec![0u8; (pos - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+        block.extend_from_slice(&buf);
+        buf = block;
+        pos = start;
+        // 多读一行，保证第一行完整
+        if buf.iter().filter(|&&b| b == b'\n').count() > n {
+            break;
+        }
+    }
+
+    let mut lines = split_lines(&buf);
+    if lines.len() > n {
+        lines.drain(..lines.len() - n);
+    }
+    Ok(lines)
+}
+
+/// 文件标识（卷与文件号）：同名文件被重建后标识不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileId(u64, u64);
+
+#[cfg(unix)]
+fn file_id(file: &File) -> std::io::Result<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = file.metadata()?;
+    Ok(FileId(meta.dev(), meta.ino()))
+}
+
+/// 不使用创建时间：NTFS 的文件名隧道会让按原名重建的文件继承旧文件的创建时间
+#[cfg(windows)]
+fn file_id(file: &File) -> std::io::Result<FileId> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+    };
+    // SAFETY: 句柄在 file 的生命周期内有效，info 为输出参数
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(FileId(
+        u64::from(info.dwVolumeSerialNumber),
+        (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow),
+    ))
+}
+
+/// 正在跟踪的文件；保持句柄打开，滚动时改名的旧文件仍可读完
+#[derive(Debug)]
+struct Tracked {
+    file: File,
+    id: FileId,
+    path: PathBuf,
+    offset: u64,
+}
+
+impl Tracked {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = File::open(&path)?;
+        let id = file_id(&file)?;
+        Ok(Self {
+            file,
+            id,
+            path,
+            offset: 0,
+        })
+    }
+
+    /// 读取上次位置之后的内容；文件被截断时从头读取
+    fn read_new(&mut self, partial: &mut Vec<u8>) -> std::io::Result<()> {
+        let len = self.file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            partial.clear();
+        }
+        if len == self.offset {
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let mut chunk = Vec::new();
+        (&mut self.file)
+            .take(len - self.offset)
+            .read_to_end(&mut chunk)?;
+        self.offset += chunk.len() as u64;
+        partial.extend_from_slice(&chunk);
+        Ok(())
+    }
+}
+
+/// 单类日志的增量读取器
+#[derive(Debug)]
+pub struct LogFollower {
+    stream: LogStream,
+    tracked: Option<Tracked>,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// 从当前文件的末尾开始跟踪
+    pub fn new(locator: &LogLocator, stream: LogStream) -> Self {
+        let tracked = locator
+            .current(stream)
+            .and_then(|p| Tracked::open(p).ok())
+            .and_then(|mut t| {
+                t.offset = t.file.metadata().ok()?.len();
+                Some(t)
+            });
+        Self {
+            stream,
+            tracked,
+            partial: Vec::new(),
+        }
+    }
+
+    /// 取出缓冲中的完整行；`flush` 为 true 时末尾不完整的行也一并取出
+    fn take_lines(&mut self, path: &Path, flush: bool, out: &mut Vec<LogLine>) {
+        let end = if flush {
+            self.partial.len()
+        } else {
+            match self.partial.iter().rposition(|&b| b == b'\n') {
+                Some(last_nl) => last_nl + 1,
+                None => return,
+            }
+        };
+        let complete: Vec<u8> = self.partial.drain(..end).collect();
+        out.extend(split_lines(&complete).into_iter().map(|line| LogLine {
+            stream: self.stream,
+            path: path.to_path_buf(),
+            line,
+        }));
+    }
+
+    /// 读取自上次调用以来新增的完整行
+    ///
+    /// 当前文件换成了另一个文件（滚动改名后按原名重建，或切换到新的滚动文件）时，
+    /// 先通过旧句柄读完旧文件的剩余内容，再从头读取新文件。
+    pub fn poll(&mut self, locator: &LogLocator) -> std::io::Result<Vec<LogLine>> {
+        let mut lines = Vec::new();
+        let current = match locator.current(self.stream).map(Tracked::open) {
+            Some(Ok(t)) => Some(t),
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Some(Err(e)) => return Err(e),
+            None => None,
+        };
+
+        match (&mut self.tracked, current) {
+            (Some(tracked), Some(current)) if tracked.id == current.id => {
+                tracked.path = current.path;
+            }
+            (_, None) => {}
+            (_, Some(current)) => {
+                if let Some(mut old) = self.tracked.take() {
+                    old.read_new(&mut self.partial)?;
+                    self.take_lines(&old.path, true, &mut lines);
+                }
+                self.partial.clear();
+                self.tracked = Some(current);
+            }
+        }
+
+        let Some(tracked) = self.tracked.as_mut() else {
+            return Ok(lines);
+        };
+        tracked.read_new(&mut self.partial)?;
+        let path = tracked.path.clone();
+        self.take_lines(&path, false, &mut lines);
+        Ok(lines)
+    }
+}
+
+static NEXT_SUBSCRIPTION: AtomicU64 = AtomicU64::new(1);
+
+fn subscriptions() -> &'static Mutex<HashMap<u64, oneshot::Sender<()>>> {
+    static SUBSCRIPTIONS: OnceLock<Mutex<HashMap<u64, oneshot::Sender<()>>>> = OnceLock::new();
+    SUBSCRIPTIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn load_locator(app: &AppHandle, req: &LogReq) -> Result<LogLocator, WinswError> {
+    let config = resolve_config(app, req.service_id.as_deref(), req.config.as_deref())?;
+    Ok(LogLocator::new(&ServiceConfig::load(config)?))
+}
+
+fn requested_streams(req: &LogReq) -> Vec<LogStream> {
+    req.streams
+        .clone()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| LogStream::ALL.to_vec())
+}
+
+/// Tauri 命令：解析服务的日志文件位置
+#[tauri::command]
+pub async fn winsw_logs_resolve(app: AppHandle, req: LogReq) -> Result<LogFiles, String> {
+    load_locator(&app, &req)
+        .map(|l| l.files())
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：读取日志末尾若干行
+#[tauri::command]
+pub async fn winsw_logs_tail(app: AppHandle, req: LogReq) -> Result<Vec<LogTail>, String> {
+    let locator = load_locator(&app, &req).map_err(|e| e.to_string())?;
+    let n = req.lines.unwrap_or(DEFAULT_TAIL_LINES);
+
+    requested_streams(&req)
+        .into_iter()
+        .map(|stream| {
+            let path = locator.current(stream);
+            let lines = match &path {
+                Some(p) => read_last_lines(p, n).map_err(|e| e.to_string())?,
+                None => Vec::new(),
+            };
+            Ok(LogTail {
+                stream,
+                path,
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Tauri 命令：跟踪日志新增内容，通过通道逐行推送，返回订阅 ID
+///
+/// 订阅在调用 `winsw_logs_unfollow` 或前端通道关闭后结束。
+#[tauri::command]
+pub async fn winsw_logs_follow(
+    app: AppHandle,
+    req: LogReq,
+    on_line: Channel<LogLine>,
+) -> Result<u64, String> {
+    let locator = load_locator(&app, &req).map_err(|e| e.to_string())?;
+    let mut followers: Vec<LogFollower> = requested_streams(&req)
+        .into_iter()
+        .map(|s| LogFollower::new(&locator, s))
+        .collect();
+
+    let id = NEXT_SUBSCRIPTION.fetch_add(1, Ordering::Relaxed);
+    let (cancel_tx, mut cancel_rx) = oneshot::channel();
+    subscriptions()
+        .lock()
+        .expect("subscriptions lock")
+        .insert(id, cancel_tx);
+
+    tauri::async_runtime::spawn(async move {
+        'follow: loop {
+            tokio::select! {
+                _ = &mut cancel_rx => break,
+                _ = sleep(Duration::from_millis(FOLLOW_INTERVAL_MS)) => {}
+            }
+            for follower in followers.iter_mut() {
+                let lines = match follower.poll(&locator) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        log::warn!("读取 WinSW 日志失败: {}", e);
+                        continue;
+                    }
+                };
+                for line in lines {
+                    if on_line.send(line).is_err() {
+                        break 'follow;
+                    }
+                }
+            }
+        }
+        subscriptions()
+            .lock()
+            .expect("subscriptions lock")
+            .remove(&id);
+    });
+
+    Ok(id)
+}
+
+/// Tauri 命令：取消日志跟踪，返回订阅是否存在
+#[tauri::command]
+pub async fn winsw_logs_unfollow(subscription_id: u64) -> Result<bool, String> {
+    let sender = subscriptions()
+        .lock()
+        .expect("subscriptions lock")
+        .remove(&subscription_id);
+    Ok(sender.map(|tx| tx.send(()).is_ok()).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winsw-logs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn locator(dir: &Path, log: &str) -> LogLocator {
+        let xml = format!("<service><id>svc</id>{}</service>", log);
+        LogLocator::new(&ServiceConfig::parse(&xml, &dir.join("svc.xml")).unwrap())
+    }
+
+    #[test]
+    fn test_matches_by_mode() {
+        let dir = Path::new("/srv");
+        let append = locator(dir, "");
+        assert!(append.matches("svc.out.log", LogStream::Out));
+        assert!(!append.matches("svc.1.out.log", LogStream::Out));
+        assert!(append.matches("svc.wrapper.log", LogStream::Wrapper));
+
+        let size = locator(dir, r#"<log mode="roll-by-size"/>"#);
+        assert!(size.matches("svc.3.err.log", LogStream::Err));
+        assert!(!size.matches("svc.x.err.log", LogStream::Err));
+
+        let time = locator(
+            dir,
+            r#"<log mode="roll-by-time"><pattern>yyyyMMdd</pattern></log>"#,
+        );
+        assert!(time.matches("svc.20240101.out.log", LogStream::Out));
+        assert!(!time.matches("other.20240101.out.log", LogStream::Out));
+
+        let none = locator(dir, r#"<log mode="none"/>"#);
+        assert!(!none.matches("svc.out.log", LogStream::Out));
+        assert!(none.matches("svc.wrapper.log", LogStream::Wrapper));
+    }
+
+    #[test]
+    fn test_read_last_lines() {
+        let dir = temp_dir("tail");
+        let path = dir.join("svc.out.log");
+        let content: String = (1..=1000).map(|i| format!("line {}\r\n", i)).collect();
+        std::fs::write(&path, content).unwrap();
+
+        let lines = read_last_lines(&path, 3).unwrap();
+        assert_eq!(lines, vec!["line 998", "line 999", "line 1000"]);
+        assert_eq!(read_last_lines(&path, 5000).unwrap().len(), 1000);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_follow_handles_rotation() {
+        let dir = temp_dir("follow");
+        let loc = locator(&dir, r#"<log mode="roll-by-size"/>"#);
+        let current = dir.join("svc.out.log");
+        std::fs::write(&current, "old\n").unwrap();
+        let read = |follower: &mut LogFollower| -> Vec<String> {
+            follower
+                .poll(&loc)
+                .unwrap()
+                .into_iter()
+                .map(|l| l.line)
+                .collect()
+        };
+
+        let mut follower = LogFollower::new(&loc, LogStream::Out);
+        assert!(follower.poll(&loc).unwrap().is_empty());
+
+        // 部分行在换行符到达前不会推送
+        std::fs::write(&current, "old\nnew 1\nnew").unwrap();
+        assert_eq!(read(&mut follower), vec!["new 1"]);
+
+        // 滚动前写入的剩余内容在两次轮询之间到达，随后旧文件改名、按原名重建
+        let mut f = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&current)
+            .unwrap();
+        std::io::Write::write_all(&mut f, b" 2\nlast").unwrap();
+        drop(f);
+        std::fs::rename(&current, dir.join("svc.1.out.log")).unwrap();
+        std::fs::write(&current, "fresh\n").unwrap();
+        // 旧文件的剩余内容（包括末尾不完整的行）不会丢失
+        assert_eq!(read(&mut follower), vec!["new 2", "last", "fresh"]);
+
+        // 按原名截断重写（比已读位置短）
+        std::fs::write(&current, "re\n").unwrap();
+        assert_eq!(read(&mut follower), vec!["re"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,398 @@
+//! 故障恢复策略与启动类型
+//!
+//! 读取和修改配置中的 `<onfailure>`、`<resetfailure>`、`<startmode>` 与 `<delayedAutoStart>`。
+//! 修改时只替换这几个元素，文档的其余部分（包括注释与缩进）保持不变。
+
+use super::catalog::resolve_config;
+use super::config::{XmlDocument, XmlElement};
+use super::history::{self, Revision};
+use super::{refresh_service, resolve_target, ActionReq, ActionResp, WinswError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+/// 故障时的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureAction {
+    Restart,
+    Reboot,
+    None,
+}
+
+impl FailureAction {
+    fn parse(s: &str) -> Result<Self, WinswError> {
+        match s.trim().to_lowercase().as_str() {
+            "restart" => Ok(Self::Restart),
+            "reboot" => Ok(Self::Reboot),
+            "none" => Ok(Self::None),
+            other => Err(WinswError::ConfigInvalid(format!(
+                "未知的 onfailure 操作: {}",
+                other
+            ))),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::Reboot => "reboot",
+            Self::None => "none",
+        }
+    }
+}
+
+/// 第 N 次故障时的处理（按 `<onfailure>` 出现顺序，最后一项用于之后的所有故障）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureStep {
+    pub action: FailureAction,
+    /// 执行前的等待时间，如 "10 sec"
+    pub delay: Option<String>,
+}
+
+/// 服务启动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    Automatic,
+    Manual,
+    Boot,
+    System,
+    Disabled,
+}
+
+impl StartMode {
+    fn parse(s: &str) -> Result<Self, WinswError> {
+        match s.trim().to_lowercase().as_str() {
+            "automatic" | "auto" => Ok(Self::Automatic),
+            "manual" | "demand" => Ok(Self::Manual),
+            "boot" => Ok(Self::Boot),
+            "system" => Ok(Self::System),
+            "disabled" => Ok(Self::Disabled),
+            other => Err(WinswError::ConfigInvalid(format!(
+                "未知的启动类型: {}",
+                other
+            ))),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Automatic => "Automatic",
+            Self::Manual => "Manual",
+            Self::Boot => "Boot",
+            Self::System => "System",
+            Self::Disabled => "Disabled",
+        }
+    }
+}
+
+/// 当前的策略设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServicePolicy {
+    pub on_failure: Vec<FailureStep>,
+    /// 故障计数清零的时间，如 "1 hour"
+    pub reset_failure: Option<String>,
+    /// 未设置时 WinSW 默认为 Automatic
+    pub start_mode: Option<StartMode>,
+    pub delayed_auto_start: bool,
+}
+
+/// 策略修改；未提供的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PolicyUpdate {
+    /// 替换整个故障处理链，空数组表示移除
+    pub on_failure: Option<Vec<FailureStep>>,
+    /// 空字符串表示移除
+    pub reset_failure: Option<String>,
+    pub start_mode: Option<StartMode>,
+    pub delayed_auto_start: Option<bool>,
+}
+
+/// 服务引用
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PolicyReq {
+    /// 服务目录中的服务 ID
+    pub service_id: Option<String>,
+    /// 配置文件路径（未提供 service_id 时使用）
+    pub config: Option<String>,
+    /// 执行 refresh 时使用的 WinSW 路径（未提供 service_id 时使用）
+    pub winsw_path: Option<String>,
+}
+
+/// 修改结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyResp {
+    pub policy: ServicePolicy,
+    /// 写入后记录的配置历史版本
+    pub revision: Revision,
+    pub refresh: Option<ActionResp>,
+}
+
+/// 解析 WinSW 时长（如 "10 sec"、"1.5 min"、"500 ms"），返回毫秒；不带单位时为毫秒
+pub fn parse_duration(text: &str) -> Result<u64, WinswError> {
+    let invalid = || WinswError::InvalidDuration(text.to_string());
+    let t = text.trim();
+    let split = t
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let value: f64 = num.parse().map_err(|_| invalid())?;
+    let factor: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "ms" => 1,
+        "sec" | "secs" => 1_000,
+        "min" | "mins" => 60_000,
+        "hr" | "hrs" | "hour" | "hours" => 3_600_000,
+        "day" | "days" => 86_400_000,
+        _ => return Err(invalid()),
+    };
+    Ok((value * factor as f64).round() as u64)
+}
+
+/// 从配置文档读取策略
+pub fn read_policy(root: &XmlElement) -> Result<ServicePolicy, WinswError> {
+    let on_failure = root
+        .children_named("onfailure")
+        .map(|e| {
+            Ok(FailureStep {
+                action: FailureAction::parse(e.attr("action").unwrap_or(""))?,
+                delay: e.attr("delay").map(str::to_string),
+            })
+        })
+        .collect::<Result<Vec<_>, WinswError>>()?;
+
+    // v2 只判断元素是否存在，v3 读取布尔值
+    let delayed_auto_start = root
+        .child("delayedAutoStart")
+        .is_some_and(|e| !e.text().eq_ignore_ascii_case("false"));
+
+    Ok(ServicePolicy {
+        on_failure,
+        reset_failure: root.child_text("resetfailure"),
+        start_mode: root
+            .child_text("startmode")
+            .map(|m| StartMode::parse(&m))
+            .transpose()?,
+        delayed_auto_start,
+    })
+}
+
+/// 校验修改内容
+fn validate_update(update: &PolicyUpdate, current: &ServicePolicy) -> Result<(), WinswError> {
+    for step in update.on_failure.iter().flatten() {
+        if let Some(delay) = &step.delay {
+            parse_duration(delay)?;
+        }
+    }
+    if let Some(reset) = update
+        .reset_failure
+        .as_deref()
+        .filter(|r| !r.trim().is_empty())
+    {
+        parse_duration(reset)?;
+    }
+
+    let start_mode = update.start_mode.or(current.start_mode);
+    let delayed = update
+        .delayed_auto_start
+        .unwrap_or(current.delayed_auto_start);
+    if delayed && start_mode.is_some_and(|m| m != StartMode::Automatic) {
+        return Err(WinswError::InvalidOption(
+            "delayedAutoStart 仅适用于 Automatic 启动类型".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 将修改应用到配置文档
+pub fn apply_update(root: &mut XmlElement, update: &PolicyUpdate) -> Result<(), WinswError> {
+    let current = read_policy(root)?;
+    validate_update(update, &current)?;
+
+    if let Some(steps) = &update.on_failure {
+        let elements = steps
+            .iter()
+            .map(|s| {
+                let mut attrs = vec![("action".to_string(), s.action.as_str().to_string())];
+                if let Some(delay) = &s.delay {
+                    attrs.push(("delay".to_string(), delay.trim().to_string()));
+                }
+                XmlElement {
+                    name: "onfailure".to_string(),
+                    attrs,
+                    children: Vec::new(),
+                }
+            })
+            .collect();
+        root.replace_elements("onfailure", elements);
+    }
+    if let Some(reset) = &update.reset_failure {
+        let reset = reset.trim();
+        root.set_child_text("resetfailure", Some(reset).filter(|r| !r.is_empty()));
+    }
+    if let Some(mode) = update.start_mode {
+        root.set_child_text("startmode", Some(mode.as_str()));
+    }
+    if let Some(delayed) = update.delayed_auto_start {
+        root.set_child_text("delayedAutoStart", delayed.then_some("true"));
+    }
+    Ok(())
+}
+
+fn load_document(path: &Path) -> Result<XmlDocument, WinswError> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            WinswError::ConfigNotFound(path.display().to_string())
+        } else {
+            WinswError::ConfigRead(e.to_string())
+        }
+    })?;
+    XmlDocument::parse(&text)
+}
+
+/// Tauri 命令：读取服务的故障恢复策略与启动类型
+#[tauri::command]
+pub async fn winsw_policy_get(app: AppHandle, req: PolicyReq) -> Result<ServicePolicy, String> {
+    resolve_config(&app, req.service_id.as_deref(), req.config.as_deref())
+        .and_then(|p| load_document(&p))
+        .and_then(|doc| read_policy(&doc.root))
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：修改故障恢复策略与启动类型，可选执行 refresh 使其生效
+///
+/// ```javascript
+/// await invoke('winsw_policy_set', {
+///   req: { service_id: 'myapp' },
+///   update: {
+///     on_failure: [
+///       { action: 'restart', delay: '10 sec' },
+///       { action: 'restart', delay: '1 min' },
+///       { action: 'none' }
+///     ],
+///     reset_failure: '1 hour',
+///     start_mode: 'automatic',
+///     delayed_auto_start: true
+///   },
+///   refresh: true
+/// });
+/// ```
+#[tauri::command]
+pub async fn winsw_policy_set(
+    app: AppHandle,
+    req: PolicyReq,
+    update: PolicyUpdate,
+    refresh: Option<bool>,
+) -> Result<PolicyResp, String> {
+    let action_req = ActionReq {
+        service_id: req.service_id.clone(),
+        winsw_path: req.winsw_path.clone(),
+        config: req.config.clone(),
+        ..Default::default()
+    };
+    let (target, timeout_secs) =
+        resolve_target(&app, Some(&action_req)).map_err(|e| e.to_string())?;
+    let path = resolve_config(&app, req.service_id.as_deref(), req.config.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    let (policy, revision) = (|| {
+        let mut doc = load_document(&path)?;
+        apply_update(&mut doc.root, &update)?;
+        let revision = history::write_config(&app, &path, &doc.to_xml())?;
+        Ok::<_, WinswError>((read_policy(&doc.root)?, revision))
+    })()
+    .map_err(|e| e.to_string())?;
+
+    let refresh = if refresh.unwrap_or(false) {
+        Some(refresh_service(&app, &target, timeout_secs).await)
+    } else {
+        None
+    };
+
+    Ok(PolicyResp {
+        policy,
+        revision,
+        refresh,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winsw::config::parse_document;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10 sec").unwrap(), 10_000);
+        assert_eq!(parse_duration("1 min").unwrap(), 60_000);
+        assert_eq!(parse_duration("1.5 hours").unwrap(), 5_400_000);
+        assert_eq!(parse_duration("500").unwrap(), 500);
+        assert_eq!(parse_duration(" 2days ").unwrap(), 172_800_000);
+        assert!(matches!(
+            parse_duration("10 seconds"),
+            Err(WinswError::InvalidDuration(_))
+        ));
+        assert!(parse_duration("sec").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    const SAMPLE: &str = "<service>\n  <id>app</id>\n  <!-- 故障处理 -->\n  <onfailure action=\"restart\" delay=\"10 sec\"/>\n  <onfailure action=\"none\"/>\n  <delayedAutoStart/>\n</service>";
+
+    #[test]
+    fn test_read_policy() {
+        let policy = read_policy(&parse_document(SAMPLE).unwrap()).unwrap();
+        assert_eq!(
+            policy.on_failure,
+            vec![
+                FailureStep {
+                    action: FailureAction::Restart,
+                    delay: Some("10 sec".into())
+                },
+                FailureStep {
+                    action: FailureAction::None,
+                    delay: None
+                },
+            ]
+        );
+        assert!(policy.delayed_auto_start);
+        assert_eq!(policy.start_mode, None);
+        assert_eq!(policy.reset_failure, None);
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut root = parse_document(SAMPLE).unwrap();
+        let update = PolicyUpdate {
+            on_failure: Some(vec![FailureStep {
+                action: FailureAction::Reboot,
+                delay: Some("1 min".into()),
+            }]),
+            reset_failure: Some("1 hour".into()),
+            start_mode: Some(StartMode::Automatic),
+            delayed_auto_start: Some(false),
+        };
+        apply_update(&mut root, &update).unwrap();
+        assert_eq!(
+            root.to_xml(),
+            "<service>\n  <id>app</id>\n  <!-- 故障处理 -->\n  <onfailure action=\"reboot\" delay=\"1 min\"/>\n  <resetfailure>1 hour</resetfailure>\n  <startmode>Automatic</startmode>\n</service>"
+        );
+
+        // 非法时长与不兼容的组合被拒绝，文档不变
+        let before = root.clone();
+        let bad = PolicyUpdate {
+            reset_failure: Some("soon".into()),
+            ..PolicyUpdate::default()
+        };
+        assert!(apply_update(&mut root, &bad).is_err());
+        let conflict = PolicyUpdate {
+            start_mode: Some(StartMode::Manual),
+            delayed_auto_start: Some(true),
+            ..PolicyUpdate::default()
+        };
+        assert!(matches!(
+            apply_update(&mut root, &conflict),
+            Err(WinswError::InvalidOption(_))
+        ));
+        assert_eq!(root, before);
+    }
+}
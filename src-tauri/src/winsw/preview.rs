@@ -0,0 +1,256 @@
+//! WinSW 操作的预览（dry run）
+//!
+//! 解析出最终的可执行文件、参数、工作目录与环境变量并做校验，但不启动任何进程。
+//! 预览时不探测 WinSW 版本，版本仅取自此前执行留下的缓存。
+
+use super::command::WinswCommand;
+use super::config::ServiceConfig;
+use super::provision::{self, WinswBinary};
+use super::{build_command_args, get_enhanced_env, starts_service, ActionTarget, WinswError};
+use crate::secrets::{self, redact_env};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// 预览结果：将要执行的完整调用
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunPlan {
+    /// WinSW 可执行文件路径
+    pub executable: String,
+    pub args: Vec<String>,
+    /// 进程工作目录（继承自本应用）
+    pub working_dir: Option<String>,
+    /// 合并后的环境变量，敏感值已脱敏；密钥引用保持原样，不在预览时解析
+    pub env: BTreeMap<String, String>,
+    /// 缓存中的 WinSW 版本
+    pub winsw_version: Option<String>,
+    /// 校验警告，不阻止执行
+    pub warnings: Vec<String>,
+}
+
+impl DryRunPlan {
+    /// 便于展示的命令行
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.executable)
+            .chain(&self.args)
+            .map(|a| {
+                if a.is_empty() || a.contains([' ', '\t', '"']) {
+                    format!("\"{}\"", a.replace('"', "\\\""))
+                } else {
+                    a.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 对执行目标的校验：版本、配置文件内容与环境变量覆盖（允许列表在定位时已校验）
+fn target_warnings(binary: &WinswBinary, target: &ActionTarget) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if binary.major.is_none() {
+        warnings.push("WinSW 版本未知（预览不运行可执行文件），按 v3 构建参数".to_string());
+    }
+
+    if let Some(cfg) = target.config.as_deref() {
+        match ServiceConfig::load(cfg) {
+            Ok(config) => warnings.extend(config_warnings(&config)),
+            Err(e) => warnings.push(e.to_string()),
+        }
+    }
+
+    if let Some(custom) = &target.env {
+        let mut overridden: Vec<&String> = custom
+            .keys()
+            .filter(|k| std::env::var_os(k).is_some())
+            .collect();
+        overridden.sort();
+        for key in overridden {
+            warnings.push(format!("环境变量 {} 将覆盖系统中的同名变量", key));
+        }
+    }
+
+    warnings
+}
+
+/// 引用了密钥库中不存在的密钥（只读取名称，不解析任何值）
+fn secret_warnings(app: &AppHandle, target: &ActionTarget) -> Vec<String> {
+    let config_text = target
+        .config
+        .as_deref()
+        .and_then(|c| std::fs::read_to_string(c).ok());
+    let missing = |known: &[secrets::SecretInfo]| {
+        secrets::missing_refs(known, target.env.as_ref(), config_text.as_deref())
+    };
+    // 没有任何引用时不打开密钥库
+    if missing(&[]).is_empty() {
+        return Vec::new();
+    }
+    match secrets::open_store(app).and_then(|s| secrets::SecretStore::list(&s)) {
+        Ok(known) => missing(&known)
+            .into_iter()
+            .map(|name| format!("引用的密钥 '{}' 不存在，执行时将失败", name))
+            .collect(),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// 配置文件中被包装程序与工作目录是否存在
+pub(crate) fn config_warnings(config: &ServiceConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    match config.executable.as_deref().map(|e| config.expand(e)) {
+        None => warnings.push("配置文件缺少 <executable>".to_string()),
+        Some(exe) => {
+            let path = Path::new(&exe);
+            let found = if path.components().count() > 1 || path.is_absolute() {
+                path.exists()
+            } else {
+                which::which(&exe).is_ok()
+            };
+            // 含未展开变量（如 %JAVA_HOME%）的路径无法在预览时判断
+            if !found && !exe.contains('%') {
+                warnings.push(format!("被包装的程序不存在: {}", exe));
+            }
+        }
+    }
+
+    if let Some(dir) = config
+        .working_directory
+        .as_deref()
+        .map(|d| config.expand(d))
+    {
+        if !dir.contains('%') && !Path::new(&dir).is_dir() {
+            warnings.push(format!("工作目录不存在: {}", dir));
+        }
+    }
+
+    warnings
+}
+
+fn plan_invocation(
+    binary: &WinswBinary,
+    args: Vec<String>,
+    target: &ActionTarget,
+    warnings: Vec<String>,
+) -> DryRunPlan {
+    DryRunPlan {
+        executable: binary.path.display().to_string(),
+        args,
+        working_dir: std::env::current_dir()
+            .ok()
+            .map(|d| d.display().to_string()),
+        env: redact_env(&get_enhanced_env(
+            target.env.as_ref(),
+            target.profile.as_ref(),
+        )),
+        winsw_version: binary.version.clone(),
+        warnings,
+    }
+}
+
+/// 预览 WinSW 操作
+pub(crate) async fn preview_action(
+    app: &AppHandle,
+    action: &str,
+    target: &ActionTarget,
+) -> Result<DryRunPlan, WinswError> {
+    let binary = provision::resolve_cached(app, &target.winsw_path).await?;
+    let args = build_command_args(action, target.config.as_deref(), Some(&binary))?;
+
+    let mut warnings = target_warnings(&binary, target);
+    warnings.extend(secret_warnings(app, target));
+    if target.health.is_some() && !starts_service(action) {
+        warnings.push(format!("操作 '{}' 不会启动服务，健康检查将被忽略", action));
+    }
+    Ok(plan_invocation(&binary, args, target, warnings))
+}
+
+/// 预览扩展命令
+pub(crate) async fn preview_command(
+    app: &AppHandle,
+    command: &WinswCommand,
+    target: &ActionTarget,
+) -> Result<DryRunPlan, WinswError> {
+    let binary = provision::resolve_cached(app, &target.winsw_path).await?;
+    let args = command.build_args(target.config.as_deref(), &binary)?;
+    let mut warnings = target_warnings(&binary, target);
+    warnings.extend(secret_warnings(app, target));
+    Ok(plan_invocation(&binary, args, target, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::REDACTED;
+    use crate::winsw::provision::{BinarySource, WinswMajor};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_plan_and_warnings() {
+        let dir = std::env::temp_dir().join(format!("winsw-preview-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = dir.join("myapp.xml");
+        std::fs::write(
+            &cfg,
+            "<service><id>myapp</id><executable>%BASE%\\missing-app.exe</executable>\
+             <workingdirectory>%BASE%</workingdirectory></service>",
+        )
+        .unwrap();
+
+        let binary = WinswBinary {
+            path: dir.join("my app.exe"),
+            source: BinarySource::Explicit,
+            sha256: "00".into(),
+            verified: None,
+            version: None,
+            major: None,
+        };
+        let target = ActionTarget {
+            service_id: None,
+            winsw_path: binary.path.display().to_string(),
+            config: Some(cfg.display().to_string()),
+            env: Some(HashMap::from([
+                ("API_KEY".to_string(), "k".to_string()),
+                ("PATH".to_string(), "C:\\tools".to_string()),
+            ])),
+            ..Default::default()
+        };
+
+        let warnings = target_warnings(&binary, &target);
+        assert!(warnings.iter().any(|w| w.contains("版本未知")));
+        assert!(warnings.iter().any(|w| w.contains("missing-app.exe")));
+        assert!(warnings.iter().any(|w| w.contains("PATH")));
+        assert!(!warnings.iter().any(|w| w.contains("工作目录")));
+
+        let plan = plan_invocation(
+            &binary,
+            vec!["install".into(), "x.xml".into()],
+            &target,
+            warnings,
+        );
+        assert_eq!(plan.env["API_KEY"], REDACTED);
+        assert_eq!(plan.env["PATH"], "C:\\tools");
+        assert!(plan.command_line().starts_with('"'));
+        assert!(plan.command_line().ends_with("install x.xml"));
+
+        // 已识别版本且通过校验时不产生对应警告
+        let known = WinswBinary {
+            verified: Some(true),
+            version: Some("3.0.0".into()),
+            major: Some(WinswMajor::V3),
+            ..binary
+        };
+        let target = ActionTarget {
+            env: None,
+            ..target
+        };
+        let warnings = target_warnings(&known, &target);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,488 @@
+//! WinSW 可执行文件的定位、校验与版本识别
+//!
+//! WinSW v2 与 v3 的命令行不同：v3 通过参数传入配置文件路径，v2 只读取与可执行文件同名、
+//! 同目录的 XML。执行前先确定实际使用的可执行文件及其主版本，再据此构建参数。
+
+use super::{data_dir, WinswError, DEFAULT_WINSW_PATH};
+use crate::exec::{self, ExecRequest};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+const SETTINGS_FILE: &str = "provision.json";
+const VERSION_TIMEOUT_SECS: u64 = 10;
+/// 资源目录中可能的 WinSW 文件名
+const BUNDLED_NAMES: &[&str] = &[
+    "winsw.exe",
+    "WinSW-x64.exe",
+    "WinSW-x86.exe",
+    "WinSW-net461.exe",
+];
+
+/// WinSW 主版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WinswMajor {
+    V2,
+    V3,
+}
+
+/// 可执行文件来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinarySource {
+    /// 请求或服务目录中给出的路径
+    Explicit,
+    /// 应用配置（provision.json）中的路径
+    Config,
+    /// 应用资源目录中随附的文件
+    Bundled,
+    /// PATH 环境变量
+    Path,
+}
+
+/// 定位设置，保存在 `<app_data_dir>/winsw/provision.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProvisionSettings {
+    /// 默认使用的 WinSW 路径
+    pub winsw_path: Option<String>,
+    /// 允许的 SHA-256（十六进制，不区分大小写）；非空时拒绝使用不在列表中的可执行文件
+    #[serde(default)]
+    pub allowed_sha256: Vec<String>,
+    /// 为 true 时允许列表为空也拒绝使用（必须先配置允许列表）
+    #[serde(default)]
+    pub require_verified: bool,
+}
+
+impl ProvisionSettings {
+    /// 哈希是否在允许列表中；允许列表为空时为 None
+    pub fn verify(&self, sha256: &str) -> Option<bool> {
+        if self.allowed_sha256.is_empty() {
+            return None;
+        }
+        Some(
+            self.allowed_sha256
+                .iter()
+                .any(|h| h.trim().eq_ignore_ascii_case(sha256)),
+        )
+    }
+
+    /// 是否拒绝使用该哈希的可执行文件
+    pub fn rejects(&self, sha256: &str) -> bool {
+        match self.verify(sha256) {
+            Some(ok) => !ok,
+            None => self.require_verified,
+        }
+    }
+}
+
+/// 解析后的 WinSW 可执行文件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WinswBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+    pub sha256: String,
+    /// 是否在允许列表中；允许列表为空时为 None
+    pub verified: Option<bool>,
+    pub version: Option<String>,
+    pub major: Option<WinswMajor>,
+}
+
+/// 按（路径, 修改时间, 大小）缓存哈希与版本，避免每次执行都重新计算
+type InspectCache = HashMap<PathBuf, (SystemTime, u64, String, Option<String>)>;
+
+fn inspect_cache() -> &'static Mutex<InspectCache> {
+    static CACHE: OnceLock<Mutex<InspectCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, WinswError> {
+    Ok(data_dir(app)?.join(SETTINGS_FILE))
+}
+
+/// 读取定位设置，文件不存在时返回默认值
+pub fn load_settings(path: &Path) -> Result<ProvisionSettings, WinswError> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            serde_json::from_str(&text).map_err(|e| WinswError::ProvisionParse(e.to_string()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProvisionSettings::default()),
+        Err(e) => Err(WinswError::ProvisionIo(e.to_string())),
+    }
+}
+
+/// 先写临时文件再替换，避免中途失败留下不完整的设置
+fn save_settings(path: &Path, settings: &ProvisionSettings) -> Result<(), WinswError> {
+    let io_err = |e: std::io::Error| WinswError::ProvisionIo(e.to_string());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    let text = serde_json::to_string_pretty(settings)
+        .map_err(|e| WinswError::ProvisionParse(e.to_string()))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, text).map_err(io_err)?;
+    std::fs::rename(&tmp, path).map_err(io_err)
+}
+
+/// 请求的路径是否为“未指定”：空或默认的裸文件名
+pub(crate) fn is_unspecified(requested: &str) -> bool {
+    let requested = requested.trim();
+    requested.is_empty() || requested.eq_ignore_ascii_case(DEFAULT_WINSW_PATH)
+}
+
+/// 定位 WinSW：显式路径 → 应用配置 → 资源目录 → PATH
+pub fn locate(
+    requested: &str,
+    settings: &ProvisionSettings,
+    bundled_dir: Option<&Path>,
+) -> Result<(PathBuf, BinarySource), WinswError> {
+    if !is_unspecified(requested) {
+        return locate_path(requested).map(|p| (p, BinarySource::Explicit));
+    }
+    if let Some(configured) = settings.winsw_path.as_deref().filter(|p| !p.is_empty()) {
+        return locate_path(configured).map(|p| (p, BinarySource::Config));
+    }
+    if let Some(dir) = bundled_dir {
+        if let Some(p) = BUNDLED_NAMES
+            .iter()
+            .map(|n| dir.join(n))
+            .find(|p| p.is_file())
+        {
+            return Ok((p, BinarySource::Bundled));
+        }
+    }
+    which::which(DEFAULT_WINSW_PATH)
+        .map(|p| (p, BinarySource::Path))
+        .map_err(|_| WinswError::BinaryNotFound(DEFAULT_WINSW_PATH.to_string()))
+}
+
+/// 含目录的路径直接使用，裸文件名在 PATH 中查找
+fn locate_path(path: &str) -> Result<PathBuf, WinswError> {
+    let p = Path::new(path);
+    if p.components().count() > 1 || p.is_absolute() {
+        if p.is_file() {
+            Ok(p.to_path_buf())
+        } else {
+            Err(WinswError::BinaryNotFound(path.to_string()))
+        }
+    } else {
+        which::which(path).map_err(|_| WinswError::BinaryNotFound(path.to_string()))
+    }
+}
+
+/// 计算文件的 SHA-256（小写十六进制）
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// 从版本输出中解析版本号与主版本，如 `WinSW 2.12.0` 或 `3.0.0-alpha.11`
+pub fn parse_version(output: &str) -> Option<(String, Option<WinswMajor>)> {
+    let token = output.split_whitespace().find(|t| {
+        let t = t.trim_start_matches(['v', 'V']);
+        let mut parts = t.split('.');
+        matches!(
+            (parts.next(), parts.next()),
+            (Some(a), Some(b)) if !a.is_empty() && a.chars().all(|c| c.is_ascii_digit())
+                && b.chars().next().is_some_and(|c| c.is_ascii_digit())
+        )
+    })?;
+    let version = token.trim_start_matches(['v', 'V']).to_string();
+    let major = match version.split('.').next() {
+        Some("2") => Some(WinswMajor::V2),
+        Some("3") => Some(WinswMajor::V3),
+        _ => None,
+    };
+    Some((version, major))
+}
+
+/// 运行可执行文件获取版本：v3 支持 `--version`，v2 使用 `version` 子命令
+pub async fn detect_version(path: &Path) -> Option<String> {
+    for arg in ["--version", "version"] {
+        let req = ExecRequest::new(path, vec![arg.to_string()], VERSION_TIMEOUT_SECS);
+        let out = match exec::run(&req, None).await {
+            Ok(out) if !out.interrupted() => out,
+            _ => continue,
+        };
+        let text = format!(
+            "{}\n{}",
+            out.stdout.unwrap_or_default(),
+            out.stderr.unwrap_or_default()
+        );
+        if let Some((version, _)) = parse_version(&text) {
+            return Some(version);
+        }
+    }
+    None
+}
+
+/// 计算哈希、识别版本并按允许列表校验
+pub async fn inspect(
+    path: PathBuf,
+    source: BinarySource,
+    settings: &ProvisionSettings,
+) -> Result<WinswBinary, WinswError> {
+    inspect_binary(path, source, settings, true).await
+}
+
+/// `probe` 为 false 时不运行可执行文件，版本仅取自缓存
+///
+/// 先按允许列表校验哈希，未通过校验的可执行文件不会被运行。
+async fn inspect_binary(
+    path: PathBuf,
+    source: BinarySource,
+    settings: &ProvisionSettings,
+    probe: bool,
+) -> Result<WinswBinary, WinswError> {
+    let meta = std::fs::metadata(&path)
+        .map_err(|_| WinswError::BinaryNotFound(path.display().to_string()))?;
+    let stamp = (
+        meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        meta.len(),
+    );
+
+    let cached = inspect_cache()
+        .lock()
+        .expect("inspect cache lock")
+        .get(&path)
+        .filter(|c| (c.0, c.1) == stamp)
+        .map(|c| (c.2.clone(), c.3.clone()));
+    let (sha256, cached_version) = match cached {
+        Some((sha256, version)) => (sha256, Some(version)),
+        None => {
+            let sha256 = sha256_file(&path).map_err(|e| WinswError::BinaryRead(e.to_string()))?;
+            (sha256, None)
+        }
+    };
+
+    let verified = settings.verify(&sha256);
+    if settings.rejects(&sha256) {
+        return Err(WinswError::BinaryUnverified {
+            path: path.display().to_string(),
+            sha256,
+        });
+    }
+
+    let version = match cached_version {
+        Some(version) => version,
+        None if !probe => None,
+        None => {
+            let version = detect_version(&path).await;
+            inspect_cache().lock().expect("inspect cache lock").insert(
+                path.clone(),
+                (stamp.0, stamp.1, sha256.clone(), version.clone()),
+            );
+            version
+        }
+    };
+
+    let major = version
+        .as_deref()
+        .and_then(parse_version)
+        .and_then(|(_, m)| m);
+    Ok(WinswBinary {
+        path,
+        source,
+        sha256,
+        verified,
+        version,
+        major,
+    })
+}
+
+/// 读取应用的定位设置
+pub(crate) fn app_settings(app: &AppHandle) -> Result<ProvisionSettings, WinswError> {
+    load_settings(&settings_path(app)?)
+}
+
+/// 按应用设置定位并检查 WinSW
+pub async fn resolve(app: &AppHandle, requested: &str) -> Result<WinswBinary, WinswError> {
+    let settings = app_settings(app)?;
+    let bundled = app.path().resource_dir().ok().map(|d| d.join("winsw"));
+    let (path, source) = locate(requested, &settings, bundled.as_deref())?;
+    inspect(path, source, &settings).await
+}
+
+/// 与 [`resolve`] 相同，但不运行可执行文件（用于预览），未缓存时版本未知
+pub async fn resolve_cached(app: &AppHandle, requested: &str) -> Result<WinswBinary, WinswError> {
+    let settings = app_settings(app)?;
+    let bundled = app.path().resource_dir().ok().map(|d| d.join("winsw"));
+    let (path, source) = locate(requested, &settings, bundled.as_deref())?;
+    inspect_binary(path, source, &settings, false).await
+}
+
+/// Tauri 命令：查看将要使用的 WinSW 可执行文件（路径、来源、哈希、版本）
+#[tauri::command]
+pub async fn winsw_binary_info(
+    app: AppHandle,
+    winsw_path: Option<String>,
+) -> Result<WinswBinary, String> {
+    resolve(&app, winsw_path.as_deref().unwrap_or(DEFAULT_WINSW_PATH))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：读取 WinSW 定位设置
+#[tauri::command]
+pub async fn winsw_provision_get(app: AppHandle) -> Result<ProvisionSettings, String> {
+    app_settings(&app).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：保存 WinSW 定位设置
+#[tauri::command]
+pub async fn winsw_provision_set(
+    app: AppHandle,
+    settings: ProvisionSettings,
+) -> Result<ProvisionSettings, String> {
+    settings_path(&app)
+        .and_then(|p| save_settings(&p, &settings))
+        .map(|_| settings)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("winsw-provision-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("WinSW 2.12.0.0"),
+            Some(("2.12.0.0".into(), Some(WinswMajor::V2)))
+        );
+        assert_eq!(
+            parse_version("3.0.0-alpha.11+0a1b2c"),
+            Some(("3.0.0-alpha.11+0a1b2c".into(), Some(WinswMajor::V3)))
+        );
+        assert_eq!(
+            parse_version("WinSW v3.0.0"),
+            Some(("3.0.0".into(), Some(WinswMajor::V3)))
+        );
+        assert_eq!(parse_version("1.19.1"), Some(("1.19.1".into(), None)));
+        assert_eq!(parse_version("Unknown command"), None);
+    }
+
+    #[test]
+    fn test_sha256_and_locate() {
+        let dir = temp_dir("locate");
+        let bundled = dir.join("bundled");
+        std::fs::create_dir_all(&bundled).unwrap();
+        std::fs::write(bundled.join("WinSW-x64.exe"), b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&bundled.join("WinSW-x64.exe")).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let explicit = dir.join("custom.exe");
+        std::fs::write(&explicit, b"x").unwrap();
+        let settings = ProvisionSettings::default();
+
+        let (p, src) = locate(explicit.to_str().unwrap(), &settings, Some(&bundled)).unwrap();
+        assert_eq!((p, src), (explicit.clone(), BinarySource::Explicit));
+
+        let (p, src) = locate(DEFAULT_WINSW_PATH, &settings, Some(&bundled)).unwrap();
+        assert_eq!(
+            (p, src),
+            (bundled.join("WinSW-x64.exe"), BinarySource::Bundled)
+        );
+
+        let configured = ProvisionSettings {
+            winsw_path: Some(explicit.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let (_, src) = locate("", &configured, Some(&bundled)).unwrap();
+        assert_eq!(src, BinarySource::Config);
+
+        assert!(matches!(
+            locate(dir.join("missing.exe").to_str().unwrap(), &settings, None),
+            Err(WinswError::BinaryNotFound(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_inspect_detects_version_and_verifies() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("inspect");
+        let exe = dir.join("winsw.exe");
+        let marker = dir.join("ran");
+        std::fs::write(
+            &exe,
+            format!(
+                "#!/bin/sh\ntouch '{}'\necho 3.0.0-alpha.11\n",
+                marker.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let sha = sha256_file(&exe).unwrap();
+
+        // 未通过校验的可执行文件不会被运行；允许列表非空即校验
+        let listed = ProvisionSettings {
+            allowed_sha256: vec!["00".repeat(32)],
+            ..Default::default()
+        };
+        assert!(matches!(
+            inspect(exe.clone(), BinarySource::Explicit, &listed).await,
+            Err(WinswError::BinaryUnverified { .. })
+        ));
+        let strict = ProvisionSettings {
+            require_verified: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            inspect(exe.clone(), BinarySource::Explicit, &strict).await,
+            Err(WinswError::BinaryUnverified { .. })
+        ));
+        assert!(!marker.exists());
+
+        let open = ProvisionSettings::default();
+        let bin = inspect(exe.clone(), BinarySource::Explicit, &open)
+            .await
+            .unwrap();
+        assert_eq!(bin.major, Some(WinswMajor::V3));
+        assert_eq!(bin.verified, None);
+        assert!(marker.exists());
+
+        let allowed = ProvisionSettings {
+            allowed_sha256: vec![sha.to_uppercase()],
+            require_verified: true,
+            ..Default::default()
+        };
+        let bin = inspect(exe, BinarySource::Explicit, &allowed)
+            .await
+            .unwrap();
+        assert_eq!(bin.verified, Some(true));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,524 @@
+//! 将 Scoop 安装的应用部署为 WinSW 服务
+//!
+//! 流程：确保包已安装 → 从应用清单的 `bin` 与 `current` 目录解析可执行文件 →
+//! 生成指向 `current` 稳定路径的配置 → 事务式部署（安装并启动）→ 登记到服务目录。
+//! 配置中只引用 `current` 路径，`scoop update` 之后服务无需重新生成配置。
+
+use super::catalog::{update_catalog, AddServiceReq};
+use super::config::{write_document, XmlElement, XmlNode};
+use super::deploy::{self, DeployReport, DeployReq};
+use super::health::HealthSpec;
+use super::provision;
+use super::{
+    data_dir, resolve_target, ActionReq, LineSink, OutputLine, WinswError, DEFAULT_WINSW_PATH,
+};
+use crate::exec;
+use crate::scoop::{self, BinEntry, InstallOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Channel;
+use tauri::AppHandle;
+
+/// 生成的服务配置
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServiceSpec {
+    /// 服务 ID，同时用作服务目录中的 ID 与配置、可执行文件的文件名
+    pub id: String,
+    /// 显示名称，默认与 ID 相同
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// 可执行文件（相对于应用的 `current` 目录），未提供时从清单的 `bin` 中选择
+    pub executable: Option<String>,
+    /// 启动参数，未提供时使用清单中 `bin` 条目附带的参数
+    pub arguments: Option<String>,
+    /// 工作目录，默认为应用的 `current` 目录
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// 启动模式（Automatic / Manual 等）
+    pub start_mode: Option<String>,
+    /// 日志模式（如 roll）
+    pub log_mode: Option<String>,
+}
+
+/// 部署请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScoopServiceReq {
+    /// Scoop 包名（可带 bucket 前缀，如 `main/nginx`）
+    pub package: String,
+    /// 是否为全局安装
+    pub global: Option<bool>,
+    /// Scoop 安装的超时时间（秒）
+    pub install_timeout_seconds: Option<u64>,
+    pub service: ServiceSpec,
+    /// 服务文件所在目录，默认为 `<app_data_dir>/winsw/services/<id>`
+    pub service_dir: Option<String>,
+    /// 复制到服务目录中使用的 WinSW 可执行文件，默认按定位规则查找
+    pub winsw_source: Option<String>,
+    /// 每个 WinSW 步骤的超时时间（秒）
+    pub timeout_seconds: Option<u64>,
+    pub health: Option<HealthSpec>,
+    /// 使用的环境配置名称（安装与服务执行均使用）
+    pub profile: Option<String>,
+    /// 是否登记到服务目录，默认为 true
+    pub register: Option<bool>,
+}
+
+/// 流程步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStep {
+    InstallPackage,
+    ResolveExecutable,
+    GenerateConfig,
+    Deploy,
+    Register,
+}
+
+/// 单个步骤的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub step: WorkflowStep,
+    pub ok: bool,
+    pub message: Option<String>,
+}
+
+/// 流程报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScoopServiceReport {
+    pub ok: bool,
+    pub steps: Vec<StepResult>,
+    /// 被包装的可执行文件（位于 `current` 目录下）
+    pub executable: Option<String>,
+    pub config_path: Option<String>,
+    /// 部署步骤的详细日志
+    pub deploy: Option<DeployReport>,
+    pub error: Option<String>,
+}
+
+impl ScoopServiceReport {
+    fn step(&mut self, step: WorkflowStep, result: Result<Option<String>, String>) -> bool {
+        let ok = result.is_ok();
+        let message = result.unwrap_or_else(|e| {
+            self.error = Some(e.clone());
+            Some(e)
+        });
+        self.steps.push(StepResult { step, ok, message });
+        ok
+    }
+}
+
+/// 选择被包装的可执行文件，返回（相对路径, 清单中的参数）
+///
+/// 只考虑 `.exe`；有多个时取文件名或别名与应用名相同的一个。
+fn choose_executable(app: &str, bins: &[BinEntry]) -> Result<(String, Vec<String>), String> {
+    let exes: Vec<&BinEntry> = bins
+        .iter()
+        .filter(|b| b.path.to_lowercase().ends_with(".exe"))
+        .collect();
+    let stem = |b: &BinEntry| {
+        Path::new(&b.path.replace('\\', "/"))
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    };
+    let app = app.to_lowercase();
+    let chosen = match exes.as_slice() {
+        [] => None,
+        [only] => Some(*only),
+        many => many
+            .iter()
+            .find(|b| {
+                stem(b) == app || b.alias.as_deref().map(str::to_lowercase) == Some(app.clone())
+            })
+            .copied(),
+    };
+    match chosen {
+        Some(b) => Ok((b.path.clone(), b.args.clone())),
+        None if exes.is_empty() => Err("应用清单的 bin 中没有 .exe，请通过 executable 指定".into()),
+        None => Err(format!(
+            "应用清单中有多个可执行文件，请通过 executable 指定: {}",
+            exes.iter()
+                .map(|b| b.path.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn push_child(root: &mut XmlElement, name: &str, text: &str) {
+    root.children.push(XmlNode::Text("\n  ".to_string()));
+    root.children
+        .push(XmlNode::Element(XmlElement::with_text(name, text)));
+}
+
+/// 生成服务配置
+fn build_config(spec: &ServiceSpec, executable: &Path, current: &Path, args: &[String]) -> String {
+    let mut root = XmlElement {
+        name: "service".to_string(),
+        ..Default::default()
+    };
+    push_child(&mut root, "id", &spec.id);
+    push_child(&mut root, "name", spec.name.as_deref().unwrap_or(&spec.id));
+    if let Some(description) = &spec.description {
+        push_child(&mut root, "description", description);
+    }
+    push_child(&mut root, "executable", &executable.display().to_string());
+    let arguments = spec.arguments.clone().unwrap_or_else(|| args.join(" "));
+    if !arguments.trim().is_empty() {
+        push_child(&mut root, "arguments", &arguments);
+    }
+    let working_dir = spec
+        .working_directory
+        .clone()
+        .unwrap_or_else(|| current.display().to_string());
+    push_child(&mut root, "workingdirectory", &working_dir);
+    for (name, value) in &spec.env {
+        root.children.push(XmlNode::Text("\n  ".to_string()));
+        root.children.push(XmlNode::Element(XmlElement {
+            name: "env".to_string(),
+            attrs: vec![
+                ("name".to_string(), name.clone()),
+                ("value".to_string(), value.clone()),
+            ],
+            children: Vec::new(),
+        }));
+    }
+    if let Some(mode) = &spec.start_mode {
+        push_child(&mut root, "startmode", mode);
+    }
+    if let Some(mode) = &spec.log_mode {
+        root.children.push(XmlNode::Text("\n  ".to_string()));
+        root.children.push(XmlNode::Element(XmlElement {
+            name: "log".to_string(),
+            attrs: vec![("mode".to_string(), mode.clone())],
+            children: Vec::new(),
+        }));
+    }
+    root.children.push(XmlNode::Text("\n".to_string()));
+    write_document(&root)
+}
+
+struct Workflow<'a> {
+    app: &'a AppHandle,
+    req: &'a ScoopServiceReq,
+    report: ScoopServiceReport,
+}
+
+impl Workflow<'_> {
+    async fn install(&mut self) -> Option<PathBuf> {
+        let (profile, redactor) = match scoop::load_profile(self.app, self.req.profile.as_deref()) {
+            Ok(p) => p,
+            Err(e) => {
+                self.report.step(WorkflowStep::InstallPackage, Err(e));
+                return None;
+            }
+        };
+        let global = self.req.global.unwrap_or(false);
+        let current = scoop::current_dir(&self.req.package, global, profile.as_ref());
+        let opts = InstallOptions {
+            timeout_seconds: self.req.install_timeout_seconds,
+            global: self.req.global,
+            profile,
+            transcript: exec::transcript_options(
+                self.app,
+                &format!("scoop-install-{}", scoop::app_name(&self.req.package)),
+            ),
+            redactor: redactor.clone(),
+            ..Default::default()
+        };
+        // 已安装时 scoop install 不做任何改动
+        let result = scoop::install_package(&self.req.package, opts)
+            .await
+            .map(|_| Some(format!("已安装到 {}", current.display())))
+            .map_err(|e| redactor.redact(&e.to_string()));
+        self.report
+            .step(WorkflowStep::InstallPackage, result)
+            .then_some(current)
+    }
+
+    fn resolve_executable(&mut self, current: &Path) -> Option<(PathBuf, Vec<String>)> {
+        let result = (|| {
+            let (relative, args) = match self.req.service.executable.as_deref() {
+                Some(exe) => (exe.to_string(), Vec::new()),
+                None => {
+                    let manifest = scoop::read_manifest(current).map_err(|e| e.to_string())?;
+                    choose_executable(scoop::app_name(&self.req.package), &manifest.bin)?
+                }
+            };
+            let exe = current.join(&relative);
+            if !exe.is_file() {
+                return Err(format!("可执行文件不存在: {}", exe.display()));
+            }
+            Ok((exe, args))
+        })();
+        let message = result
+            .as_ref()
+            .map(|(exe, _)| Some(exe.display().to_string()));
+        self.report.step(
+            WorkflowStep::ResolveExecutable,
+            message.map_err(Clone::clone),
+        );
+        let (exe, args) = result.ok()?;
+        self.report.executable = Some(exe.display().to_string());
+        Some((exe, args))
+    }
+
+    fn service_dir(&self) -> Result<PathBuf, WinswError> {
+        match self.req.service_dir.as_deref() {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => Ok(data_dir(self.app)?
+                .join("services")
+                .join(&self.req.service.id)),
+        }
+    }
+
+    async fn deploy(&mut self, config_path: &Path, xml: String, on_line: LineSink<'_>) -> bool {
+        let dir = config_path.parent().unwrap_or(Path::new(""));
+        let winsw_path = dir.join(format!("{}.exe", self.req.service.id));
+        let requested = self
+            .req
+            .winsw_source
+            .clone()
+            .unwrap_or_else(|| DEFAULT_WINSW_PATH.to_string());
+        let source = match provision::resolve(self.app, &requested).await {
+            Ok(b) => b.path,
+            Err(e) => return self.report.step(WorkflowStep::Deploy, Err(e.to_string())),
+        };
+
+        let deploy_req = DeployReq {
+            winsw_path: Some(winsw_path.display().to_string()),
+            config: Some(config_path.display().to_string()),
+            config_xml: Some(xml),
+            // 服务目录中已是同一文件时不再替换
+            winsw_source: (source != winsw_path).then(|| source.display().to_string()),
+            timeout_seconds: self.req.timeout_seconds,
+            health: self.req.health.clone(),
+            profile: self.req.profile.clone(),
+            ..Default::default()
+        };
+        let action_req = ActionReq {
+            winsw_path: deploy_req.winsw_path.clone(),
+            config: deploy_req.config.clone(),
+            timeout_seconds: deploy_req.timeout_seconds,
+            health: deploy_req.health.clone(),
+            profile: deploy_req.profile.clone(),
+            ..Default::default()
+        };
+        let (target, timeout_secs) = match resolve_target(self.app, Some(&action_req)) {
+            Ok(t) => t,
+            Err(e) => return self.report.step(WorkflowStep::Deploy, Err(e.to_string())),
+        };
+
+        let report =
+            deploy::deploy(self.app, &deploy_req, target, timeout_secs, Some(on_line)).await;
+        let result = match &report.error {
+            None if report.upgrade => Ok(Some("已更新并启动".to_string())),
+            None => Ok(Some("已安装并启动".to_string())),
+            Some(e) => Err(e.clone()),
+        };
+        self.report.deploy = Some(report);
+        self.report.step(WorkflowStep::Deploy, result)
+    }
+
+    async fn register(&mut self, config_path: &Path) {
+        let id = self.req.service.id.clone();
+        let winsw_path = config_path.with_extension("exe");
+        let req = AddServiceReq {
+            id: id.clone(),
+            config_path: config_path.display().to_string(),
+            winsw_path: Some(winsw_path.display().to_string()),
+            tags: Some(vec!["scoop".to_string()]),
+            env_vars: None,
+            profile: None,
+            health: self.req.health.clone(),
+            depends_on: None,
+        };
+        let result = update_catalog(self.app, |c| {
+            if c.get(&id).is_some() {
+                return Ok(Some("服务目录中已存在，未修改".to_string()));
+            }
+            c.add(req).map(|_| None)
+        })
+        .await
+        .map_err(|e| e.to_string());
+        self.report.step(WorkflowStep::Register, result);
+    }
+
+    async fn run(&mut self, on_line: LineSink<'_>) {
+        let Some(current) = self.install().await else {
+            return;
+        };
+        let Some((exe, args)) = self.resolve_executable(&current) else {
+            return;
+        };
+
+        let config_path = match self.service_dir() {
+            Ok(dir) => dir.join(format!("{}.xml", self.req.service.id)),
+            Err(e) => {
+                self.report
+                    .step(WorkflowStep::GenerateConfig, Err(e.to_string()));
+                return;
+            }
+        };
+        let xml = build_config(&self.req.service, &exe, &current, &args);
+        self.report.config_path = Some(config_path.display().to_string());
+        self.report.step(
+            WorkflowStep::GenerateConfig,
+            Ok(Some(config_path.display().to_string())),
+        );
+
+        if !self.deploy(&config_path, xml, on_line).await {
+            return;
+        }
+        if self.req.register.unwrap_or(true) {
+            self.register(&config_path).await;
+        }
+    }
+}
+
+fn validate(req: &ScoopServiceReq) -> Result<(), String> {
+    if scoop::app_name(&req.package).is_empty() {
+        return Err(scoop::ScoopError::InvalidPackageName.to_string());
+    }
+    let id = req.service.id.trim();
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    {
+        return Err(format!(
+            "服务 ID 无效: '{}'（仅允许字母、数字、'-'、'_'、'.'）",
+            req.service.id
+        ));
+    }
+    Ok(())
+}
+
+/// 执行完整流程；在第一个失败的步骤处停止，部署失败时已自动回滚
+pub(crate) async fn deploy_scoop_app(
+    app: &AppHandle,
+    req: &ScoopServiceReq,
+    on_line: LineSink<'_>,
+) -> ScoopServiceReport {
+    if let Err(e) = validate(req) {
+        return ScoopServiceReport {
+            error: Some(e),
+            ..Default::default()
+        };
+    }
+    let mut workflow = Workflow {
+        app,
+        req,
+        report: ScoopServiceReport::default(),
+    };
+    workflow.run(on_line).await;
+    let mut report = workflow.report;
+    report.ok = report.error.is_none();
+    report
+}
+
+/// Tauri 命令：将 Scoop 应用部署为 WinSW 服务（安装包 → 解析可执行文件 → 生成配置 → 部署 → 登记）
+///
+/// ```javascript
+/// const report = await invoke('winsw_deploy_scoop_app', {
+///   req: {
+///     package: 'nginx',
+///     service: { id: 'nginx', description: 'Nginx', start_mode: 'Automatic' },
+///     health: { checks: [{ type: 'tcp', host: '127.0.0.1', port: 80 }] }
+///   },
+///   onOutput: new Channel()
+/// });
+/// // report.steps: [{ step: 'install_package', ok: true, message: '...' }, ...]
+/// ```
+#[tauri::command]
+pub async fn winsw_deploy_scoop_app(
+    app: AppHandle,
+    req: ScoopServiceReq,
+    on_output: Option<Channel<OutputLine>>,
+) -> Result<ScoopServiceReport, String> {
+    let sink = |line: OutputLine| {
+        if let Some(channel) = &on_output {
+            let _ = channel.send(line);
+        }
+    };
+    Ok(deploy_scoop_app(&app, &req, &sink).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bin(path: &str, alias: Option<&str>) -> BinEntry {
+        BinEntry {
+            path: path.to_string(),
+            alias: alias.map(str::to_string),
+            args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_choose_executable() {
+        let bins = vec![bin("nginx.exe", None)];
+        assert_eq!(choose_executable("nginx", &bins).unwrap().0, "nginx.exe");
+
+        // 多个 .exe 时按应用名或别名匹配，脚本不参与选择
+        let bins = vec![
+            bin("mc.exe", None),
+            bin("bin\\minio-server.exe", Some("minio")),
+            bin("minio.ps1", None),
+        ];
+        assert_eq!(
+            choose_executable("minio", &bins).unwrap().0,
+            "bin\\minio-server.exe"
+        );
+        let err = choose_executable("redis", &bins).unwrap_err();
+        assert!(err.contains("mc.exe"), "{}", err);
+        assert!(choose_executable("x", &[bin("x.ps1", None)]).is_err());
+    }
+
+    #[test]
+    fn test_build_config() {
+        let spec = ServiceSpec {
+            id: "redis".into(),
+            description: Some("Redis <cache>".into()),
+            env: BTreeMap::from([("REDIS_PORT".to_string(), "6379".to_string())]),
+            start_mode: Some("Automatic".into()),
+            ..Default::default()
+        };
+        let current = Path::new("C:\\scoop\\apps\\redis\\current");
+        let xml = build_config(
+            &spec,
+            &current.join("redis-server.exe"),
+            current,
+            &["redis.conf".to_string()],
+        );
+        let root = crate::winsw::config::parse_document(&xml).unwrap();
+        assert_eq!(root.child_text("name").as_deref(), Some("redis"));
+        assert_eq!(
+            root.child_text("description").as_deref(),
+            Some("Redis <cache>")
+        );
+        assert!(root.child_text("executable").unwrap().contains("current"));
+        assert_eq!(root.child_text("arguments").as_deref(), Some("redis.conf"));
+        assert_eq!(root.child("env").unwrap().attr("value"), Some("6379"));
+        assert_eq!(root.child_text("startmode").as_deref(), Some("Automatic"));
+
+        assert!(validate(&ScoopServiceReq {
+            package: "main/redis".into(),
+            service: spec,
+            ..Default::default()
+        })
+        .is_ok());
+        assert!(validate(&ScoopServiceReq {
+            package: "redis".into(),
+            service: ServiceSpec {
+                id: "bad id".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
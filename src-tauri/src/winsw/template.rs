@@ -0,0 +1,601 @@
+//! WinSW 配置模板
+//!
+//! 同一服务部署到不同环境时，只在端口、日志路径、JVM 参数等处略有差异。模板支持：
+//! - 占位符 `${name}`、带默认值的 `${name:-default}`；替换值按 XML 转义，`$${name}` 输出字面量 `${name}`；
+//! - 密钥引用 `${secret:NAME}`，渲染为 `%SECRET_NAME%`，由执行时注入的环境变量展开。
+//!   只能用于 `install` 时读取的 `<serviceaccount>`，出现在 `<env>`、`<arguments>` 等处时报错（见 [`crate::secrets`]）；
+//! - 条件块：独占一行的 `<!-- #if cond -->`、`<!-- #else -->`、`<!-- #endif -->`，可嵌套。
+//!   `cond` 为 `name`、`!name`、`name == "value"` 或 `name != "value"`；
+//!   变量未定义、为空或为 `0`/`false`/`no`/`off` 时视为假。
+//!
+//! 覆盖文件（overlay）同样是 `<service>` 模板，渲染后逐个元素覆盖到基础配置上：
+//! `<env>` 按 `name` 合并，其余元素替换基础配置中的全部同名元素。
+//!
+//! 未定义的变量、条件块不匹配与 XML 错误均在写入前报告，存在任何错误时不写入。
+
+use super::catalog::resolve_config;
+use super::config::{
+    misplaced_secret_refs, parse_document, ServiceConfig, XmlDocument, XmlElement,
+};
+use super::history::{self, Revision};
+use super::{preview, WinswError};
+use quick_xml::escape::escape;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+/// 视为假的变量值（不区分大小写）
+const FALSY: &[&str] = &["", "0", "false", "no", "off"];
+
+/// 模板来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    Template,
+    Overlay,
+}
+
+/// 未定义的变量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnresolvedVar {
+    pub name: String,
+    pub source: TemplateSource,
+    /// 所在行（从 1 开始）
+    pub line: usize,
+}
+
+/// 单个模板的渲染结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Rendered {
+    text: String,
+    unresolved: Vec<(String, usize)>,
+    errors: Vec<String>,
+}
+
+/// 渲染请求
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RenderReq {
+    /// 模板文件路径
+    pub template: Option<String>,
+    /// 模板内容，提供时忽略 `template`
+    pub template_xml: Option<String>,
+    /// 变量
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    /// 覆盖文件路径
+    pub overlay: Option<String>,
+    /// 写入目标：服务目录中的服务 ID；与 `output` 都未提供时只返回渲染结果
+    pub service_id: Option<String>,
+    /// 写入目标：配置文件路径
+    pub output: Option<String>,
+}
+
+/// 渲染结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RenderResp {
+    pub ok: bool,
+    /// 最终的 XML，存在错误时为 None
+    pub xml: Option<String>,
+    pub unresolved: Vec<UnresolvedVar>,
+    pub errors: Vec<String>,
+    /// 校验警告（如被包装的程序不存在），不阻止写入
+    pub warnings: Vec<String>,
+    /// 写入后的配置历史版本
+    pub revision: Option<Revision>,
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$?\$\{([^}]*)\}").expect("placeholder regex"))
+}
+
+fn directive_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\s*<!--\s*#(if|else|endif)\b\s*(.*?)\s*-->\s*$").expect("directive regex")
+    })
+}
+
+fn condition_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"^(!)?\s*([A-Za-z_][A-Za-z0-9_.]*)\s*(?:(==|!=)\s*(?:"([^"]*)"|'([^']*)'|(\S+)))?$"#,
+        )
+        .expect("condition regex")
+    })
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn truthy(value: Option<&String>) -> bool {
+    value.is_some_and(|v| !FALSY.iter().any(|f| v.trim().eq_ignore_ascii_case(f)))
+}
+
+/// 求值条件表达式
+fn eval_condition(cond: &str, vars: &BTreeMap<String, String>) -> Result<bool, String> {
+    let caps = condition_regex()
+        .captures(cond)
+        .ok_or_else(|| format!("无效的条件: '{}'", cond))?;
+    let negate = caps.get(1).is_some();
+    let value = vars.get(&caps[2]);
+    let result = match caps.get(3).map(|m| m.as_str()) {
+        None => truthy(value),
+        Some(op) => {
+            let expected = caps
+                .get(4)
+                .or_else(|| caps.get(5))
+                .or_else(|| caps.get(6))
+                .map(|m| m.as_str())
+                .unwrap_or_default();
+            let equal = value.is_some_and(|v| v == expected);
+            if op == "==" {
+                equal
+            } else {
+                !equal
+            }
+        }
+    };
+    if negate && caps.get(3).is_some() {
+        return Err(format!("'!' 不能与比较同时使用: '{}'", cond));
+    }
+    Ok(result != negate)
+}
+
+/// 替换一行中的占位符
+fn substitute(
+    line: &str,
+    line_no: usize,
+    vars: &BTreeMap<String, String>,
+    out: &mut Rendered,
+) -> String {
+    placeholder_regex()
+        .replace_all(line, |caps: &regex::Captures| {
+            let whole = &caps[0];
+            if whole.starts_with("$$") {
+                return whole[1..].to_string();
+            }
+            let inner = caps[1].trim();
+            if let Some(name) = inner.strip_prefix("secret:") {
+                if crate::secrets::validate_name(name).is_err() {
+                    out.errors
+                        .push(format!("第 {} 行: 无效的密钥引用 '{}'", line_no, whole));
+                    return whole.to_string();
+                }
+                return format!("%{}{}%", crate::secrets::CONFIG_ENV_PREFIX, name);
+            }
+            let (name, default) = match inner.split_once(":-") {
+                Some((n, d)) => (n.trim(), Some(d)),
+                None => (inner, None),
+            };
+            if !is_var_name(name) {
+                out.errors
+                    .push(format!("第 {} 行: 无效的占位符 '{}'", line_no, whole));
+                return whole.to_string();
+            }
+            match vars.get(name).map(String::as_str).or(default) {
+                Some(v) => escape(v).to_string(),
+                None => {
+                    out.unresolved.push((name.to_string(), line_no));
+                    whole.to_string()
+                }
+            }
+        })
+        .to_string()
+}
+
+/// 条件块栈中的一层
+struct Frame {
+    /// 外层是否处于生效状态
+    parent_active: bool,
+    cond: bool,
+    in_else: bool,
+    line: usize,
+}
+
+impl Frame {
+    fn active(&self) -> bool {
+        self.parent_active && (self.cond != self.in_else)
+    }
+}
+
+/// 渲染模板文本
+fn render(template: &str, vars: &BTreeMap<String, String>) -> Rendered {
+    let mut out = Rendered::default();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut text = String::with_capacity(template.len());
+
+    for (idx, line) in template.split_inclusive('\n').enumerate() {
+        let line_no = idx + 1;
+        let active = stack.last().map_or(true, Frame::active);
+
+        if let Some(caps) = directive_regex().captures(line.trim_end_matches(['\r', '\n'])) {
+            let arg = caps[2].trim();
+            match &caps[1] {
+                "if" => {
+                    // 未生效的分支中不求值，避免报告无关的错误
+                    let cond = if active {
+                        eval_condition(arg, vars).unwrap_or_else(|e| {
+                            out.errors.push(format!("第 {} 行: {}", line_no, e));
+                            false
+                        })
+                    } else {
+                        false
+                    };
+                    stack.push(Frame {
+                        parent_active: active,
+                        cond,
+                        in_else: false,
+                        line: line_no,
+                    });
+                }
+                "else" => match stack.last_mut() {
+                    Some(frame) if !frame.in_else => frame.in_else = true,
+                    Some(_) => out.errors.push(format!("第 {} 行: 重复的 #else", line_no)),
+                    None => out
+                        .errors
+                        .push(format!("第 {} 行: #else 没有对应的 #if", line_no)),
+                },
+                _ => {
+                    if stack.pop().is_none() {
+                        out.errors
+                            .push(format!("第 {} 行: #endif 没有对应的 #if", line_no));
+                    }
+                }
+            }
+            continue;
+        }
+
+        if active {
+            text.push_str(&substitute(line, line_no, vars, &mut out));
+        }
+    }
+
+    for frame in stack {
+        out.errors
+            .push(format!("第 {} 行: #if 缺少对应的 #endif", frame.line));
+    }
+    out.text = text;
+    out
+}
+
+/// 将覆盖文件合并到基础配置：`<env>` 按 `name` 合并，其余元素整体替换同名元素
+fn apply_overlay(base: &mut XmlElement, overlay: &XmlElement) -> Result<(), WinswError> {
+    if overlay.name != "service" {
+        return Err(WinswError::ConfigInvalid(format!(
+            "覆盖文件的根元素应为 <service>，实际为 <{}>",
+            overlay.name
+        )));
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for el in overlay.elements() {
+        if !names.contains(&el.name.as_str()) {
+            names.push(&el.name);
+        }
+    }
+
+    for name in names {
+        let items: Vec<XmlElement> = overlay.children_named(name).cloned().collect();
+        if name == "env" {
+            let key = |e: &XmlElement| e.attr("name").map(str::to_ascii_lowercase);
+            let mut merged: Vec<XmlElement> = base
+                .children_named("env")
+                .map(|e| {
+                    items
+                        .iter()
+                        .find(|o| key(o).is_some() && key(o) == key(e))
+                        .unwrap_or(e)
+                        .clone()
+                })
+                .collect();
+            for item in &items {
+                if !merged.iter().any(|m| key(m) == key(item)) {
+                    merged.push(item.clone());
+                }
+            }
+            base.replace_elements("env", merged);
+        } else {
+            base.replace_elements(name, items);
+        }
+    }
+    Ok(())
+}
+
+fn read_text(path: &str) -> Result<String, WinswError> {
+    std::fs::read_to_string(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            WinswError::ConfigNotFound(path.to_string())
+        } else {
+            WinswError::ConfigRead(e.to_string())
+        }
+    })
+}
+
+/// 渲染模板与覆盖文件并校验，不写入
+///
+/// `config_path` 用于确定 `%BASE%` 以检查被包装的程序与工作目录。
+pub(crate) fn render_config(
+    template: &str,
+    overlay: Option<&str>,
+    vars: &BTreeMap<String, String>,
+    config_path: &Path,
+) -> RenderResp {
+    let mut resp = RenderResp::default();
+
+    let collect = |rendered: Rendered, source: TemplateSource, resp: &mut RenderResp| {
+        resp.unresolved.extend(
+            rendered
+                .unresolved
+                .into_iter()
+                .map(|(name, line)| UnresolvedVar { name, source, line }),
+        );
+        let prefix = match source {
+            TemplateSource::Template => "模板",
+            TemplateSource::Overlay => "覆盖文件",
+        };
+        resp.errors.extend(
+            rendered
+                .errors
+                .into_iter()
+                .map(|e| format!("{}{}", prefix, e)),
+        );
+        rendered.text
+    };
+
+    let base_text = collect(render(template, vars), TemplateSource::Template, &mut resp);
+    let overlay_text =
+        overlay.map(|o| collect(render(o, vars), TemplateSource::Overlay, &mut resp));
+
+    for u in &resp.unresolved {
+        let prefix = match u.source {
+            TemplateSource::Template => "模板",
+            TemplateSource::Overlay => "覆盖文件",
+        };
+        resp.errors.push(format!(
+            "{}第 {} 行: 变量 '{}' 未定义",
+            prefix, u.line, u.name
+        ));
+    }
+    if !resp.errors.is_empty() {
+        return resp;
+    }
+
+    let xml = match overlay_text {
+        None => base_text,
+        Some(overlay_text) => {
+            let merged = XmlDocument::parse(&base_text).and_then(|mut doc| {
+                apply_overlay(&mut doc.root, &parse_document(&overlay_text)?)?;
+                Ok(doc.to_xml())
+            });
+            match merged {
+                Ok(xml) => xml,
+                Err(e) => {
+                    resp.errors.push(e.to_string());
+                    return resp;
+                }
+            }
+        }
+    };
+
+    match ServiceConfig::parse(&xml, config_path) {
+        Ok(config) => resp.warnings = preview::config_warnings(&config),
+        Err(e) => {
+            resp.errors.push(e.to_string());
+            return resp;
+        }
+    }
+    if let Ok(root) = parse_document(&xml) {
+        resp.errors.extend(misplaced_secret_refs(&root));
+    }
+    if !resp.errors.is_empty() {
+        return resp;
+    }
+
+    resp.ok = true;
+    resp.xml = Some(xml);
+    resp
+}
+
+/// Tauri 命令：渲染配置模板，校验通过且指定了目标时写入（记录配置历史）
+///
+/// ```javascript
+/// const resp = await invoke('winsw_template_render', {
+///   req: {
+///     template: 'C:\\templates\\api.xml',
+///     overlay: 'C:\\templates\\api.prod.xml',
+///     vars: { env: 'prod', port: '8080', log_dir: 'D:\\logs\\api' },
+///     service_id: 'api'
+///   }
+/// });
+/// // resp.unresolved: [{ name, source: 'template' | 'overlay', line }]
+/// ```
+#[tauri::command]
+pub async fn winsw_template_render(app: AppHandle, req: RenderReq) -> Result<RenderResp, String> {
+    let target: Option<PathBuf> = match (req.service_id.as_deref(), req.output.as_deref()) {
+        (None, None) => None,
+        (id, output) => Some(resolve_config(&app, id, output).map_err(|e| e.to_string())?),
+    };
+
+    let template = match (&req.template_xml, &req.template) {
+        (Some(xml), _) => xml.clone(),
+        (None, Some(path)) => read_text(path).map_err(|e| e.to_string())?,
+        (None, None) => return Err("需要提供模板路径 (template) 或模板内容 (template_xml)".into()),
+    };
+    let overlay = req
+        .overlay
+        .as_deref()
+        .map(read_text)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    // 未写入时以模板所在位置确定 %BASE%
+    let base_path = target
+        .clone()
+        .or_else(|| req.template.as_ref().map(PathBuf::from))
+        .unwrap_or_default();
+    let mut resp = render_config(&template, overlay.as_deref(), &req.vars, &base_path);
+
+    if let (true, Some(path), Some(xml)) = (resp.ok, &target, &resp.xml) {
+        match history::write_config(&app, path, xml) {
+            Ok(revision) => resp.revision = Some(revision),
+            Err(e) => {
+                resp.ok = false;
+                resp.errors.push(e.to_string());
+            }
+        }
+    }
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    const TEMPLATE: &str = "<service>
+  <id>api</id>
+  <executable>java</executable>
+  <arguments>${jvm_args:-} -jar api.jar --port=${port}</arguments>
+  <logpath>${log_dir}</logpath>
+  <!-- #if env == \"prod\" -->
+  <onfailure action=\"restart\" delay=\"10 sec\"/>
+  <!-- #else -->
+  <env name=\"DEBUG\" value=\"1\"/>
+  <!-- #if trace -->
+  <env name=\"TRACE\" value=\"${trace_level}\"/>
+  <!-- #endif -->
+  <!-- #endif -->
+  <serviceaccount><password>${secret:DB_PASS}</password></serviceaccount>
+  <description>$${literal}</description>
+</service>
+";
+
+    #[test]
+    fn test_render_conditions_and_placeholders() {
+        let prod = render(
+            TEMPLATE,
+            &vars(&[("env", "prod"), ("port", "8080"), ("log_dir", "D:\\logs")]),
+        );
+        assert!(prod.errors.is_empty(), "{:?}", prod.errors);
+        // trace_level 位于未生效的分支中，不报告
+        assert!(prod.unresolved.is_empty());
+        assert!(prod
+            .text
+            .contains("<arguments> -jar api.jar --port=8080</arguments>"));
+        assert!(prod.text.contains("<onfailure"));
+        assert!(!prod.text.contains("DEBUG"));
+        assert!(prod.text.contains("<password>%SECRET_DB_PASS%</password>"));
+        assert!(prod.text.contains("<description>${literal}</description>"));
+        assert!(!prod.text.contains("#if"));
+
+        let dev = render(
+            TEMPLATE,
+            &vars(&[("env", "dev"), ("trace", "yes"), ("jvm_args", "-Xmx<1g>")]),
+        );
+        assert!(dev.text.contains("name=\"DEBUG\""));
+        assert!(dev.text.contains("-Xmx&lt;1g&gt;"));
+        let missing: Vec<&str> = dev.unresolved.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(missing, vec!["port", "log_dir", "trace_level"]);
+        assert_eq!(dev.unresolved[2].1, 11);
+    }
+
+    #[test]
+    fn test_render_reports_unbalanced_blocks() {
+        let r = render(
+            "<service>\n<!-- #if a -->\n<!-- #else -->\n<!-- #else -->\n</service>\n<!-- #endif -->\n<!-- #endif -->\n<!-- #if x = 1 -->\n",
+            &BTreeMap::new(),
+        );
+        assert_eq!(r.errors.len(), 4, "{:?}", r.errors);
+        assert!(r.errors[0].contains("第 4 行"));
+        assert!(r.errors[1].contains("第 7 行"));
+        // 无效条件与缺少 #endif
+        assert!(r.errors[2].contains("第 8 行"));
+        assert!(r.errors[3].contains("#endif"));
+    }
+
+    #[test]
+    fn test_render_config_with_overlay() {
+        let template = "<service>\n  <id>api</id>\n  <executable>java</executable>\n  <arguments>--port=${port}</arguments>\n  <env name=\"A\" value=\"1\"/>\n  <env name=\"B\" value=\"2\"/>\n</service>\n";
+        let overlay = "<service>\n  <arguments>--port=${port} --prod</arguments>\n  <env name=\"b\" value=\"${b}\"/>\n  <env name=\"C\" value=\"3\"/>\n</service>\n";
+        let path = Path::new("/srv/api/api.xml");
+
+        let resp = render_config(template, Some(overlay), &vars(&[("port", "9000")]), path);
+        assert!(!resp.ok);
+        assert!(resp.xml.is_none());
+        assert_eq!(
+            resp.unresolved,
+            vec![UnresolvedVar {
+                name: "b".into(),
+                source: TemplateSource::Overlay,
+                line: 3,
+            }]
+        );
+
+        let resp = render_config(
+            template,
+            Some(overlay),
+            &vars(&[("port", "9000"), ("b", "two")]),
+            path,
+        );
+        assert!(resp.ok, "{:?}", resp.errors);
+        let root = parse_document(resp.xml.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            root.child_text("arguments").as_deref(),
+            Some("--port=9000 --prod")
+        );
+        let envs: Vec<(String, String)> = root
+            .children_named("env")
+            .map(|e| {
+                (
+                    e.attr("name").unwrap().to_string(),
+                    e.attr("value").unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            envs,
+            vec![
+                ("A".into(), "1".into()),
+                ("b".into(), "two".into()),
+                ("C".into(), "3".into()),
+            ]
+        );
+
+        // 渲染结果不是合法的服务配置
+        let resp = render_config(
+            "<service><name>x</name></service>",
+            None,
+            &BTreeMap::new(),
+            path,
+        );
+        assert!(!resp.ok);
+        assert!(resp.errors[0].contains("<id>"));
+    }
+
+    #[test]
+    fn test_render_config_rejects_runtime_secret_refs() {
+        let path = Path::new("/srv/api/api.xml");
+        let template = "<service>\n  <id>api</id>\n  <executable>api.exe</executable>\n  <arguments>--token ${secret:API_TOKEN}</arguments>\n  <env name=\"DB_PASSWORD\" value=\"${secret:DB_PASS}\"/>\n  <serviceaccount><password>${secret:SVC_PASS}</password></serviceaccount>\n</service>\n";
+        let resp = render_config(template, None, &BTreeMap::new(), path);
+        assert!(!resp.ok);
+        assert_eq!(resp.errors.len(), 2, "{:?}", resp.errors);
+        assert!(resp.errors[0].contains("<arguments>"));
+        assert!(resp.errors[1].contains("%SECRET_DB_PASS%"));
+    }
+}
@@ -0,0 +1,780 @@
+//! 服务看门狗：周期检查目录中服务的状态与健康，失败时按策略自动恢复
+//!
+//! WinSW 自身的 `<onfailure>` 只在进程退出时生效，无法发现“进程仍在但已不响应”的服务。
+//! 看门狗在后台按固定间隔轮询，发现故障后按指数退避重启；窗口内重启次数达到上限即放弃并告警。
+//!
+//! 每次状态转换都会以 [`WATCHDOG_EVENT`] 事件推送给前端，并追加到应用数据目录下的事故日志。
+//! 通过本应用停止或卸载的服务不会被自动拉起；首次巡检时已停止的服务同样视为有意停止。
+
+use super::catalog::{load_catalog, now_secs};
+use super::config::ServiceConfig;
+use super::health::check_once;
+use super::{
+    data_dir, lifecycle, parse_status, perform_action, ActionTarget, ServiceStatus, WinswError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+use tokio::time::{sleep, Duration};
+
+/// 状态转换事件名
+pub const WATCHDOG_EVENT: &str = "winsw://watchdog";
+
+const DEFAULT_INCIDENT_LIMIT: usize = 200;
+/// 事故日志超过该大小时只保留后一半
+const MAX_INCIDENT_LOG_BYTES: u64 = 1024 * 1024;
+
+/// 恢复策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryPolicy {
+    /// 首次重启前的等待时间（秒）
+    pub initial_backoff_seconds: u64,
+    /// 等待时间上限（秒）
+    pub max_backoff_seconds: u64,
+    /// 每次重启后等待时间的倍数
+    pub multiplier: u32,
+    /// 窗口内最多重启次数，达到后放弃
+    pub max_restarts: u32,
+    /// 统计重启次数的窗口（秒）
+    pub window_seconds: u64,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 300,
+            multiplier: 2,
+            max_restarts: 3,
+            window_seconds: 600,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// 第 `attempt` 次（从 0 开始）重启前的等待时间（毫秒）
+    fn backoff_ms(&self, attempt: usize) -> u64 {
+        let factor = u64::from(self.multiplier.max(1)).saturating_pow(attempt as u32);
+        self.initial_backoff_seconds
+            .saturating_mul(factor)
+            .min(self.max_backoff_seconds)
+            .saturating_mul(1000)
+    }
+}
+
+/// 看门狗配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// 巡检间隔（秒）
+    pub interval_seconds: u64,
+    /// 每次状态查询的超时时间（秒）
+    pub timeout_seconds: u64,
+    /// 监控的服务 ID，为空时监控目录中的全部服务
+    pub services: Vec<String>,
+    pub policy: RecoveryPolicy,
+    /// 按服务 ID 覆盖恢复策略
+    pub overrides: BTreeMap<String, RecoveryPolicy>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: 30,
+            timeout_seconds: 30,
+            services: Vec::new(),
+            policy: RecoveryPolicy::default(),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+/// 看门狗眼中的服务状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchState {
+    /// 尚未巡检
+    #[default]
+    Unknown,
+    Healthy,
+    /// 发现故障，等待退避后重启
+    Failing,
+    /// 已执行重启，等待下次巡检确认
+    Recovering,
+    /// 窗口内重启次数达到上限，不再自动恢复
+    GaveUp,
+    /// 有意停止或未安装，不监控
+    Suspended,
+}
+
+/// 一次状态转换（事件与事故日志的内容）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Incident {
+    pub service_id: String,
+    pub timestamp: u64,
+    pub from: WatchState,
+    pub to: WatchState,
+    pub reason: String,
+    /// 窗口内已执行的重启次数
+    pub restarts: u32,
+    /// 是否需要人工介入
+    pub alert: bool,
+}
+
+/// 巡检结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Observation {
+    Healthy,
+    /// 服务已停止
+    Down(String),
+    /// 服务在运行但健康检查失败
+    Unhealthy(String),
+    /// 服务未安装
+    Absent,
+}
+
+/// 一次巡检后需要执行的动作
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Step {
+    transition: Option<(WatchState, WatchState, String)>,
+    restart: bool,
+}
+
+/// 单个服务的恢复状态
+#[derive(Debug, Clone, Default)]
+struct Tracker {
+    state: WatchState,
+    /// 窗口内的重启时间（毫秒）
+    restarts: VecDeque<u64>,
+    /// 计划的下次重启时间（毫秒）
+    next_attempt: Option<u64>,
+}
+
+impl Tracker {
+    fn set(&mut self, to: WatchState, reason: String, step: &mut Step) {
+        if self.state != to {
+            step.transition = Some((self.state, to, reason));
+            self.state = to;
+        }
+    }
+
+    fn observe(&mut self, obs: Observation, now_ms: u64, policy: &RecoveryPolicy) -> Step {
+        let mut step = Step::default();
+        let window_ms = policy.window_seconds.saturating_mul(1000);
+        while self
+            .restarts
+            .front()
+            .is_some_and(|t| now_ms.saturating_sub(*t) > window_ms)
+        {
+            self.restarts.pop_front();
+        }
+
+        let reason = match obs {
+            Observation::Healthy => {
+                self.next_attempt = None;
+                let reason = if self.state == WatchState::Unknown {
+                    "服务运行正常"
+                } else {
+                    "服务已恢复"
+                };
+                self.set(WatchState::Healthy, reason.to_string(), &mut step);
+                return step;
+            }
+            Observation::Absent => {
+                self.next_attempt = None;
+                self.set(WatchState::Suspended, "服务未安装".to_string(), &mut step);
+                return step;
+            }
+            // 从未见到运行的服务视为有意停止
+            Observation::Down(reason)
+                if matches!(self.state, WatchState::Unknown | WatchState::Suspended) =>
+            {
+                self.set(WatchState::Suspended, reason, &mut step);
+                return step;
+            }
+            Observation::Down(reason) | Observation::Unhealthy(reason) => reason,
+        };
+
+        if self.state == WatchState::GaveUp {
+            return step;
+        }
+        let restarts = self.restarts.len();
+        if restarts as u32 >= policy.max_restarts {
+            self.next_attempt = None;
+            self.set(
+                WatchState::GaveUp,
+                format!(
+                    "{}；{} 秒内已重启 {} 次，停止自动恢复",
+                    reason, policy.window_seconds, restarts
+                ),
+                &mut step,
+            );
+            return step;
+        }
+
+        match self.next_attempt {
+            None => {
+                let delay = policy.backoff_ms(restarts);
+                self.next_attempt = Some(now_ms + delay);
+                self.set(
+                    WatchState::Failing,
+                    format!("{}；{} 秒后重启", reason, delay / 1000),
+                    &mut step,
+                );
+            }
+            Some(at) if now_ms >= at => {
+                self.next_attempt = None;
+                self.restarts.push_back(now_ms);
+                step.restart = true;
+                self.set(
+                    WatchState::Recovering,
+                    format!("{}；第 {} 次重启", reason, restarts + 1),
+                    &mut step,
+                );
+            }
+            Some(_) => {}
+        }
+        step
+    }
+
+    /// 记录通过本应用执行的操作；返回状态转换
+    fn note_action(&mut self, action: &str) -> Step {
+        let mut step = Step::default();
+        match action {
+            "stop" | "uninstall" => {
+                self.next_attempt = None;
+                self.set(
+                    WatchState::Suspended,
+                    format!("通过本应用执行了 {}", action),
+                    &mut step,
+                );
+            }
+            // 看门狗自己的重启不改变恢复进度
+            "start" | "restart" | "restart!"
+                if matches!(self.state, WatchState::Suspended | WatchState::GaveUp) =>
+            {
+                self.next_attempt = None;
+                self.restarts.clear();
+                self.set(
+                    WatchState::Unknown,
+                    format!("通过本应用执行了 {}", action),
+                    &mut step,
+                );
+            }
+            _ => {}
+        }
+        step
+    }
+}
+
+/// 单个服务的监控状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceWatch {
+    pub service_id: String,
+    pub state: WatchState,
+    pub restarts: u32,
+    /// 计划的下次重启时间（秒）
+    pub next_attempt_at: Option<u64>,
+}
+
+/// 看门狗状态
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogStatus {
+    pub config: WatchdogConfig,
+    pub running: bool,
+    pub services: Vec<ServiceWatch>,
+}
+
+fn trackers() -> &'static Mutex<HashMap<String, Tracker>> {
+    static TRACKERS: OnceLock<Mutex<HashMap<String, Tracker>>> = OnceLock::new();
+    TRACKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 正在运行的巡检任务的取消通道
+static LOOP: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn watchdog_dir(app: &AppHandle) -> Result<PathBuf, WinswError> {
+    Ok(data_dir(app)?.join("watchdog"))
+}
+
+fn io_err(e: impl std::fmt::Display) -> WinswError {
+    WinswError::FileIo(e.to_string())
+}
+
+/// 读取配置，文件不存在时使用默认值
+pub fn load_config(app: &AppHandle) -> Result<WatchdogConfig, WinswError> {
+    let path = watchdog_dir(app)?.join("config.json");
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(io_err),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(WatchdogConfig::default()),
+        Err(e) => Err(io_err(e)),
+    }
+}
+
+fn save_config(app: &AppHandle, config: &WatchdogConfig) -> Result<(), WinswError> {
+    let dir = watchdog_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(io_err)?;
+    let text = serde_json::to_string_pretty(config).map_err(io_err)?;
+    let tmp = dir.join("config.json.tmp");
+    std::fs::write(&tmp, text).map_err(io_err)?;
+    std::fs::rename(&tmp, dir.join("config.json")).map_err(io_err)
+}
+
+fn validate_config(config: &WatchdogConfig) -> Result<(), WinswError> {
+    if config.interval_seconds == 0 || config.timeout_seconds == 0 {
+        return Err(WinswError::InvalidOption(
+            "interval_seconds 与 timeout_seconds 必须大于 0".into(),
+        ));
+    }
+    for policy in std::iter::once(&config.policy).chain(config.overrides.values()) {
+        if policy.window_seconds == 0 || policy.max_backoff_seconds < policy.initial_backoff_seconds
+        {
+            return Err(WinswError::InvalidOption(
+                "window_seconds 必须大于 0，且 max_backoff_seconds 不能小于 initial_backoff_seconds"
+                    .into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 追加事故日志，过大时只保留后一半
+fn append_incident(app: &AppHandle, incident: &Incident) -> Result<(), WinswError> {
+    let dir = watchdog_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(io_err)?;
+    let path = dir.join("incidents.jsonl");
+
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() > MAX_INCIDENT_LOG_BYTES) {
+        let text = std::fs::read_to_string(&path).map_err(io_err)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let kept = lines[lines.len() / 2..].join("\n") + "\n";
+        std::fs::write(&path, kept).map_err(io_err)?;
+    }
+
+    let line = serde_json::to_string(incident).map_err(io_err)?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| writeln!(f, "{}", line))
+        .map_err(io_err)
+}
+
+/// 读取最近的事故，按时间先后排列
+pub fn read_incidents(
+    app: &AppHandle,
+    service_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<Incident>, WinswError> {
+    let path = watchdog_dir(app)?.join("incidents.jsonl");
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_err(e)),
+    };
+    let mut incidents: Vec<Incident> = text
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Incident>(l).ok())
+        .filter(|i| service_id.map_or(true, |id| i.service_id == id))
+        .collect();
+    let skip = incidents.len().saturating_sub(limit);
+    incidents.drain(..skip);
+    Ok(incidents)
+}
+
+/// 推送状态转换并写入事故日志
+fn publish(app: &AppHandle, service_id: &str, tracker: &Tracker, step: Step) {
+    let Some((from, to, reason)) = step.transition else {
+        return;
+    };
+    let incident = Incident {
+        service_id: service_id.to_string(),
+        timestamp: now_secs(),
+        from,
+        to,
+        reason,
+        restarts: tracker.restarts.len() as u32,
+        alert: to == WatchState::GaveUp,
+    };
+    if incident.alert {
+        log::error!("看门狗放弃恢复服务 {}: {}", service_id, incident.reason);
+    } else {
+        log::info!(
+            "看门狗: 服务 {} {:?} -> {:?} ({})",
+            service_id,
+            from,
+            to,
+            incident.reason
+        );
+    }
+    if let Err(e) = append_incident(app, &incident) {
+        log::warn!("写入看门狗事故日志失败: {}", e);
+    }
+    let _ = app.emit(WATCHDOG_EVENT, incident);
+}
+
+/// 记录通过本应用对目录中服务执行的操作（由 [`perform_action`] 调用）
+pub(crate) fn note_action(app: &AppHandle, service_id: &str, action: &str, ok: bool) {
+    if !ok {
+        return;
+    }
+    let (tracker, step) = {
+        let mut all = trackers().lock().expect("watchdog lock");
+        let tracker = all.entry(service_id.to_string()).or_default();
+        let step = tracker.note_action(action);
+        (tracker.clone(), step)
+    };
+    publish(app, service_id, &tracker, step);
+}
+
+/// 查询服务状态与健康；查询本身失败时返回 None（不视为服务故障）
+async fn observe_service(
+    app: &AppHandle,
+    target: &ActionTarget,
+    timeout_secs: u64,
+) -> Option<Observation> {
+    let resp = match perform_action(app, "status", target, timeout_secs, None).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            log::warn!("看门狗查询服务状态失败: {}", e);
+            return None;
+        }
+    };
+    match parse_status(resp.exec.stdout.as_deref().unwrap_or("")) {
+        ServiceStatus::NonExistent => Some(Observation::Absent),
+        ServiceStatus::Stopped => Some(Observation::Down("服务已停止".to_string())),
+        ServiceStatus::Pending | ServiceStatus::Unknown => None,
+        ServiceStatus::Running => match &target.health {
+            Some(spec) if !spec.checks.is_empty() => {
+                let cfg = target
+                    .config
+                    .as_deref()
+                    .and_then(|c| ServiceConfig::load(c).ok());
+                let report = check_once(spec, cfg.as_ref()).await;
+                Some(if report.healthy {
+                    Observation::Healthy
+                } else {
+                    Observation::Unhealthy(format!("健康检查失败: {}", report.failure_summary()))
+                })
+            }
+            _ => Some(Observation::Healthy),
+        },
+    }
+}
+
+/// 巡检一轮
+async fn tick(app: &AppHandle, config: &WatchdogConfig) {
+    let catalog = match load_catalog(app) {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("看门狗读取服务目录失败: {}", e);
+            return;
+        }
+    };
+    let targets: Vec<ActionTarget> = catalog
+        .list()
+        .iter()
+        .filter(|e| config.services.is_empty() || config.services.contains(&e.id))
+        .filter_map(|entry| {
+            ActionTarget::from_entry(app, entry)
+                .map_err(|e| log::warn!("看门狗无法巡检服务 {}: {}", entry.id, e))
+                .ok()
+        })
+        .collect();
+
+    // 不再监控的服务不保留状态
+    trackers().lock().expect("watchdog lock").retain(|id, _| {
+        targets
+            .iter()
+            .any(|t| t.service_id.as_deref() == Some(id.as_str()))
+    });
+
+    for target in targets {
+        let Some(id) = target.service_id.clone() else {
+            continue;
+        };
+        // 操作进行中的中间状态不作为故障依据
+        if lifecycle::is_busy(&id) {
+            continue;
+        }
+        let Some(obs) = observe_service(app, &target, config.timeout_seconds).await else {
+            continue;
+        };
+        let policy = config.overrides.get(&id).unwrap_or(&config.policy);
+        let (tracker, step) = {
+            let mut all = trackers().lock().expect("watchdog lock");
+            let tracker = all.entry(id.clone()).or_default();
+            let step = tracker.observe(obs, now_ms(), policy);
+            (tracker.clone(), step)
+        };
+        let restart = step.restart;
+        publish(app, &id, &tracker, step);
+
+        if restart {
+            match perform_action(app, "restart", &target, config.timeout_seconds, None).await {
+                Ok(resp) if resp.exec.ok => {}
+                Ok(resp) => log::warn!(
+                    "看门狗重启服务 {} 失败: {}",
+                    id,
+                    resp.exec.error.or(resp.exec.stderr).unwrap_or_default()
+                ),
+                Err(e) => log::warn!("看门狗重启服务 {} 失败: {}", id, e),
+            }
+        }
+    }
+}
+
+fn stop_loop() -> bool {
+    let sender = LOOP.lock().expect("watchdog loop lock").take();
+    sender.map(|tx| tx.send(()).is_ok()).unwrap_or(false)
+}
+
+fn start_loop(app: &AppHandle, config: WatchdogConfig) {
+    stop_loop();
+    let (tx, mut rx) = oneshot::channel();
+    *LOOP.lock().expect("watchdog loop lock") = Some(tx);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tick(&app, &config).await;
+            tokio::select! {
+                _ = &mut rx => break,
+                _ = sleep(Duration::from_secs(config.interval_seconds)) => {}
+            }
+        }
+    });
+}
+
+/// 看门狗是否正在巡检该服务
+pub(crate) fn is_watching(service_id: &str) -> bool {
+    is_running()
+        && trackers()
+            .lock()
+            .expect("watchdog lock")
+            .contains_key(service_id)
+}
+
+fn is_running() -> bool {
+    LOOP.lock()
+        .expect("watchdog loop lock")
+        .as_ref()
+        .is_some_and(|tx| !tx.is_closed())
+}
+
+fn status(config: WatchdogConfig) -> WatchdogStatus {
+    let mut services: Vec<ServiceWatch> = trackers()
+        .lock()
+        .expect("watchdog lock")
+        .iter()
+        .map(|(id, t)| ServiceWatch {
+            service_id: id.clone(),
+            state: t.state,
+            restarts: t.restarts.len() as u32,
+            next_attempt_at: t.next_attempt.map(|ms| ms / 1000),
+        })
+        .collect();
+    services.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+    WatchdogStatus {
+        config,
+        running: is_running(),
+        services,
+    }
+}
+
+/// 应用启动时按保存的配置启动看门狗
+pub fn init(app: &AppHandle) {
+    match load_config(app) {
+        Ok(config) if config.enabled => start_loop(app, config),
+        Ok(_) => {}
+        Err(e) => log::warn!("读取看门狗配置失败: {}", e),
+    }
+}
+
+/// Tauri 命令：读取看门狗配置与各服务的监控状态
+#[tauri::command]
+pub async fn winsw_watchdog_get(app: AppHandle) -> Result<WatchdogStatus, String> {
+    load_config(&app).map(status).map_err(|e| e.to_string())
+}
+
+/// Tauri 命令：保存看门狗配置，并按 `enabled` 启动或停止巡检
+///
+/// ```javascript
+/// await invoke('winsw_watchdog_set', {
+///   config: {
+///     enabled: true,
+///     interval_seconds: 30,
+///     services: ['api', 'worker'],
+///     policy: { initial_backoff_seconds: 10, max_backoff_seconds: 300, multiplier: 2, max_restarts: 3, window_seconds: 600 }
+///   }
+/// });
+/// await listen('winsw://watchdog', ({ payload }) => {
+///   // payload: { service_id, timestamp, from, to, reason, restarts, alert }
+/// });
+/// ```
+#[tauri::command]
+pub async fn winsw_watchdog_set(
+    app: AppHandle,
+    config: WatchdogConfig,
+) -> Result<WatchdogStatus, String> {
+    validate_config(&config).map_err(|e| e.to_string())?;
+    save_config(&app, &config).map_err(|e| e.to_string())?;
+    if config.enabled {
+        start_loop(&app, config.clone());
+    } else {
+        stop_loop();
+    }
+    Ok(status(config))
+}
+
+/// Tauri 命令：读取最近的事故日志（默认 200 条）
+#[tauri::command]
+pub async fn winsw_watchdog_incidents(
+    app: AppHandle,
+    service_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<Incident>, String> {
+    read_incidents(
+        &app,
+        service_id.as_deref(),
+        limit.unwrap_or(DEFAULT_INCIDENT_LIMIT),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_state(step: &Step) -> Option<WatchState> {
+        step.transition.as_ref().map(|(_, to, _)| *to)
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RecoveryPolicy::default();
+        assert_eq!(policy.backoff_ms(0), 10_000);
+        assert_eq!(policy.backoff_ms(1), 20_000);
+        assert_eq!(policy.backoff_ms(4), 160_000);
+        assert_eq!(policy.backoff_ms(10), 300_000);
+    }
+
+    #[test]
+    fn test_recovery_with_backoff_and_give_up() {
+        let policy = RecoveryPolicy {
+            max_restarts: 2,
+            ..Default::default()
+        };
+        let mut t = Tracker::default();
+        let down = || Observation::Down("服务已停止".into());
+
+        // 首次巡检已停止：视为有意停止
+        assert_eq!(
+            to_state(&t.observe(down(), 0, &policy)),
+            Some(WatchState::Suspended)
+        );
+        let step = t.note_action("start");
+        assert_eq!(to_state(&step), Some(WatchState::Unknown));
+        assert_eq!(
+            to_state(&t.observe(Observation::Healthy, 1_000, &policy)),
+            Some(WatchState::Healthy)
+        );
+
+        // 故障：等待 10 秒后第一次重启
+        let step = t.observe(Observation::Unhealthy("hung".into()), 2_000, &policy);
+        assert_eq!(to_state(&step), Some(WatchState::Failing));
+        assert!(!step.restart);
+        assert_eq!(t.observe(down(), 5_000, &policy), Step::default());
+        let step = t.observe(down(), 12_000, &policy);
+        assert!(step.restart);
+        assert_eq!(to_state(&step), Some(WatchState::Recovering));
+
+        // 仍失败：下一次等待 20 秒
+        let step = t.observe(down(), 13_000, &policy);
+        assert_eq!(to_state(&step), Some(WatchState::Failing));
+        assert_eq!(t.next_attempt, Some(33_000));
+        assert!(t.observe(down(), 33_000, &policy).restart);
+
+        // 窗口内已重启 2 次：放弃，之后不再动作
+        let step = t.observe(down(), 34_000, &policy);
+        assert_eq!(to_state(&step), Some(WatchState::GaveUp));
+        assert_eq!(t.observe(down(), 90_000, &policy), Step::default());
+
+        // 人工恢复后重新监控
+        assert_eq!(
+            to_state(&t.observe(Observation::Healthy, 95_000, &policy)),
+            Some(WatchState::Healthy)
+        );
+    }
+
+    #[test]
+    fn test_window_expiry_and_manual_stop() {
+        let policy = RecoveryPolicy {
+            max_restarts: 1,
+            window_seconds: 60,
+            ..Default::default()
+        };
+        let mut t = Tracker {
+            state: WatchState::Healthy,
+            ..Default::default()
+        };
+        let down = || Observation::Down("服务已停止".into());
+        t.observe(down(), 0, &policy);
+        assert!(t.observe(down(), 10_000, &policy).restart);
+        t.observe(Observation::Healthy, 20_000, &policy);
+
+        // 窗口过后重启次数清零，可再次重启
+        t.observe(down(), 80_000, &policy);
+        assert_eq!(t.state, WatchState::Failing);
+        assert!(t.restarts.is_empty());
+
+        // 通过本应用停止：不再恢复
+        assert_eq!(
+            to_state(&t.note_action("stop")),
+            Some(WatchState::Suspended)
+        );
+        assert_eq!(t.next_attempt, None);
+        assert_eq!(to_state(&t.observe(down(), 200_000, &policy)), None);
+        // 看门狗自己的重启不影响恢复中的状态
+        let mut recovering = Tracker {
+            state: WatchState::Recovering,
+            ..Default::default()
+        };
+        assert_eq!(recovering.note_action("restart"), Step::default());
+    }
+
+    #[test]
+    fn test_stop_command_suspends() {
+        use crate::winsw::command::WinswCommand;
+
+        let command: WinswCommand = serde_json::from_str(r#"{"command":"stop"}"#).unwrap();
+        let mut t = Tracker {
+            state: WatchState::Healthy,
+            ..Default::default()
+        };
+        let action = command.service_action().unwrap();
+        assert_eq!(
+            to_state(&t.note_action(action)),
+            Some(WatchState::Suspended)
+        );
+
+        // 只读命令不影响看门狗
+        let list: WinswCommand = serde_json::from_str(r#"{"command":"dev_list"}"#).unwrap();
+        assert_eq!(list.service_action(), None);
+    }
+}
@@ -12,5 +12,5 @@ async fn integration_detect_and_dry_runs() {
     assert!(resp2.ok);
     assert!(resp2.stdout.unwrap().contains("scoop uninstall"));
 
-    let _ = ensure_scoop_installed(BootstrapOptions { dry_run: Some(true), timeout_seconds: Some(1) }).await.unwrap();
+    let _ = ensure_scoop_installed(BootstrapOptions { dry_run: Some(true), timeout_seconds: Some(1), ..Default::default() }).await.unwrap();
 }
\ No newline at end of file